@@ -0,0 +1,122 @@
+//! Compare the packet hot path of the [`PacketReader`] and [`PacketWriter`],
+//! which reuse their buffers, against the per-packet [`Packet`] reading and writing,
+//! on a steady-state encrypted and uncompressed connection,
+//! and the encoding of messages into growing, pre-sized and reused buffers.
+
+#![allow(clippy::unwrap_used)]
+
+use binrw::BinWrite;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand_core::OsRng;
+use ssh_packet::{connect, Packet, PacketReader, PacketWriter, Seq, TryIntoPacket};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::Xor;
+
+const PACKETS: usize = 256;
+
+fn message(size: usize) -> connect::ChannelData<'static> {
+    connect::ChannelData {
+        recipient_channel: 0,
+        data: vec![0x42; size].into(),
+    }
+}
+
+fn send(c: &mut Criterion) {
+    let mut group = c.benchmark_group("send");
+    group.throughput(Throughput::Elements(PACKETS as u64));
+
+    for size in [64, 1024, 32768] {
+        let message = message(size);
+
+        group.bench_with_input(BenchmarkId::new("packet", size), &message, |b, message| {
+            b.iter(|| {
+                let (mut writer, mut seq) = (std::io::sink(), Seq::new());
+                for _ in 0..PACKETS {
+                    let packet = message.try_into_packet().unwrap();
+                    packet
+                        .to_blocking_writer(&mut writer, &mut Xor, &mut seq, &mut OsRng)
+                        .unwrap();
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("writer", size), &message, |b, message| {
+            b.iter(|| {
+                let mut writer = PacketWriter::new(std::io::sink(), Xor, OsRng);
+                for _ in 0..PACKETS {
+                    writer.blocking_send(message).unwrap();
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn recv(c: &mut Criterion) {
+    let mut group = c.benchmark_group("recv");
+    group.throughput(Throughput::Elements(PACKETS as u64));
+
+    for size in [64, 1024, 32768] {
+        let message = message(size);
+
+        let mut writer = PacketWriter::new(Vec::new(), Xor, OsRng);
+        for _ in 0..PACKETS {
+            writer.blocking_send(&message).unwrap();
+        }
+        let (wire, _) = writer.into_inner();
+
+        group.bench_with_input(BenchmarkId::new("packet", size), &wire, |b, wire| {
+            b.iter(|| {
+                let (mut reader, mut seq) = (&wire[..], Seq::new());
+                for _ in 0..PACKETS {
+                    black_box(
+                        Packet::from_blocking_reader(&mut reader, &mut Xor, &mut seq).unwrap(),
+                    );
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("reader", size), &wire, |b, wire| {
+            b.iter(|| {
+                let mut reader = PacketReader::new(&wire[..], Xor);
+                for _ in 0..PACKETS {
+                    black_box(reader.blocking_recv_packet().unwrap());
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+
+    for size in [64, 1024, 32768] {
+        let message = message(size);
+
+        group.bench_with_input(BenchmarkId::new("growing", size), &message, |b, message| {
+            b.iter(|| {
+                let mut buffer = std::io::Cursor::new(Vec::new());
+                message.write(&mut buffer).unwrap();
+
+                black_box(buffer.into_inner())
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("hinted", size), &message, |b, message| {
+            b.iter(|| black_box(message.try_into_packet().unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("reused", size), &message, |b, message| {
+            let mut buf = Vec::new();
+            b.iter(|| {
+                black_box(Packet::encode_into(message, &mut buf).unwrap());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, send, recv, encode);
+criterion_main!(benches);
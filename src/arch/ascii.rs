@@ -2,7 +2,11 @@ use std::ops::Deref;
 
 use binrw::binrw;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
 use super::Bytes;
+use crate::IntoOwned;
 
 /// Create an [`Ascii`] string from a literal in _const_-context.
 #[doc(hidden)]
@@ -22,6 +26,7 @@ pub use __ascii__ as ascii;
 
 /// Errors which can occur when attempting to interpret a string as a ASCII characters.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct AsciiError {}
 
 impl std::fmt::Display for AsciiError {
@@ -36,9 +41,10 @@ impl std::error::Error for AsciiError {}
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4251#section-5>.
 #[binrw]
-#[derive(Default, Clone, PartialEq, Eq)]
-#[br(assert(self_0.as_borrow().is_ascii()))]
-pub struct Ascii<'b>(Bytes<'b>);
+#[derive(Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "zeroize", derive(Zeroize))]
+#[br(import_raw(options: crate::DecodeOptions), assert(self_0.as_borrow().is_ascii()))]
+pub struct Ascii<'b>(#[br(args_raw(options))] Bytes<'b>);
 
 impl<'b> Ascii<'b> {
     /// Create an [`Ascii`] string from a [`String`].
@@ -81,6 +87,14 @@ impl<'b> Ascii<'b> {
     }
 }
 
+impl IntoOwned for Ascii<'_> {
+    type Owned = Ascii<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Ascii(self.0.into_owned())
+    }
+}
+
 impl std::fmt::Debug for Ascii<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("Ascii").field(&&**self).finish()
@@ -101,6 +115,61 @@ impl Deref for Ascii<'_> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Ascii<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Ascii<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        Self::owned(value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Ascii<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", &**self)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Encode for Ascii<'_> {
+    fn encoded_len(&self) -> Result<usize, ssh_encoding::Error> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl ssh_encoding::Writer) -> Result<(), ssh_encoding::Error> {
+        self.0.encode(writer)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Decode for Ascii<'_> {
+    type Error = ssh_encoding::Error;
+
+    fn decode(reader: &mut impl ssh_encoding::Reader) -> Result<Self, Self::Error> {
+        Self::owned(String::decode(reader)?).map_err(|_| ssh_encoding::Error::CharacterEncoding)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Ascii<'_> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let len = u.arbitrary_len::<u8>()?;
+        let value = (0..len)
+            .map(|_| u.int_in_range(0..=0x7f).map(char::from))
+            .collect::<arbitrary::Result<String>>()?;
+
+        Ok(Self(Bytes::owned(value.into_bytes())))
+    }
+}
+
 impl AsRef<str> for Ascii<'_> {
     fn as_ref(&self) -> &str {
         self
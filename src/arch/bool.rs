@@ -1,10 +1,15 @@
 use binrw::binrw;
 
+use crate::IntoOwned;
+
 /// A `boolean` as defined in the SSH protocol.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4251#section-5>.
 #[binrw]
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big)]
 pub struct Bool(
     #[br(map = |n: u8| n > 0)]
@@ -12,6 +17,14 @@ pub struct Bool(
     pub bool,
 );
 
+impl IntoOwned for Bool {
+    type Owned = Self;
+
+    fn into_owned(self) -> Self::Owned {
+        self
+    }
+}
+
 impl std::ops::Not for Bool {
     type Output = Self;
 
@@ -1,7 +1,12 @@
-use std::ops::Deref;
+use std::{
+    io::Read,
+    ops::{Deref, RangeBounds},
+};
 
 use binrw::{BinRead, BinWrite};
 
+use crate::IntoOwned;
+
 #[cfg(feature = "zeroize")]
 use zeroize::Zeroize;
 
@@ -12,6 +17,10 @@ enum Inner<'b> {
 
     #[cfg_attr(feature = "zeroize", zeroize(skip))]
     Borrowed(&'b [u8]),
+
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(feature = "zeroize", zeroize(skip))]
+    Shared(bytes::Bytes),
 }
 
 /// A `string` as defined in the SSH protocol.
@@ -51,11 +60,67 @@ impl<'b> Bytes<'b> {
         Bytes::borrowed(self)
     }
 
+    /// Obtain a view of the `range` of the buffer, without copying it.
+    ///
+    /// The view borrows the buffer, or shares it when created from a [`bytes::Bytes`] with the `bytes` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `range` is out of bounds, as when indexing a slice.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Bytes<'_> {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+
+        match self.inner {
+            #[cfg(feature = "bytes")]
+            Inner::Shared(ref shared) => Bytes {
+                inner: Inner::Shared(shared.slice(range)),
+            },
+            _ => Bytes::borrowed(&self[range]),
+        }
+    }
+
+    /// Divide the buffer in two views at the `mid` index, without copying it, see [`Bytes::slice`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`.
+    pub fn split_at(&self, mid: usize) -> (Bytes<'_>, Bytes<'_>) {
+        (self.slice(..mid), self.slice(mid..))
+    }
+
     /// Extract the buffer into a [`Vec`].
     pub fn into_vec(self) -> Vec<u8> {
         match self.inner {
             Inner::Owned(vec) => vec,
             Inner::Borrowed(slice) => slice.to_vec(),
+            #[cfg(feature = "bytes")]
+            Inner::Shared(shared) => shared.into(),
+        }
+    }
+
+    /// Reclaim the allocation of owned [`Bytes`] as an empty [`Vec`] to be reused,
+    /// borrowed [`Bytes`] yield a new unallocated [`Vec`].
+    pub fn recycle(self) -> Vec<u8> {
+        match self.inner {
+            Inner::Owned(mut vec) => {
+                vec.clear();
+                vec
+            }
+            Inner::Borrowed(_) => Vec::new(),
+            #[cfg(feature = "bytes")]
+            Inner::Shared(_) => Vec::new(),
+        }
+    }
+}
+
+impl IntoOwned for Bytes<'_> {
+    type Owned = Bytes<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self.inner {
+            #[cfg(feature = "bytes")]
+            Inner::Shared(shared) => shared.into(),
+            _ => Bytes::owned(self.into_vec()),
         }
     }
 }
@@ -67,6 +132,8 @@ impl Deref for Bytes<'_> {
         match self.inner {
             Inner::Owned(ref vec) => vec,
             Inner::Borrowed(slice) => slice,
+            #[cfg(feature = "bytes")]
+            Inner::Shared(ref shared) => shared,
         }
     }
 }
@@ -85,37 +152,79 @@ impl PartialEq for Bytes<'_> {
 
 impl Eq for Bytes<'_> {}
 
+impl std::hash::Hash for Bytes<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
 impl From<Vec<u8>> for Bytes<'_> {
     fn from(value: Vec<u8>) -> Self {
         Self::owned(value)
     }
 }
 
+#[cfg(feature = "bytes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+impl From<bytes::Bytes> for Bytes<'_> {
+    fn from(value: bytes::Bytes) -> Self {
+        Self {
+            inner: Inner::Shared(value),
+        }
+    }
+}
+
 impl<'b> From<&'b [u8]> for Bytes<'b> {
     fn from(value: &'b [u8]) -> Self {
         Self::borrowed(value)
     }
 }
 
+impl From<String> for Bytes<'_> {
+    fn from(value: String) -> Self {
+        Self::owned(value.into_bytes())
+    }
+}
+
+impl<'b> From<&'b str> for Bytes<'b> {
+    fn from(value: &'b str) -> Self {
+        Self::borrowed(value.as_bytes())
+    }
+}
+
+/// The `string` is read bounded by the [`DecodeOptions::max_string_size`](crate::DecodeOptions::max_string_size),
+/// and always copied into owned [`Bytes`], since `binrw` readers can't be borrowed from.
 impl BinRead for Bytes<'_> {
-    type Args<'a> = ();
+    type Args<'a> = crate::DecodeOptions;
 
     fn read_options<R: std::io::Read + std::io::Seek>(
         reader: &mut R,
-        endian: binrw::Endian,
-        args: Self::Args<'_>,
+        _endian: binrw::Endian,
+        options: Self::Args<'_>,
     ) -> binrw::BinResult<Self> {
-        let size = u32::read_be(reader)?;
-
-        BinRead::read_options(
-            reader,
-            endian,
-            binrw::VecArgs {
-                count: size as usize,
-                inner: args,
-            },
-        )
-        .map(Self::owned)
+        let pos = reader.stream_position()?;
+        let size = u32::read_be(reader)? as usize;
+
+        let max_size = options.max_string_size;
+        if size > max_size {
+            return Err(binrw::Error::AssertFail {
+                pos,
+                message: format!(
+                    "The string's size of {size} bytes exceeds the maximum of {max_size}"
+                ),
+            });
+        }
+
+        // Bound the up-front reservation so that a forged size cannot
+        // trigger a huge allocation before any data has been received.
+        let mut buf = Vec::with_capacity(size.min(crate::PACKET_MAX_SIZE));
+        let read = reader.take(size as u64).read_to_end(&mut buf)?;
+
+        if read != size {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(Self::owned(buf))
     }
 }
 
@@ -125,13 +234,197 @@ impl BinWrite for Bytes<'_> {
     fn write_options<W: std::io::Write + std::io::Seek>(
         &self,
         writer: &mut W,
-        endian: binrw::Endian,
-        args: Self::Args<'_>,
+        _endian: binrw::Endian,
+        _args: Self::Args<'_>,
     ) -> binrw::BinResult<()> {
         let buf = &**self;
         let size = buf.len() as u32;
 
         size.write_be(writer)?;
-        buf.write_options(writer, endian, args)
+
+        // Write the buffer at once, since `binrw` writes slices element by element
+        writer.write_all(buf)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Bytes<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use base64::Engine;
+
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(&**self))
+        } else {
+            serializer.serialize_bytes(self)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Bytes<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a base64-encoded string or a byte array")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                use base64::Engine;
+
+                base64::engine::general_purpose::STANDARD
+                    .decode(v)
+                    .map_err(E::custom)
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut buf = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(byte) = seq.next_element()? {
+                    buf.push(byte);
+                }
+
+                Ok(buf)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(Visitor).map(Self::owned)
+        } else {
+            deserializer.deserialize_byte_buf(Visitor).map(Self::owned)
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Bytes<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=[u8]:x}", &**self)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ssh-encoding")))]
+impl Bytes<'_> {
+    /// Decode the [`Bytes`] as a `T` with it's [`ssh_encoding::Decode`] implementation,
+    /// such as a public key or signature blob, ensuring no data is left over.
+    pub fn decode_as<T: ssh_encoding::Decode>(&self) -> Result<T, T::Error> {
+        use ssh_encoding::Reader;
+
+        let mut reader = &**self;
+        let value = T::decode(&mut reader)?;
+
+        Ok(reader.finish(value)?)
+    }
+
+    /// Encode the `value` into [`Bytes`] with it's [`ssh_encoding::Encode`] implementation,
+    /// such as a public key or signature blob.
+    pub fn encode_from(value: &impl ssh_encoding::Encode) -> Result<Self, ssh_encoding::Error> {
+        let mut buf = Vec::with_capacity(value.encoded_len()?);
+        value.encode(&mut buf)?;
+
+        Ok(Self::owned(buf))
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Encode for Bytes<'_> {
+    fn encoded_len(&self) -> Result<usize, ssh_encoding::Error> {
+        (**self).encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl ssh_encoding::Writer) -> Result<(), ssh_encoding::Error> {
+        (**self).encode(writer)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Decode for Bytes<'_> {
+    type Error = ssh_encoding::Error;
+
+    fn decode(reader: &mut impl ssh_encoding::Reader) -> Result<Self, Self::Error> {
+        Vec::decode(reader).map(Self::owned)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Bytes<'_> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Vec::arbitrary(u).map(Self::owned)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        Vec::<u8>::size_hint(depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use binrw::BinReaderExt;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(&[0, 0, 0, 3, b'a', b'b', b'c'], Some(&b"abc"[..]))]
+    #[case(&[0, 0, 0, 0], Some(&b""[..]))]
+    #[case(&[0, 0, 0, 4, b'a', b'b', b'c'], None)]
+    #[case(&[0xff, 0xff, 0xff, 0xff, b'a'], None)]
+    fn it_reads_sized(#[case] wire: &[u8], #[case] expected: Option<&[u8]>) {
+        let bytes = std::io::Cursor::new(wire).read_be::<Bytes>();
+
+        assert_eq!(bytes.ok().as_deref(), expected);
+    }
+
+    #[test]
+    // The shared buffers of the `bytes` feature have interior mutability, but aren't hashed
+    #[allow(clippy::mutable_key_type)]
+    fn it_compares_contents_regardless_of_ownership() {
+        let mut keys = std::collections::HashMap::new();
+        keys.insert(Bytes::owned(b"host key".to_vec()), "known");
+
+        assert_eq!(keys.get(&Bytes::borrowed(b"host key")), Some(&"known"));
+        assert_eq!(
+            crate::arch::Ascii::borrowed("ssh-ed25519").unwrap(),
+            crate::arch::Ascii::owned("ssh-ed25519".into()).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_slices_without_copying() {
+        let bytes = Bytes::owned(b"key blob".to_vec());
+
+        let (algorithm, blob) = bytes.split_at(3);
+        assert_eq!(&*algorithm, b"key");
+        assert_eq!(blob.slice(1..).as_ptr(), bytes[4..].as_ptr());
+        assert_eq!(bytes.slice(..=2), algorithm);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn it_slices_shared_buffers() {
+        let shared = bytes::Bytes::from_static(b"key blob");
+        let bytes = Bytes::from(shared.clone());
+
+        let blob = bytes.slice(4..).into_owned();
+        assert_eq!(&*blob, b"blob");
+        assert_eq!(blob.as_ptr(), shared[4..].as_ptr());
     }
 }
@@ -0,0 +1,233 @@
+use std::{io, ops::Deref};
+
+use binrw::{
+    meta::{ReadEndian, WriteEndian},
+    BinRead, BinWrite,
+};
+
+use crate::SizeError;
+
+/// A structure wrapped in a `string`, prefixed with it's `size` as an `uint32`.
+///
+/// This is the encoding of the nested blobs of the protocol, such as the public keys, signatures
+/// and `SSH_MSG_KEXINIT` payloads in the exchange hashes.
+///
+/// When reading, the value is parsed bounded to it's `size`, and any bytes it left unparsed are skipped,
+/// while a `size` exceeding the remaining input fails with an [`std::io::ErrorKind::UnexpectedEof`].
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4251#section-5>.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lengthed<T>(pub T);
+
+impl<T> Deref for Lengthed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Lengthed<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> BinRead for Lengthed<T>
+where
+    T: BinRead,
+{
+    type Args<'a> = T::Args<'a>;
+
+    fn read_options<R: std::io::Read + std::io::Seek>(
+        reader: &mut R,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> binrw::BinResult<Self> {
+        let pos = reader.stream_position()?;
+        let size = u32::read_be(reader)? as usize;
+        if size > crate::PACKET_MAX_SIZE {
+            return Err(binrw::Error::Custom {
+                pos,
+                err: Box::new(SizeError {
+                    size,
+                    max: crate::PACKET_MAX_SIZE,
+                }),
+            });
+        }
+
+        // Ensure the whole value is present, as seeking past the end of the input would succeed
+        let start = pos + std::mem::size_of::<u32>() as u64;
+        let end = start + size as u64;
+        if reader.seek(io::SeekFrom::End(0))? < end {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        reader.seek(io::SeekFrom::Start(start))?;
+
+        // Parse the value in place, bounded to it's size, and skip any unparsed bytes
+        let value = T::read_options(
+            &mut Bounded {
+                inner: reader,
+                pos: start,
+                end,
+            },
+            endian,
+            args,
+        )?;
+        reader.seek(io::SeekFrom::Start(end))?;
+
+        Ok(Self(value))
+    }
+}
+
+/// A reader bounded to the absolute `end` position of the `inner` reader.
+struct Bounded<'r, R> {
+    inner: &'r mut R,
+    pos: u64,
+    end: u64,
+}
+
+impl<R: io::Read> io::Read for Bounded<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.end.saturating_sub(self.pos) as usize;
+        let len = buf.len().min(remaining);
+
+        let read = self.inner.read(&mut buf[..len])?;
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: io::Seek> io::Seek for Bounded<'_, R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            io::SeekFrom::End(offset) => self
+                .end
+                .checked_add_signed(offset)
+                .map(io::SeekFrom::Start)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?,
+            pos => pos,
+        };
+
+        self.pos = self.inner.seek(pos)?;
+
+        Ok(self.pos)
+    }
+}
+
+impl<T> ReadEndian for Lengthed<T>
+where
+    T: ReadEndian,
+{
+    const ENDIAN: binrw::meta::EndianKind = T::ENDIAN;
+}
+
+impl<T> BinWrite for Lengthed<T>
+where
+    T: BinWrite,
+{
+    type Args<'a> = T::Args<'a>;
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> binrw::BinResult<()> {
+        // Write a placeholder length, to be patched once the value is serialized in place
+        let start = writer.stream_position()?;
+        0u32.write_be(writer)?;
+
+        self.0.write_options(writer, endian, args)?;
+
+        let end = writer.stream_position()?;
+        let size = (end - start) as usize - std::mem::size_of::<u32>();
+        if size > crate::PACKET_MAX_SIZE {
+            return Err(binrw::Error::Custom {
+                pos: start,
+                err: Box::new(SizeError {
+                    size,
+                    max: crate::PACKET_MAX_SIZE,
+                }),
+            });
+        }
+
+        writer.seek(io::SeekFrom::Start(start))?;
+        (size as u32).write_be(writer)?;
+        writer.seek(io::SeekFrom::Start(end))?;
+
+        Ok(())
+    }
+}
+
+impl<T> WriteEndian for Lengthed<T>
+where
+    T: WriteEndian,
+{
+    const ENDIAN: binrw::meta::EndianKind = T::ENDIAN;
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use binrw::{BinReaderExt, BinWriterExt};
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn it_roundtrips() {
+        let mut buf = io::Cursor::new(Vec::new());
+        buf.write_be(&Lengthed(0xdeadbeef_u32)).unwrap();
+        assert_eq!(buf.get_ref(), &[0, 0, 0, 4, 0xde, 0xad, 0xbe, 0xef]);
+
+        buf.set_position(0);
+        let value: Lengthed<u32> = buf.read_be().unwrap();
+        assert_eq!(*value, 0xdeadbeef);
+    }
+
+    #[test]
+    fn it_writes_in_place_after_existing_data() {
+        let mut buf = io::Cursor::new(vec![0xff, 0xff]);
+        buf.set_position(2);
+
+        buf.write_be(&Lengthed(0x42_u8)).unwrap();
+        buf.write_be(&0x13_u8).unwrap();
+
+        assert_eq!(buf.get_ref(), &[0xff, 0xff, 0, 0, 0, 1, 0x42, 0x13]);
+    }
+
+    #[rstest]
+    #[case(&[0, 0, 0, 2, 0x42, 0xff, 0x13], Some(0x42), 6)]
+    #[case(&[0, 0, 0, 0, 0x42, 0xff, 0x13], None, 0)]
+    #[case(&[0, 0, 0, 8, 0x42, 0xff, 0x13], None, 0)]
+    fn it_reads_bounded_by_size(#[case] wire: &[u8], #[case] value: Option<u8>, #[case] pos: u64) {
+        let mut buf = io::Cursor::new(wire);
+
+        match value {
+            Some(value) => {
+                assert_eq!(*buf.read_be::<Lengthed<u8>>().unwrap(), value);
+                assert_eq!(buf.position(), pos);
+            }
+            None => {
+                buf.read_be::<Lengthed<u8>>().unwrap_err();
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_oversized() {
+        let mut buf = io::Cursor::new(u32::MAX.to_be_bytes());
+
+        let err = buf.read_be::<Lengthed<u32>>().unwrap_err();
+
+        assert_eq!(
+            err.custom_err::<SizeError>(),
+            Some(&SizeError {
+                size: u32::MAX as usize,
+                max: crate::PACKET_MAX_SIZE
+            })
+        );
+    }
+}
@@ -20,3 +20,9 @@ pub use mpint::MpInt;
 
 mod bool;
 pub use bool::Bool;
+
+mod lengthed;
+pub use lengthed::Lengthed;
+
+mod redacted;
+pub use redacted::Redacted;
@@ -1,17 +1,25 @@
 use binrw::binrw;
 
 #[cfg(feature = "zeroize")]
-use zeroize::Zeroize;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use super::Bytes;
+use crate::IntoOwned;
 
 /// A `mpint` as defined in the SSH protocol.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4251#section-5>.
+///
+/// With the `zeroize` feature, it's owned buffer is wiped when dropped,
+/// since it usually holds the _shared secret_ of the key exchange.
 #[binrw]
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "zeroize", derive(Zeroize))]
-pub struct MpInt<'b>(Bytes<'b>);
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "zeroize", derive(Zeroize, ZeroizeOnDrop))]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct MpInt<'b>(#[br(args_raw(options))] Bytes<'b>);
 
 impl<'b> MpInt<'b> {
     /// Create a [`MpInt`] from _bytes_.
@@ -32,14 +40,87 @@ impl<'b> MpInt<'b> {
         }
     }
 
+    /// The magnitude of a positive [`MpInt`] as unsigned big-endian bytes, without the sign byte
+    /// nor any leading zero, as expected by key derivation functions and bignum libraries,
+    /// or [`None`] if it is negative.
+    pub fn as_unsigned_bytes(&self) -> Option<&[u8]> {
+        match self.0.first() {
+            Some(byte) if *byte >= 0x80 => None,
+            _ => {
+                let start = self
+                    .0
+                    .iter()
+                    .position(|byte| *byte != 0)
+                    .unwrap_or(self.0.len());
+
+                Some(&self.0[start..])
+            }
+        }
+    }
+
     /// Obtain an [`MpInt`] from a reference by borrowing the internal buffer.
     pub fn as_borrow<'a: 'b>(&'a self) -> MpInt<'a> {
         Self(self.0.as_borrow())
     }
 }
 
+impl IntoOwned for MpInt<'_> {
+    type Owned = MpInt<'static>;
+
+    fn into_owned(mut self) -> Self::Owned {
+        // Taken rather than moved out, as the type may implement `Drop`.
+        MpInt(std::mem::take(&mut self.0).into_owned())
+    }
+}
+
 impl AsRef<[u8]> for MpInt<'_> {
     fn as_ref(&self) -> &[u8] {
         &self.0
     }
 }
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Encode for MpInt<'_> {
+    fn encoded_len(&self) -> Result<usize, ssh_encoding::Error> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl ssh_encoding::Writer) -> Result<(), ssh_encoding::Error> {
+        self.0.encode(writer)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Decode for MpInt<'_> {
+    type Error = ssh_encoding::Error;
+
+    fn decode(reader: &mut impl ssh_encoding::Reader) -> Result<Self, Self::Error> {
+        Bytes::decode(reader).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(&[], Some(&[][..]))]
+    #[case(&[0, 0x80, 1], Some(&[0x80, 1][..]))]
+    #[case(&[0, 0, 0x7f], Some(&[0x7f][..]))]
+    #[case(&[0xff, 1], None)]
+    fn it_strips_unsigned_bytes(#[case] value: &[u8], #[case] unsigned: Option<&[u8]>) {
+        let mpint = MpInt::from_bytes(Bytes::borrowed(value));
+
+        assert_eq!(mpint.as_unsigned_bytes(), unsigned);
+        if let Some(unsigned) = unsigned.filter(|unsigned| !unsigned.is_empty()) {
+            assert_eq!(
+                MpInt::positive(unsigned).as_unsigned_bytes(),
+                Some(unsigned)
+            );
+        }
+    }
+}
@@ -1,20 +1,60 @@
 use binrw::binrw;
 
 use super::Ascii;
+use crate::IntoOwned;
 
 /// A `name-list` as defined in the SSH protocol,
 /// a `,`-separated list of **ASCII** identifiers.
 ///
+/// The list is kept as it's raw buffer, and names are
+/// sliced out of it lazily when iterating or matching.
+///
 /// see <https://datatracker.ietf.org/doc/html/rfc4251#section-5>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
-pub struct NameList<'b>(pub Ascii<'b>);
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[br(import_raw(options: crate::DecodeOptions), assert(
+    self_0.split(',').filter(|name| !name.is_empty()).count() <= options.max_name_list_entries,
+    "The name-list has more names than the maximum of {}",
+    options.max_name_list_entries
+))]
+pub struct NameList<'b>(#[br(args_raw(options))] pub Ascii<'b>);
 
 impl NameList<'_> {
+    /// Iterate over the names in `self`, as [`Ascii`] strings borrowing the underlying buffer.
+    pub fn names(&self) -> impl Iterator<Item = Ascii<'_>> {
+        #[allow(deprecated)]
+        self.split().map(Ascii::borrowed_unchecked)
+    }
+
+    /// Whether `name` is present in `self`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.split().any(|this| this == name)
+    }
+
     /// Retrieve the first name from `self` that is also in `other`.
     pub fn preferred_in(&self, other: &Self) -> Option<Ascii<'_>> {
-        self.into_iter()
-            .find(|this| other.into_iter().any(|other| this == &other))
+        self.names().find(|this| other.contains(this))
+    }
+
+    fn split(&self) -> impl Iterator<Item = &str> {
+        self.0.split(',').filter(|name| !name.is_empty())
+    }
+}
+
+impl From<Vec<Ascii<'_>>> for NameList<'_> {
+    fn from(names: Vec<Ascii<'_>>) -> Self {
+        names.into_iter().collect()
+    }
+}
+
+impl IntoOwned for NameList<'_> {
+    type Owned = NameList<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        NameList(self.0.into_owned())
     }
 }
 
@@ -49,3 +89,80 @@ impl<'a: 'b, 'b> IntoIterator for &'a NameList<'b> {
             .filter_map(|name| (!name.is_empty()).then_some(Ascii::borrowed_unchecked(name)))
     }
 }
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Encode for NameList<'_> {
+    fn encoded_len(&self) -> Result<usize, ssh_encoding::Error> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl ssh_encoding::Writer) -> Result<(), ssh_encoding::Error> {
+        self.0.encode(writer)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Decode for NameList<'_> {
+    type Error = ssh_encoding::Error;
+
+    fn decode(reader: &mut impl ssh_encoding::Reader) -> Result<Self, Self::Error> {
+        Ascii::decode(reader).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(
+        "curve25519-sha256,,ecdh-sha2-nistp256",
+        "ecdh-sha2-nistp256,curve25519-sha256",
+        Some("curve25519-sha256")
+    )]
+    #[case("aes128-ctr,aes256-ctr", "aes256-ctr", Some("aes256-ctr"))]
+    #[case("none", "zlib", None)]
+    #[case("", "none", None)]
+    fn it_matches_names(#[case] this: &str, #[case] other: &str, #[case] preferred: Option<&str>) {
+        let this = NameList(Ascii::borrowed(this).unwrap());
+        let other = NameList(Ascii::borrowed(other).unwrap());
+
+        assert!(this.names().all(|name| !name.is_empty()));
+        assert!(NameList::from(this.names().collect::<Vec<_>>())
+            .names()
+            .eq(this.names()));
+        assert_eq!(this.preferred_in(&other).as_deref(), preferred);
+        if let Some(name) = preferred {
+            assert!(this.contains(name) && other.contains(name));
+        }
+    }
+
+    #[cfg(feature = "ssh-encoding")]
+    #[test]
+    fn it_interoperates_with_ssh_encoding() {
+        use binrw::BinWrite;
+        use ssh_encoding::{Decode, Encode};
+
+        let list = NameList(Ascii::borrowed("curve25519-sha256,ext-info-c").unwrap());
+
+        let mut buf = Vec::new();
+        list.encode(&mut buf).unwrap();
+
+        let mut expected = std::io::Cursor::new(Vec::new());
+        list.write_be(&mut expected).unwrap();
+        assert_eq!(buf, expected.into_inner());
+
+        let decoded = NameList::decode(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, list);
+
+        let blob = crate::arch::Bytes::encode_from(&list).unwrap();
+        assert_eq!(blob.decode_as::<NameList>().unwrap(), list);
+        assert!(crate::arch::Bytes::owned(vec![0xff; 5])
+            .decode_as::<NameList>()
+            .is_err());
+    }
+}
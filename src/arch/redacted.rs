@@ -0,0 +1,20 @@
+/// A wrapper redacting the inner value from it's [`Debug`](std::fmt::Debug)
+/// (and `defmt`) output, to avoid leaking secrets when logging messages.
+///
+/// It is used by the crate to redact passwords, shared secrets and signatures,
+/// and can be used to redact other sensitive values from hand-written implementations.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Redacted<T>(pub T);
+
+impl<T> std::fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<T> defmt::Format for Redacted<T> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "<redacted>")
+    }
+}
@@ -2,15 +2,20 @@ use std::ops::Deref;
 
 use binrw::binrw;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
 use super::Bytes;
+use crate::IntoOwned;
 
 /// A `string` as defined in the SSH protocol, restricted to valid **UTF-8**.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4251#section-5>.
 #[binrw]
-#[derive(Default, Clone, PartialEq, Eq)]
-#[br(assert(std::str::from_utf8(&self_0).is_ok()))]
-pub struct Utf8<'b>(Bytes<'b>);
+#[derive(Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "zeroize", derive(Zeroize))]
+#[br(import_raw(options: crate::DecodeOptions), assert(std::str::from_utf8(&self_0).is_ok()))]
+pub struct Utf8<'b>(#[br(args_raw(options))] Bytes<'b>);
 
 impl<'b> Utf8<'b> {
     /// Create an [`Utf8`] string from a [`String`].
@@ -34,6 +39,14 @@ impl<'b> Utf8<'b> {
     }
 }
 
+impl IntoOwned for Utf8<'_> {
+    type Owned = Utf8<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Utf8(self.0.into_owned())
+    }
+}
+
 impl std::fmt::Debug for Utf8<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("Utf8").field(&&**self).finish()
@@ -54,6 +67,56 @@ impl Deref for Utf8<'_> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Utf8<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Utf8<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        Ok(Self::owned(value))
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Utf8<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", &**self)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Encode for Utf8<'_> {
+    fn encoded_len(&self) -> Result<usize, ssh_encoding::Error> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl ssh_encoding::Writer) -> Result<(), ssh_encoding::Error> {
+        self.0.encode(writer)
+    }
+}
+
+#[cfg(feature = "ssh-encoding")]
+impl ssh_encoding::Decode for Utf8<'_> {
+    type Error = ssh_encoding::Error;
+
+    fn decode(reader: &mut impl ssh_encoding::Reader) -> Result<Self, Self::Error> {
+        Ok(Self::owned(String::decode(reader)?))
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Utf8<'_> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        String::arbitrary(u).map(Self::owned)
+    }
+}
+
 impl AsRef<str> for Utf8<'_> {
     fn as_ref(&self) -> &str {
         self
@@ -1,68 +1,125 @@
 //! Messages involved in the SSH's **connect** (`SSH-CONNECT`) part of the protocol,
 //! as defined in the [RFC 4254](https://datatracker.ietf.org/doc/html/rfc4254).
 
-use std::num::NonZeroU32;
+use std::{
+    collections::{btree_map, BTreeMap, VecDeque},
+    num::NonZeroU32,
+};
 
-use binrw::binrw;
+use binrw::{binrw, meta::WriteEndian, BinWrite};
 
-use crate::arch;
+use crate::{arch, message, owned, DecodeError, IntoOwned, Packet};
+
+mod modes;
+pub use modes::{TerminalMode, TerminalModes};
+
+mod signal;
+pub use signal::SignalName;
+
+mod endpoint;
+pub use endpoint::{Endpoint, EndpointError};
 
 /// The `SSH_MSG_GLOBAL_REQUEST` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 80_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct GlobalRequest<'b> {
     #[bw(calc = context.as_ascii())]
+    #[br(args_raw(options))]
     kind: arch::Ascii<'b>,
 
     /// Whether the sender wants a reply.
     pub want_reply: arch::Bool,
 
     /// The context of the global request.
-    #[br(args(kind))]
+    #[br(args(options, kind))]
     pub context: GlobalRequestContext<'b>,
 }
 
+impl<'b> GlobalRequest<'b> {
+    /// Create a [`GlobalRequest`] message with the provided `context`,
+    /// not wanting a reply, see [`GlobalRequest::want_reply`].
+    pub fn new(context: GlobalRequestContext<'b>) -> Self {
+        Self {
+            want_reply: false.into(),
+            context,
+        }
+    }
+
+    /// Set whether the sender wants a reply to the [`GlobalRequest`].
+    pub fn want_reply(mut self, want_reply: bool) -> Self {
+        self.want_reply = want_reply.into();
+        self
+    }
+
+    /// Whether the sender expects a `SSH_MSG_REQUEST_SUCCESS` or `SSH_MSG_REQUEST_FAILURE` in reply.
+    pub fn reply_expected(&self) -> bool {
+        *self.want_reply
+    }
+
+    /// Create a [`GlobalRequest`] message for a [`GlobalRequestContext::TcpipForward`],
+    /// binding on the `bind` endpoint on the remote.
+    pub fn tcpip_forward(bind: Endpoint<'b>) -> Self {
+        Self::new(GlobalRequestContext::TcpipForward { bind })
+    }
+
+    /// Create a [`GlobalRequest`] message for a [`GlobalRequestContext::CancelTcpipForward`],
+    /// of the forwarding bound on the `bind` endpoint on the remote.
+    pub fn cancel_tcpip_forward(bind: Endpoint<'b>) -> Self {
+        Self::new(GlobalRequestContext::CancelTcpipForward { bind })
+    }
+}
+
 /// The `context` in the `SSH_MSG_GLOBAL_REQUEST` message.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big)]
-#[br(import(kind: arch::Ascii<'_>))]
+#[br(import(options: crate::DecodeOptions, kind: arch::Ascii<'_>))]
 pub enum GlobalRequestContext<'b> {
     /// A request of type `tcpip-forward`,
     /// as defined in [RFC4254 section 7.1](https://datatracker.ietf.org/doc/html/rfc4254#section-7.1).
     #[br(pre_assert(kind == GlobalRequestContext::TCPIP_FORWARD))]
     TcpipForward {
-        /// Address to bind on the remote.
-        bind_address: arch::Bytes<'b>,
-
-        /// Port to bind on the remote, randomly choosen if 0.
-        bind_port: u32,
+        /// Address and port to bind on the remote, the port being randomly choosen if 0.
+        #[br(args_raw(options))]
+        bind: Endpoint<'b>,
     },
 
     /// A request of type `cancel-tcpip-forward`,
     /// as defined in [RFC4254 section 7.1](https://datatracker.ietf.org/doc/html/rfc4254#section-7.1).
     #[br(pre_assert(kind == GlobalRequestContext::CANCEL_TCPIP_FORWARD))]
     CancelTcpipForward {
-        /// Address that was bound on the remote.
-        bind_address: arch::Bytes<'b>,
-
-        /// Port that was bound on the remote.
-        bind_port: u32,
+        /// Address and port that were bound on the remote.
+        #[br(args_raw(options))]
+        bind: Endpoint<'b>,
     },
+
+    /// A request of type `keepalive@openssh.com`, probing the liveness of the peer,
+    /// which replies with either a success or a failure.
+    #[br(pre_assert(kind == GlobalRequestContext::KEEPALIVE_OPENSSH))]
+    KeepaliveOpenssh,
 }
 
 impl GlobalRequestContext<'_> {
     const TCPIP_FORWARD: arch::Ascii<'static> = arch::ascii!("tcpip-forward");
     const CANCEL_TCPIP_FORWARD: arch::Ascii<'static> = arch::ascii!("cancel-tcpip-forward");
+    const KEEPALIVE_OPENSSH: arch::Ascii<'static> = arch::ascii!("keepalive@openssh.com");
 
     /// Get the [`GlobalRequestContext`]'s SSH identifier.
     pub fn as_ascii(&self) -> arch::Ascii<'static> {
         match self {
             Self::TcpipForward { .. } => Self::TCPIP_FORWARD,
             Self::CancelTcpipForward { .. } => Self::CANCEL_TCPIP_FORWARD,
+            Self::KeepaliveOpenssh => Self::KEEPALIVE_OPENSSH,
         }
     }
 }
@@ -70,7 +127,10 @@ impl GlobalRequestContext<'_> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 81_u8)]
 pub struct RequestSuccess;
 
@@ -79,7 +139,10 @@ pub struct RequestSuccess;
 ///
 /// see [RFC4254 section 7.1](https://datatracker.ietf.org/doc/html/rfc4254#section-7.1).
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 81_u8)]
 pub struct ForwardingSuccess {
     /// Port that was bound on the remote.
@@ -90,7 +153,10 @@ pub struct ForwardingSuccess {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 82_u8)]
 pub struct RequestFailure;
 
@@ -98,10 +164,15 @@ pub struct RequestFailure;
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.1>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 90_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct ChannelOpen<'b> {
     #[bw(calc = context.as_ascii())]
+    #[br(args_raw(options))]
     kind: arch::Ascii<'b>,
 
     /// Sender channel.
@@ -114,15 +185,110 @@ pub struct ChannelOpen<'b> {
     pub maximum_packet_size: u32,
 
     /// The context of the open request.
-    #[br(args(kind))]
+    #[br(args(options, kind))]
     pub context: ChannelOpenContext<'b>,
 }
 
+impl<'b> ChannelOpen<'b> {
+    /// Create a [`ChannelOpen`] message for the `sender_channel` with the provided `context`,
+    /// advertising the `initial_window_size` and `maximum_packet_size` of the channel.
+    pub fn new(
+        sender_channel: u32,
+        initial_window_size: u32,
+        maximum_packet_size: u32,
+        context: ChannelOpenContext<'b>,
+    ) -> Self {
+        Self {
+            sender_channel,
+            initial_window_size,
+            maximum_packet_size,
+            context,
+        }
+    }
+
+    /// Create a [`ChannelOpen`] message for a [`ChannelOpenContext::Session`] channel.
+    pub fn session(
+        sender_channel: u32,
+        initial_window_size: u32,
+        maximum_packet_size: u32,
+    ) -> Self {
+        Self::new(
+            sender_channel,
+            initial_window_size,
+            maximum_packet_size,
+            ChannelOpenContext::Session,
+        )
+    }
+
+    /// Create a [`ChannelOpen`] message for a [`ChannelOpenContext::X11`](type@ChannelOpenContext::X11) channel,
+    /// originating from `originator_address` and `originator_port`.
+    pub fn x11(
+        sender_channel: u32,
+        initial_window_size: u32,
+        maximum_packet_size: u32,
+        originator_address: arch::Ascii<'b>,
+        originator_port: u32,
+    ) -> Self {
+        Self::new(
+            sender_channel,
+            initial_window_size,
+            maximum_packet_size,
+            ChannelOpenContext::X11 {
+                originator_address,
+                originator_port,
+            },
+        )
+    }
+
+    /// Create a [`ChannelOpen`] message for a [`ChannelOpenContext::ForwardedTcpip`] channel,
+    /// connected on `address` from `originator`.
+    pub fn forwarded_tcpip(
+        sender_channel: u32,
+        initial_window_size: u32,
+        maximum_packet_size: u32,
+        address: Endpoint<'b>,
+        originator: Endpoint<'b>,
+    ) -> Self {
+        Self::new(
+            sender_channel,
+            initial_window_size,
+            maximum_packet_size,
+            ChannelOpenContext::ForwardedTcpip {
+                address,
+                originator,
+            },
+        )
+    }
+
+    /// Create a [`ChannelOpen`] message for a [`ChannelOpenContext::DirectTcpip`] channel,
+    /// to connect to `address` from `originator`.
+    pub fn direct_tcpip(
+        sender_channel: u32,
+        initial_window_size: u32,
+        maximum_packet_size: u32,
+        address: Endpoint<'b>,
+        originator: Endpoint<'b>,
+    ) -> Self {
+        Self::new(
+            sender_channel,
+            initial_window_size,
+            maximum_packet_size,
+            ChannelOpenContext::DirectTcpip {
+                address,
+                originator,
+            },
+        )
+    }
+}
+
 /// The `context` in the `SSH_MSG_CHANNEL_OPEN` message.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big)]
-#[br(import(kind: arch::Ascii<'_>))]
+#[br(import(options: crate::DecodeOptions, kind: arch::Ascii<'_>))]
 pub enum ChannelOpenContext<'b> {
     /// A channel of type `session`,
     /// as defined in [RFC4254 section 6.1](https://datatracker.ietf.org/doc/html/rfc4254#section-6.1).
@@ -134,6 +300,7 @@ pub enum ChannelOpenContext<'b> {
     #[br(pre_assert(kind == ChannelOpenContext::X11))]
     X11 {
         /// Originator address.
+        #[br(args_raw(options))]
         originator_address: arch::Ascii<'b>,
 
         /// Originator port.
@@ -144,34 +311,26 @@ pub enum ChannelOpenContext<'b> {
     /// as defined in [RFC4254 section 7.2](https://datatracker.ietf.org/doc/html/rfc4254#section-7.2).
     #[br(pre_assert(kind == ChannelOpenContext::FORWARDED_TCPIP))]
     ForwardedTcpip {
-        /// Address that was connected on the remote.
-        address: arch::Ascii<'b>,
-
-        /// Port that was connected on the remote.
-        port: u32,
-
-        /// Originator address.
-        originator_address: arch::Ascii<'b>,
+        /// Address and port that were connected on the remote.
+        #[br(args_raw(options))]
+        address: Endpoint<'b>,
 
-        /// Originator port.
-        originator_port: u32,
+        /// Originator address and port.
+        #[br(args_raw(options))]
+        originator: Endpoint<'b>,
     },
 
     /// A channel of type `direct-tcpip`,
     /// as defined in [RFC4254 section 7.2](https://datatracker.ietf.org/doc/html/rfc4254#section-7.2).
     #[br(pre_assert(kind == ChannelOpenContext::DIRECT_TCPIP))]
     DirectTcpip {
-        /// Address to connect to.
-        address: arch::Ascii<'b>,
-
-        /// Port to connect to.
-        port: u32,
+        /// Address and port to connect to.
+        #[br(args_raw(options))]
+        address: Endpoint<'b>,
 
-        /// Originator address.
-        originator_address: arch::Ascii<'b>,
-
-        /// Originator port.
-        originator_port: u32,
+        /// Originator address and port.
+        #[br(args_raw(options))]
+        originator: Endpoint<'b>,
     },
 }
 
@@ -196,7 +355,10 @@ impl ChannelOpenContext<'_> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.1>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 91_u8)]
 pub struct ChannelOpenConfirmation {
     /// Recipient channel.
@@ -216,8 +378,12 @@ pub struct ChannelOpenConfirmation {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.1>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 92_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct ChannelOpenFailure<'b> {
     /// Recipient channel.
     pub recipient_channel: u32,
@@ -226,15 +392,82 @@ pub struct ChannelOpenFailure<'b> {
     pub reason: ChannelOpenFailureReason,
 
     /// Description of the reason.
+    #[br(args_raw(options))]
     pub description: arch::Utf8<'b>,
 
     /// Language tag.
+    #[br(args_raw(options))]
     pub language: arch::Ascii<'b>,
 }
 
+impl<'b> ChannelOpenFailure<'b> {
+    /// Create a [`ChannelOpenFailure`] message for the `recipient_channel` and the `reason`,
+    /// with a human-readable `description` and an empty language tag.
+    pub fn new(
+        recipient_channel: u32,
+        reason: ChannelOpenFailureReason,
+        description: impl Into<arch::Utf8<'b>>,
+    ) -> Self {
+        Self {
+            recipient_channel,
+            reason,
+            description: description.into(),
+            language: Default::default(),
+        }
+    }
+
+    /// Create a [`ChannelOpenFailure`] message for [`ChannelOpenFailureReason::AdministrativelyProhibited`].
+    pub fn administratively_prohibited(
+        recipient_channel: u32,
+        description: impl Into<arch::Utf8<'b>>,
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelOpenFailureReason::AdministrativelyProhibited,
+            description,
+        )
+    }
+
+    /// Create a [`ChannelOpenFailure`] message for [`ChannelOpenFailureReason::ConnectFailed`].
+    pub fn connect_failed(recipient_channel: u32, description: impl Into<arch::Utf8<'b>>) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelOpenFailureReason::ConnectFailed,
+            description,
+        )
+    }
+
+    /// Create a [`ChannelOpenFailure`] message for [`ChannelOpenFailureReason::UnknownChannelType`].
+    pub fn unknown_channel_type(
+        recipient_channel: u32,
+        description: impl Into<arch::Utf8<'b>>,
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelOpenFailureReason::UnknownChannelType,
+            description,
+        )
+    }
+
+    /// Create a [`ChannelOpenFailure`] message for [`ChannelOpenFailureReason::ResourceShortage`].
+    pub fn resource_shortage(
+        recipient_channel: u32,
+        description: impl Into<arch::Utf8<'b>>,
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelOpenFailureReason::ResourceShortage,
+            description,
+        )
+    }
+}
+
 /// The `reason` for failure in the `SSH_MSG_CHANNEL_OPEN_FAILURE` message.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big)]
 pub enum ChannelOpenFailureReason {
     /// `SSH_OPEN_ADMINISTRATIVELY_PROHIBITED`.
@@ -260,11 +493,53 @@ pub enum ChannelOpenFailureReason {
     Other(u32),
 }
 
+impl ChannelOpenFailureReason {
+    /// The numeric code of the reason, as encoded in the message.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::AdministrativelyProhibited => 1,
+            Self::ConnectFailed => 2,
+            Self::UnknownChannelType => 3,
+            Self::ResourceShortage => 4,
+            Self::Other(code) => *code,
+        }
+    }
+
+    /// The name of the reason's constant, or [`None`] for [`ChannelOpenFailureReason::Other`].
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::AdministrativelyProhibited => "SSH_OPEN_ADMINISTRATIVELY_PROHIBITED",
+            Self::ConnectFailed => "SSH_OPEN_CONNECT_FAILED",
+            Self::UnknownChannelType => "SSH_OPEN_UNKNOWN_CHANNEL_TYPE",
+            Self::ResourceShortage => "SSH_OPEN_RESOURCE_SHORTAGE",
+            Self::Other(_) => return None,
+        })
+    }
+
+    /// Whether the reason is in the `0xFE000000` through `0xFFFFFFFF` range reserved for PRIVATE USE.
+    pub fn is_private_use(&self) -> bool {
+        self.code() >= 0xFE000000
+    }
+}
+
+impl std::fmt::Display for ChannelOpenFailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name} ({})", self.code()),
+            None if self.is_private_use() => write!(f, "private use reason {:#010x}", self.code()),
+            None => write!(f, "unknown reason {}", self.code()),
+        }
+    }
+}
+
 /// The `SSH_MSG_CHANNEL_WINDOW_ADJUST` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.2>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 93_u8)]
 pub struct ChannelWindowAdjust {
     /// Recipient channel.
@@ -278,22 +553,97 @@ pub struct ChannelWindowAdjust {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.2>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 94_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct ChannelData<'b> {
     /// Recipient channel.
     pub recipient_channel: u32,
 
     /// Data bytes to transport.
+    #[br(args_raw(options))]
     pub data: arch::Bytes<'b>,
 }
 
+impl<'b> ChannelData<'b> {
+    /// Split the `data` into [`ChannelData`] messages for the `recipient_channel`, each carrying at most
+    /// `maximum_packet_size` bytes, and stopping once the peer's remaining `window` is exhausted.
+    ///
+    /// The `window` is decreased by the size of each message as it is yielded,
+    /// and the data left to be sent after a window adjustment is available from [`DataChunks::remainder`].
+    pub fn chunks<'w>(
+        recipient_channel: u32,
+        data: &'b [u8],
+        maximum_packet_size: u32,
+        window: &'w mut u32,
+    ) -> DataChunks<'b, 'w> {
+        DataChunks {
+            recipient_channel,
+            data,
+            maximum_packet_size,
+            window,
+        }
+    }
+}
+
+/// An iterator over [`ChannelData`] messages, see [`ChannelData::chunks`].
+#[derive(Debug)]
+pub struct DataChunks<'b, 'w> {
+    recipient_channel: u32,
+    data: &'b [u8],
+    maximum_packet_size: u32,
+    window: &'w mut u32,
+}
+
+impl<'b> DataChunks<'b, '_> {
+    /// The data that is yet to be yielded.
+    pub fn remainder(&self) -> &'b [u8] {
+        self.data
+    }
+
+    /// The remaining window of the peer.
+    pub fn window(&self) -> u32 {
+        *self.window
+    }
+}
+
+impl<'b> Iterator for DataChunks<'b, '_> {
+    type Item = ChannelData<'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let size = self
+            .data
+            .len()
+            .min(self.maximum_packet_size as usize)
+            .min(*self.window as usize);
+        if size == 0 {
+            return None;
+        }
+
+        let (chunk, remainder) = self.data.split_at(size);
+        self.data = remainder;
+        *self.window -= size as u32;
+
+        Some(ChannelData {
+            recipient_channel: self.recipient_channel,
+            data: arch::Bytes::borrowed(chunk),
+        })
+    }
+}
+
 /// The `SSH_MSG_CHANNEL_EXTENDED_DATA` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.2>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 95_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct ChannelExtendedData<'b> {
     /// Recipient channel.
     pub recipient_channel: u32,
@@ -302,14 +652,69 @@ pub struct ChannelExtendedData<'b> {
     pub data_type: NonZeroU32,
 
     /// Data bytes to transport.
+    #[br(args_raw(options))]
     pub data: arch::Bytes<'b>,
 }
 
+impl<'b> ChannelExtendedData<'b> {
+    /// Split the `data` of `data_type` into [`ChannelExtendedData`] messages for the `recipient_channel`,
+    /// each carrying at most `maximum_packet_size` bytes, and stopping once the peer's remaining `window` is exhausted.
+    ///
+    /// The `window` is decreased by the size of each message as it is yielded,
+    /// and the data left to be sent after a window adjustment is available from [`ExtendedDataChunks::remainder`].
+    pub fn chunks<'w>(
+        recipient_channel: u32,
+        data_type: NonZeroU32,
+        data: &'b [u8],
+        maximum_packet_size: u32,
+        window: &'w mut u32,
+    ) -> ExtendedDataChunks<'b, 'w> {
+        ExtendedDataChunks {
+            data_type,
+            inner: ChannelData::chunks(recipient_channel, data, maximum_packet_size, window),
+        }
+    }
+}
+
+/// An iterator over [`ChannelExtendedData`] messages, see [`ChannelExtendedData::chunks`].
+#[derive(Debug)]
+pub struct ExtendedDataChunks<'b, 'w> {
+    data_type: NonZeroU32,
+    inner: DataChunks<'b, 'w>,
+}
+
+impl<'b> ExtendedDataChunks<'b, '_> {
+    /// The data that is yet to be yielded.
+    pub fn remainder(&self) -> &'b [u8] {
+        self.inner.remainder()
+    }
+
+    /// The remaining window of the peer.
+    pub fn window(&self) -> u32 {
+        self.inner.window()
+    }
+}
+
+impl<'b> Iterator for ExtendedDataChunks<'b, '_> {
+    type Item = ChannelExtendedData<'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|message| ChannelExtendedData {
+            recipient_channel: message.recipient_channel,
+            data_type: self.data_type,
+            data: message.data,
+        })
+    }
+}
+
 /// The `SSH_MSG_CHANNEL_EOF` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.3>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 96_u8)]
 pub struct ChannelEof {
     /// Recipient channel.
@@ -320,7 +725,10 @@ pub struct ChannelEof {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.3>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 97_u8)]
 pub struct ChannelClose {
     /// Recipient channel.
@@ -331,34 +739,209 @@ pub struct ChannelClose {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 98_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct ChannelRequest<'b> {
     /// Recipient channel.
     pub recipient_channel: u32,
 
     #[bw(calc = context.as_ascii())]
+    #[br(args_raw(options))]
     kind: arch::Ascii<'b>,
 
     /// Whether the sender wants a reply.
     pub want_reply: arch::Bool,
 
     /// The context of the channel request.
-    #[br(args(kind))]
+    #[br(args(options, kind))]
     pub context: ChannelRequestContext<'b>,
 }
 
+impl<'b> ChannelRequest<'b> {
+    /// Create a [`ChannelRequest`] message for the `recipient_channel` with the provided `context`,
+    /// not wanting a reply, see [`ChannelRequest::want_reply`].
+    pub fn new(recipient_channel: u32, context: ChannelRequestContext<'b>) -> Self {
+        Self {
+            recipient_channel,
+            want_reply: false.into(),
+            context,
+        }
+    }
+
+    /// Set whether the sender wants a reply to the [`ChannelRequest`].
+    pub fn want_reply(mut self, want_reply: bool) -> Self {
+        self.want_reply = want_reply.into();
+        self
+    }
+
+    /// Whether the sender expects a `SSH_MSG_CHANNEL_SUCCESS` or `SSH_MSG_CHANNEL_FAILURE` in reply.
+    pub fn reply_expected(&self) -> bool {
+        *self.want_reply
+    }
+
+    /// Create a [`ChannelRequest`] message for a [`ChannelRequestContext::Pty`],
+    /// of `term` type, with `(width, height)` dimensions in characters and pixels and encoded `modes`,
+    /// which may be built from [`TerminalModes`].
+    pub fn pty(
+        recipient_channel: u32,
+        term: impl Into<arch::Bytes<'b>>,
+        (width_chars, height_chars): (u32, u32),
+        (width_pixels, height_pixels): (u32, u32),
+        modes: impl Into<arch::Bytes<'b>>,
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::Pty {
+                term: term.into(),
+                width_chars,
+                height_chars,
+                width_pixels,
+                height_pixels,
+                modes: modes.into(),
+            },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for a [`ChannelRequestContext::X11`](type@ChannelRequestContext::X11) forwarding.
+    pub fn x11(
+        recipient_channel: u32,
+        single_connection: bool,
+        x11_authentication_protocol: impl Into<arch::Bytes<'b>>,
+        x11_authentication_cookie: impl Into<arch::Bytes<'b>>,
+        x11_screen_number: u32,
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::X11 {
+                single_connection: single_connection.into(),
+                x11_authentication_protocol: x11_authentication_protocol.into(),
+                x11_authentication_cookie: x11_authentication_cookie.into(),
+                x11_screen_number,
+            },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for a [`ChannelRequestContext::Env`] variable.
+    pub fn env(
+        recipient_channel: u32,
+        name: impl Into<arch::Bytes<'b>>,
+        value: impl Into<arch::Bytes<'b>>,
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::Env {
+                name: name.into(),
+                value: value.into(),
+            },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for a [`ChannelRequestContext::Shell`].
+    pub fn shell(recipient_channel: u32) -> Self {
+        Self::new(recipient_channel, ChannelRequestContext::Shell)
+    }
+
+    /// Create a [`ChannelRequest`] message to [`ChannelRequestContext::Exec`] the `command`.
+    pub fn exec(recipient_channel: u32, command: impl Into<arch::Bytes<'b>>) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::Exec {
+                command: command.into(),
+            },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for the [`ChannelRequestContext::Subsystem`] `name`.
+    pub fn subsystem(recipient_channel: u32, name: impl Into<arch::Bytes<'b>>) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::Subsystem { name: name.into() },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for a [`ChannelRequestContext::WindowChange`],
+    /// with `(width, height)` dimensions in characters and pixels.
+    pub fn window_change(
+        recipient_channel: u32,
+        (width_chars, height_chars): (u32, u32),
+        (width_pixels, height_pixels): (u32, u32),
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::WindowChange {
+                width_chars,
+                height_chars,
+                width_pixels,
+                height_pixels,
+            },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for a [`ChannelRequestContext::XonXoff`] flow control.
+    pub fn xon_xoff(recipient_channel: u32, client_can_do: bool) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::XonXoff {
+                client_can_do: client_can_do.into(),
+            },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message to deliver a [`ChannelRequestContext::Signal`] by it's `name`.
+    pub fn signal(recipient_channel: u32, name: impl Into<SignalName<'b>>) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::Signal { name: name.into() },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for the [`ChannelRequestContext::ExitStatus`] `code`.
+    pub fn exit_status(recipient_channel: u32, code: u32) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::ExitStatus { code },
+        )
+    }
+
+    /// Create a [`ChannelRequest`] message for a [`ChannelRequestContext::ExitSignal`] by it's `name`,
+    /// with an `error_message` and an empty language tag.
+    pub fn exit_signal(
+        recipient_channel: u32,
+        name: impl Into<SignalName<'b>>,
+        core_dumped: bool,
+        error_message: impl Into<arch::Utf8<'b>>,
+    ) -> Self {
+        Self::new(
+            recipient_channel,
+            ChannelRequestContext::ExitSignal {
+                name: name.into(),
+                core_dumped: core_dumped.into(),
+                error_message: error_message.into(),
+                language: Default::default(),
+            },
+        )
+    }
+}
+
 /// The `context` in the `SSH_MSG_CHANNEL_REQUEST` message.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big)]
-#[br(import(kind: arch::Ascii<'_>))]
+#[br(import(options: crate::DecodeOptions, kind: arch::Ascii<'_>))]
 pub enum ChannelRequestContext<'b> {
     /// A request of type `pty-req`,
     /// as defined in [RFC4254 section 6.2](https://datatracker.ietf.org/doc/html/rfc4254#section-6.2).
     #[br(pre_assert(kind == ChannelRequestContext::PTY))]
     Pty {
         /// Peer's `$TERM` environment variable value.
+        #[br(args_raw(options))]
         term: arch::Bytes<'b>,
 
         /// Terminal width, in columns.
@@ -373,7 +956,8 @@ pub enum ChannelRequestContext<'b> {
         /// Terminal height, in pixels.
         height_pixels: u32,
 
-        /// Encoded terminal modes.
+        /// Encoded terminal modes, see [`TerminalModes`].
+        #[br(args_raw(options))]
         modes: arch::Bytes<'b>,
     },
 
@@ -385,9 +969,11 @@ pub enum ChannelRequestContext<'b> {
         single_connection: arch::Bool,
 
         /// X11 authentication protocol.
+        #[br(args_raw(options))]
         x11_authentication_protocol: arch::Bytes<'b>,
 
         /// X11 authentication cookie.
+        #[br(args_raw(options))]
         x11_authentication_cookie: arch::Bytes<'b>,
 
         /// X11 authentication number.
@@ -399,9 +985,11 @@ pub enum ChannelRequestContext<'b> {
     #[br(pre_assert(kind == ChannelRequestContext::ENV))]
     Env {
         /// Environment variable name.
+        #[br(args_raw(options))]
         name: arch::Bytes<'b>,
 
         /// Environment variable value.
+        #[br(args_raw(options))]
         value: arch::Bytes<'b>,
     },
 
@@ -415,6 +1003,7 @@ pub enum ChannelRequestContext<'b> {
     #[br(pre_assert(kind == ChannelRequestContext::EXEC))]
     Exec {
         /// Command to be executed.
+        #[br(args_raw(options))]
         command: arch::Bytes<'b>,
     },
 
@@ -423,6 +1012,7 @@ pub enum ChannelRequestContext<'b> {
     #[br(pre_assert(kind == ChannelRequestContext::SUBSYSTEM))]
     Subsystem {
         /// Name of the requested subsystem.
+        #[br(args_raw(options))]
         name: arch::Bytes<'b>,
     },
 
@@ -456,7 +1046,8 @@ pub enum ChannelRequestContext<'b> {
     #[br(pre_assert(kind == ChannelRequestContext::SIGNAL))]
     Signal {
         /// Signal name (without the "SIG" prefix).
-        name: arch::Bytes<'b>,
+        #[br(args_raw(options))]
+        name: SignalName<'b>,
     },
 
     /// A request of type `exit-status`,
@@ -472,15 +1063,18 @@ pub enum ChannelRequestContext<'b> {
     #[br(pre_assert(kind == ChannelRequestContext::EXIT_SIGNAL))]
     ExitSignal {
         /// Signal name (without the "SIG" prefix).
-        name: arch::Bytes<'b>,
+        #[br(args_raw(options))]
+        name: SignalName<'b>,
 
         /// Whether a core dump is triggering the signal.
         core_dumped: arch::Bool,
 
         /// The error message for the signal.
+        #[br(args_raw(options))]
         error_message: arch::Utf8<'b>,
 
         /// Language tag.
+        #[br(args_raw(options))]
         language: arch::Ascii<'b>,
     },
 }
@@ -498,6 +1092,33 @@ impl ChannelRequestContext<'_> {
     const EXIT_STATUS: arch::Ascii<'static> = arch::ascii!("exit-status");
     const EXIT_SIGNAL: arch::Ascii<'static> = arch::ascii!("exit-signal");
 
+    #[cfg(feature = "rand")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+    /// Generate a fake `MIT-MAGIC-COOKIE-1` X11 authentication cookie from the operating system,
+    /// to be sent in place of the real one, see [`ChannelRequestContext::fake_x11_cookie_with_rng`].
+    pub fn fake_x11_cookie() -> arch::Bytes<'static> {
+        Self::fake_x11_cookie_with_rng(&mut rand_core::OsRng)
+    }
+
+    /// Generate a fake `MIT-MAGIC-COOKIE-1` X11 authentication cookie from `rng`,
+    /// as 16 random bytes in lowercase hexadecimal, to be sent in place of the real one.
+    ///
+    /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-6.3.1>.
+    pub fn fake_x11_cookie_with_rng(
+        rng: &mut impl rand_core::CryptoRngCore,
+    ) -> arch::Bytes<'static> {
+        let mut cookie = [0; 16];
+        rng.fill_bytes(&mut cookie);
+
+        arch::Bytes::owned(
+            cookie
+                .iter()
+                .flat_map(|byte| [byte >> 4, byte & 0xf])
+                .map(|nibble| b"0123456789abcdef"[nibble as usize])
+                .collect(),
+        )
+    }
+
     /// Get the [`ChannelRequestContext`]'s SSH identifier.
     pub fn as_ascii(&self) -> arch::Ascii<'static> {
         match self {
@@ -520,7 +1141,10 @@ impl ChannelRequestContext<'_> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 99_u8)]
 pub struct ChannelSuccess {
     /// Recipient channel.
@@ -531,9 +1155,762 @@ pub struct ChannelSuccess {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 100_u8)]
 pub struct ChannelFailure {
     /// Recipient channel.
     pub recipient_channel: u32,
 }
+
+/// Any of the messages of the **connect** (`SSH-CONNECT`) part of the protocol,
+/// decoded from a [`Packet`] by dispatching on it's message number.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Message<'b> {
+    /// The `SSH_MSG_GLOBAL_REQUEST` message.
+    GlobalRequest(GlobalRequest<'b>),
+
+    /// The `SSH_MSG_REQUEST_SUCCESS` message, such as [`RequestSuccess`]
+    /// or [`ForwardingSuccess`], depending on the global request.
+    RequestSuccess(Packet<'b>),
+
+    /// The `SSH_MSG_REQUEST_FAILURE` message.
+    RequestFailure(RequestFailure),
+
+    /// The `SSH_MSG_CHANNEL_OPEN` message.
+    ChannelOpen(ChannelOpen<'b>),
+
+    /// The `SSH_MSG_CHANNEL_OPEN_CONFIRMATION` message.
+    ChannelOpenConfirmation(ChannelOpenConfirmation),
+
+    /// The `SSH_MSG_CHANNEL_OPEN_FAILURE` message.
+    ChannelOpenFailure(ChannelOpenFailure<'b>),
+
+    /// The `SSH_MSG_CHANNEL_WINDOW_ADJUST` message.
+    ChannelWindowAdjust(ChannelWindowAdjust),
+
+    /// The `SSH_MSG_CHANNEL_DATA` message.
+    ChannelData(ChannelData<'b>),
+
+    /// The `SSH_MSG_CHANNEL_EXTENDED_DATA` message.
+    ChannelExtendedData(ChannelExtendedData<'b>),
+
+    /// The `SSH_MSG_CHANNEL_EOF` message.
+    ChannelEof(ChannelEof),
+
+    /// The `SSH_MSG_CHANNEL_CLOSE` message.
+    ChannelClose(ChannelClose),
+
+    /// The `SSH_MSG_CHANNEL_REQUEST` message.
+    ChannelRequest(ChannelRequest<'b>),
+
+    /// The `SSH_MSG_CHANNEL_SUCCESS` message.
+    ChannelSuccess(ChannelSuccess),
+
+    /// The `SSH_MSG_CHANNEL_FAILURE` message.
+    ChannelFailure(ChannelFailure),
+}
+
+impl<'b> Message<'b> {
+    /// Decode the [`Message`] in the `packet` by dispatching on it's message number,
+    /// failing if it is not a known message of this part of the protocol.
+    pub fn from_packet(packet: &'b Packet<'_>) -> Result<Self, DecodeError> {
+        Self::dispatch(packet, &Default::default())
+            .unwrap_or_else(|| Err(message::unexpected(packet)))
+    }
+
+    pub(crate) fn dispatch(
+        packet: &'b Packet<'_>,
+        options: &crate::DecodeOptions,
+    ) -> Option<Result<Self, DecodeError>> {
+        Some(match packet.payload.first()? {
+            80 => packet.to_with(options).map(Self::GlobalRequest),
+            81 => Ok(Self::RequestSuccess(packet.as_borrow())),
+            82 => packet.to_with(options).map(Self::RequestFailure),
+            90 => packet.to_with(options).map(Self::ChannelOpen),
+            91 => packet.to_with(options).map(Self::ChannelOpenConfirmation),
+            92 => packet.to_with(options).map(Self::ChannelOpenFailure),
+            93 => packet.to_with(options).map(Self::ChannelWindowAdjust),
+            94 => packet.to_with(options).map(Self::ChannelData),
+            95 => packet.to_with(options).map(Self::ChannelExtendedData),
+            96 => packet.to_with(options).map(Self::ChannelEof),
+            97 => packet.to_with(options).map(Self::ChannelClose),
+            98 => packet.to_with(options).map(Self::ChannelRequest),
+            99 => packet.to_with(options).map(Self::ChannelSuccess),
+            100 => packet.to_with(options).map(Self::ChannelFailure),
+            _ => return None,
+        })
+    }
+}
+
+impl BinWrite for Message<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> binrw::BinResult<()> {
+        match self {
+            Self::GlobalRequest(message) => message.write_options(writer, endian, args),
+            Self::RequestFailure(message) => message.write_options(writer, endian, args),
+            Self::ChannelOpen(message) => message.write_options(writer, endian, args),
+            Self::ChannelOpenConfirmation(message) => message.write_options(writer, endian, args),
+            Self::ChannelOpenFailure(message) => message.write_options(writer, endian, args),
+            Self::ChannelWindowAdjust(message) => message.write_options(writer, endian, args),
+            Self::ChannelData(message) => message.write_options(writer, endian, args),
+            Self::ChannelExtendedData(message) => message.write_options(writer, endian, args),
+            Self::ChannelEof(message) => message.write_options(writer, endian, args),
+            Self::ChannelClose(message) => message.write_options(writer, endian, args),
+            Self::ChannelRequest(message) => message.write_options(writer, endian, args),
+            Self::ChannelSuccess(message) => message.write_options(writer, endian, args),
+            Self::ChannelFailure(message) => message.write_options(writer, endian, args),
+            Self::RequestSuccess(packet) => Ok(writer.write_all(&packet.payload)?),
+        }
+    }
+}
+
+impl WriteEndian for Message<'_> {
+    const ENDIAN: binrw::meta::EndianKind = binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+}
+
+/// The outcome of a request wanting a reply, carrying the token it was queued with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Reply<T> {
+    /// The request succeeded.
+    Success(T),
+
+    /// The request failed.
+    Failure(T),
+}
+
+/// A reply was received while no request was waiting for one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("Received a reply to a request that didn't want one, on channel {channel:?}")]
+pub struct UnsolicitedReply {
+    /// The local channel the reply was received on, or [`None`] for a global request.
+    pub channel: Option<u32>,
+}
+
+impl UnsolicitedReply {
+    /// Create a [`trans::Disconnect`](crate::trans::Disconnect) message to be sent to the peer for this error.
+    pub fn to_disconnect(&self) -> crate::trans::Disconnect<'static> {
+        crate::trans::Disconnect::protocol_error(self.to_string())
+    }
+}
+
+/// A queue correlating the [`GlobalRequest`]s and [`ChannelRequest`]s sent wanting a reply
+/// to the replies received, each associated to a caller-provided token of type `T`.
+///
+/// Replies to global requests are received in the order the requests were sent,
+/// and so are the replies to the requests on a single channel.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4254#section-4> and
+/// <https://datatracker.ietf.org/doc/html/rfc4254#section-5.4>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingReplies<T> {
+    global: VecDeque<T>,
+    channels: BTreeMap<u32, VecDeque<T>>,
+}
+
+impl<T> Default for PendingReplies<T> {
+    fn default() -> Self {
+        Self {
+            global: Default::default(),
+            channels: Default::default(),
+        }
+    }
+}
+
+impl<T> PendingReplies<T> {
+    /// Create an empty [`PendingReplies`] queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the `token` for the sent `request` if it wants a reply, returning whether it was queued.
+    pub fn global_request(&mut self, request: &GlobalRequest<'_>, token: T) -> bool {
+        if request.reply_expected() {
+            self.global.push_back(token);
+        }
+
+        request.reply_expected()
+    }
+
+    /// Queue the `token` for the `request` sent on the `local_channel` if it wants a reply,
+    /// returning whether it was queued.
+    ///
+    /// The `local_channel` is the one the peer sends it's replies to,
+    /// not the [`ChannelRequest::recipient_channel`].
+    pub fn channel_request(
+        &mut self,
+        local_channel: u32,
+        request: &ChannelRequest<'_>,
+        token: T,
+    ) -> bool {
+        if request.reply_expected() {
+            self.channels
+                .entry(local_channel)
+                .or_default()
+                .push_back(token);
+        }
+
+        request.reply_expected()
+    }
+
+    /// Correlate the received `message` to the token of the request it replies to,
+    /// or return [`None`] if it's not a reply.
+    pub fn reply(&mut self, message: &Message<'_>) -> Result<Option<Reply<T>>, UnsolicitedReply> {
+        let (channel, success) = match message {
+            Message::RequestSuccess(_) => (None, true),
+            Message::RequestFailure(_) => (None, false),
+            Message::ChannelSuccess(ChannelSuccess { recipient_channel }) => {
+                (Some(*recipient_channel), true)
+            }
+            Message::ChannelFailure(ChannelFailure { recipient_channel }) => {
+                (Some(*recipient_channel), false)
+            }
+            _ => return Ok(None),
+        };
+
+        let token = match channel {
+            None => self.global.pop_front(),
+            Some(channel) => match self.channels.entry(channel) {
+                btree_map::Entry::Occupied(mut queue) => {
+                    let token = queue.get_mut().pop_front();
+                    if queue.get().is_empty() {
+                        queue.remove();
+                    }
+
+                    token
+                }
+                btree_map::Entry::Vacant(_) => None,
+            },
+        }
+        .ok_or(UnsolicitedReply { channel })?;
+
+        Ok(Some(if success {
+            Reply::Success(token)
+        } else {
+            Reply::Failure(token)
+        }))
+    }
+
+    /// Remove the tokens of the requests still waiting for a reply on the closed `local_channel`.
+    pub fn close(&mut self, local_channel: u32) -> impl Iterator<Item = T> {
+        self.channels.remove(&local_channel).into_iter().flatten()
+    }
+
+    /// The number of requests waiting for a reply.
+    pub fn len(&self) -> usize {
+        self.global.len() + self.channels.values().map(VecDeque::len).sum::<usize>()
+    }
+
+    /// Whether no request is waiting for a reply.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The kind of probes sent by a [`Keepalive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeepaliveProbe {
+    /// A [`GlobalRequest`] of type `keepalive@openssh.com` wanting a reply.
+    GlobalRequest,
+
+    /// An empty [`trans::Ignore`](crate::trans::Ignore) message, which is never replied to,
+    /// relying on any traffic from the peer to prove it's liveness.
+    Ignore,
+}
+
+/// Too many probes were sent by a [`Keepalive`] without hearing from the peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("The peer didn't answer {unanswered} keepalive probes")]
+pub struct KeepaliveTimeout {
+    /// The number of probes sent since the peer was last heard from.
+    pub unanswered: usize,
+}
+
+impl KeepaliveTimeout {
+    /// Create a [`trans::Disconnect`](crate::trans::Disconnect) message to be sent to the peer for this error.
+    pub fn to_disconnect(&self) -> crate::trans::Disconnect<'static> {
+        crate::trans::Disconnect::new(
+            crate::trans::DisconnectReason::ConnectionLost,
+            self.to_string(),
+        )
+    }
+}
+
+/// A generator of keepalive probes, to detect an unresponsive peer
+/// in the fashion of OpenSSH's `ServerAlive` and `ClientAlive` options.
+///
+/// The cadence is driven by the caller, which is to [`Keepalive::probe`] each time
+/// the connection has been idle for it's interval, and to feed every received message
+/// to [`Keepalive::recv`], which recognizes the replies to the probes.
+///
+/// Replies to global requests are received in order, so the replies to the probes
+/// are only recognized when no other global request is waiting for a reply,
+/// otherwise they are to be correlated with [`PendingReplies`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Keepalive {
+    kind: KeepaliveProbe,
+    max_unanswered: usize,
+    unanswered: usize,
+    in_flight: usize,
+}
+
+impl Keepalive {
+    /// Create a [`Keepalive`] sending probes of `kind`, and timing out
+    /// after `max_unanswered` probes were sent without hearing from the peer.
+    pub fn new(kind: KeepaliveProbe, max_unanswered: usize) -> Self {
+        Self {
+            kind,
+            max_unanswered,
+            unanswered: 0,
+            in_flight: 0,
+        }
+    }
+
+    /// Produce the next probe to be sent to the peer, or a [`KeepaliveTimeout`]
+    /// if the peer didn't answer the `max_unanswered` previous ones.
+    pub fn probe(&mut self) -> Result<crate::Message<'static>, KeepaliveTimeout> {
+        if self.unanswered >= self.max_unanswered {
+            return Err(KeepaliveTimeout {
+                unanswered: self.unanswered,
+            });
+        }
+        self.unanswered += 1;
+
+        Ok(match self.kind {
+            KeepaliveProbe::GlobalRequest => {
+                self.in_flight += 1;
+
+                crate::Message::Connect(Message::GlobalRequest(
+                    GlobalRequest::new(GlobalRequestContext::KeepaliveOpenssh).want_reply(true),
+                ))
+            }
+            KeepaliveProbe::Ignore => crate::Message::Trans(crate::trans::Message::Ignore(
+                crate::trans::Ignore::default(),
+            )),
+        })
+    }
+
+    /// Account for the received `message` as a sign of liveness of the peer,
+    /// returning whether it's a reply to a probe, to be discarded by the caller.
+    pub fn recv(&mut self, message: &crate::Message<'_>) -> bool {
+        self.unanswered = 0;
+
+        match message {
+            crate::Message::Connect(Message::RequestSuccess(_) | Message::RequestFailure(_))
+                if self.in_flight > 0 =>
+            {
+                self.in_flight -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+owned::into_owned!(
+    RequestSuccess,
+    ForwardingSuccess,
+    RequestFailure,
+    ChannelOpenConfirmation,
+    ChannelWindowAdjust,
+    ChannelEof,
+    ChannelClose,
+    ChannelSuccess,
+    ChannelFailure,
+);
+owned::into_owned!(
+    GlobalRequest { context; want_reply },
+    ChannelOpen { context; sender_channel, initial_window_size, maximum_packet_size },
+    ChannelOpenFailure { description, language; recipient_channel, reason },
+    ChannelData { data; recipient_channel },
+    ChannelExtendedData { data; recipient_channel, data_type },
+    ChannelRequest { context; recipient_channel, want_reply },
+);
+
+impl IntoOwned for GlobalRequestContext<'_> {
+    type Owned = GlobalRequestContext<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::TcpipForward { bind } => GlobalRequestContext::TcpipForward {
+                bind: bind.into_owned(),
+            },
+            Self::CancelTcpipForward { bind } => GlobalRequestContext::CancelTcpipForward {
+                bind: bind.into_owned(),
+            },
+            Self::KeepaliveOpenssh => GlobalRequestContext::KeepaliveOpenssh,
+        }
+    }
+}
+
+impl IntoOwned for ChannelOpenContext<'_> {
+    type Owned = ChannelOpenContext<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::Session => ChannelOpenContext::Session,
+            Self::X11 {
+                originator_address,
+                originator_port,
+            } => ChannelOpenContext::X11 {
+                originator_address: originator_address.into_owned(),
+                originator_port,
+            },
+            Self::ForwardedTcpip {
+                address,
+                originator,
+            } => ChannelOpenContext::ForwardedTcpip {
+                address: address.into_owned(),
+                originator: originator.into_owned(),
+            },
+            Self::DirectTcpip {
+                address,
+                originator,
+            } => ChannelOpenContext::DirectTcpip {
+                address: address.into_owned(),
+                originator: originator.into_owned(),
+            },
+        }
+    }
+}
+
+impl IntoOwned for ChannelRequestContext<'_> {
+    type Owned = ChannelRequestContext<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::Pty {
+                term,
+                width_chars,
+                height_chars,
+                width_pixels,
+                height_pixels,
+                modes,
+            } => ChannelRequestContext::Pty {
+                term: term.into_owned(),
+                width_chars,
+                height_chars,
+                width_pixels,
+                height_pixels,
+                modes: modes.into_owned(),
+            },
+            Self::X11 {
+                single_connection,
+                x11_authentication_protocol,
+                x11_authentication_cookie,
+                x11_screen_number,
+            } => ChannelRequestContext::X11 {
+                single_connection,
+                x11_authentication_protocol: x11_authentication_protocol.into_owned(),
+                x11_authentication_cookie: x11_authentication_cookie.into_owned(),
+                x11_screen_number,
+            },
+            Self::Env { name, value } => ChannelRequestContext::Env {
+                name: name.into_owned(),
+                value: value.into_owned(),
+            },
+            Self::Shell => ChannelRequestContext::Shell,
+            Self::Exec { command } => ChannelRequestContext::Exec {
+                command: command.into_owned(),
+            },
+            Self::Subsystem { name } => ChannelRequestContext::Subsystem {
+                name: name.into_owned(),
+            },
+            Self::WindowChange {
+                width_chars,
+                height_chars,
+                width_pixels,
+                height_pixels,
+            } => ChannelRequestContext::WindowChange {
+                width_chars,
+                height_chars,
+                width_pixels,
+                height_pixels,
+            },
+            Self::XonXoff { client_can_do } => ChannelRequestContext::XonXoff { client_can_do },
+            Self::Signal { name } => ChannelRequestContext::Signal {
+                name: name.into_owned(),
+            },
+            Self::ExitStatus { code } => ChannelRequestContext::ExitStatus { code },
+            Self::ExitSignal {
+                name,
+                core_dumped,
+                error_message,
+                language,
+            } => ChannelRequestContext::ExitSignal {
+                name: name.into_owned(),
+                core_dumped,
+                error_message: error_message.into_owned(),
+                language: language.into_owned(),
+            },
+        }
+    }
+}
+
+impl IntoOwned for Message<'_> {
+    type Owned = Message<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::GlobalRequest(message) => Message::GlobalRequest(message.into_owned()),
+            Self::RequestSuccess(packet) => Message::RequestSuccess(packet.into_owned()),
+            Self::RequestFailure(message) => Message::RequestFailure(message),
+            Self::ChannelOpen(message) => Message::ChannelOpen(message.into_owned()),
+            Self::ChannelOpenConfirmation(message) => Message::ChannelOpenConfirmation(message),
+            Self::ChannelOpenFailure(message) => Message::ChannelOpenFailure(message.into_owned()),
+            Self::ChannelWindowAdjust(message) => Message::ChannelWindowAdjust(message),
+            Self::ChannelData(message) => Message::ChannelData(message.into_owned()),
+            Self::ChannelExtendedData(message) => {
+                Message::ChannelExtendedData(message.into_owned())
+            }
+            Self::ChannelEof(message) => Message::ChannelEof(message),
+            Self::ChannelClose(message) => Message::ChannelClose(message),
+            Self::ChannelRequest(message) => Message::ChannelRequest(message.into_owned()),
+            Self::ChannelSuccess(message) => Message::ChannelSuccess(message),
+            Self::ChannelFailure(message) => Message::ChannelFailure(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::TryIntoPacket;
+
+    #[test]
+    fn it_correlates_replies_in_order() {
+        let mut pending = PendingReplies::new();
+
+        assert!(pending.global_request(
+            &GlobalRequest::tcpip_forward(Endpoint::borrowed("", 0).unwrap()).want_reply(true),
+            "forward"
+        ));
+        assert!(!pending.global_request(
+            &GlobalRequest::cancel_tcpip_forward(Endpoint::borrowed("", 22).unwrap()),
+            "cancel"
+        ));
+        assert!(pending.channel_request(3, &ChannelRequest::shell(7).want_reply(true), "shell"));
+        assert!(pending.channel_request(
+            3,
+            &ChannelRequest::exec(7, "ls").want_reply(true),
+            "exec"
+        ));
+        assert!(pending.channel_request(4, &ChannelRequest::shell(8).want_reply(true), "other"));
+        assert_eq!(pending.len(), 4);
+
+        let success =
+            |recipient_channel| Message::ChannelSuccess(ChannelSuccess { recipient_channel });
+        let failure =
+            |recipient_channel| Message::ChannelFailure(ChannelFailure { recipient_channel });
+
+        assert_eq!(
+            pending.reply(&success(3)),
+            Ok(Some(Reply::Success("shell")))
+        );
+        assert_eq!(pending.reply(&failure(3)), Ok(Some(Reply::Failure("exec"))));
+        assert_eq!(
+            pending.reply(&failure(3)),
+            Err(UnsolicitedReply { channel: Some(3) })
+        );
+        assert_eq!(
+            pending.reply(&Message::RequestFailure(RequestFailure)),
+            Ok(Some(Reply::Failure("forward")))
+        );
+        assert_eq!(
+            pending.reply(&Message::ChannelClose(ChannelClose {
+                recipient_channel: 4
+            })),
+            Ok(None)
+        );
+
+        assert_eq!(pending.close(4).collect::<Vec<_>>(), ["other"]);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn it_probes_the_peer_liveness() {
+        let mut keepalive = Keepalive::new(KeepaliveProbe::GlobalRequest, 2);
+
+        let probe = keepalive.probe().unwrap();
+        let packet = (&probe).try_into_packet().unwrap();
+        assert_eq!(packet.decode().unwrap(), probe);
+        assert!(matches!(
+            probe,
+            crate::Message::Connect(Message::GlobalRequest(GlobalRequest {
+                want_reply: arch::Bool(true),
+                context: GlobalRequestContext::KeepaliveOpenssh,
+            }))
+        ));
+
+        keepalive.probe().unwrap();
+        assert_eq!(
+            keepalive.probe().unwrap_err(),
+            KeepaliveTimeout { unanswered: 2 }
+        );
+
+        let failure = crate::Message::Connect(Message::RequestFailure(RequestFailure));
+        assert!(keepalive.recv(&failure));
+        assert!(keepalive.recv(&failure));
+        assert!(!keepalive.recv(&failure));
+        keepalive.probe().unwrap();
+
+        let mut keepalive = Keepalive::new(KeepaliveProbe::Ignore, 1);
+        assert!(matches!(
+            keepalive.probe().unwrap(),
+            crate::Message::Trans(crate::trans::Message::Ignore(_))
+        ));
+        keepalive.probe().unwrap_err();
+        assert!(!keepalive.recv(&failure));
+        keepalive.probe().unwrap();
+    }
+
+    #[rstest]
+    #[case(10, 4, 100, &[4, 4, 2], 90)]
+    #[case(10, 4, 6, &[4, 2], 0)]
+    #[case(10, 32768, 0, &[], 0)]
+    #[case(0, 4, 100, &[], 100)]
+    #[case(10, 0, 100, &[], 100)]
+    fn it_chunks_channel_data(
+        #[case] size: usize,
+        #[case] maximum_packet_size: u32,
+        #[case] mut window: u32,
+        #[case] sizes: &[usize],
+        #[case] remaining: u32,
+    ) {
+        let data = (0..size as u8).collect::<Vec<_>>();
+
+        let mut chunks = ChannelData::chunks(3, &data, maximum_packet_size, &mut window);
+        let messages = chunks.by_ref().collect::<Vec<_>>();
+        let remainder = chunks.remainder();
+
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| message.data.len())
+                .collect::<Vec<_>>(),
+            sizes
+        );
+        assert!(messages
+            .iter()
+            .all(|message| message.recipient_channel == 3));
+        assert_eq!(
+            messages
+                .iter()
+                .flat_map(|message| message.data.iter().copied())
+                .chain(remainder.iter().copied())
+                .collect::<Vec<_>>(),
+            data
+        );
+        assert_eq!(window, remaining);
+
+        let mut window = 100;
+        let stderr = std::num::NonZeroU32::new(1).unwrap();
+        assert!(
+            ChannelExtendedData::chunks(3, stderr, &data, 4, &mut window)
+                .all(|message| message.data_type == stderr && message.data.len() <= 4)
+        );
+    }
+
+    #[test]
+    fn it_builds_channel_messages() {
+        assert_eq!(
+            ChannelOpen::session(3, 2 * 1024 * 1024, 32768),
+            ChannelOpen {
+                sender_channel: 3,
+                initial_window_size: 2 * 1024 * 1024,
+                maximum_packet_size: 32768,
+                context: ChannelOpenContext::Session,
+            }
+        );
+
+        let request = ChannelRequest::exec(7, "uname -a").want_reply(true);
+        assert_eq!(
+            request,
+            ChannelRequest {
+                recipient_channel: 7,
+                want_reply: true.into(),
+                context: ChannelRequestContext::Exec {
+                    command: arch::Bytes::borrowed(b"uname -a"),
+                },
+            }
+        );
+        assert!(request.reply_expected());
+        assert!(!ChannelRequest::shell(7).reply_expected());
+
+        crate::assert_wire_roundtrip!(
+            ChannelRequest::window_change(7, (80, 24), (0, 0)),
+            "62 00000007 0000000d 77696e646f772d6368616e6765 00 00000050 00000018 00000000 00000000"
+        );
+    }
+
+    #[test]
+    fn it_builds_global_requests() {
+        let forward = GlobalRequest::tcpip_forward(Endpoint::borrowed("localhost", 0).unwrap())
+            .want_reply(true);
+        assert!(forward.reply_expected());
+        assert_eq!(
+            forward,
+            GlobalRequest {
+                want_reply: true.into(),
+                context: GlobalRequestContext::TcpipForward {
+                    bind: Endpoint::borrowed("localhost", 0).unwrap(),
+                },
+            }
+        );
+        assert!(!GlobalRequest::cancel_tcpip_forward(
+            Endpoint::borrowed("localhost", 8080).unwrap()
+        )
+        .reply_expected());
+    }
+
+    #[rstest]
+    #[case(
+        ChannelOpenFailureReason::UnknownChannelType,
+        "SSH_OPEN_UNKNOWN_CHANNEL_TYPE (3)"
+    )]
+    #[case(ChannelOpenFailureReason::Other(7), "unknown reason 7")]
+    fn it_displays_channel_open_failure_reasons(
+        #[case] reason: ChannelOpenFailureReason,
+        #[case] display: &str,
+    ) {
+        assert_eq!(reason.to_string(), display);
+        assert!(!reason.is_private_use());
+    }
+
+    #[test]
+    fn it_constructs_failures() {
+        let failure = ChannelOpenFailure::unknown_channel_type(4, "no such type");
+        assert_eq!(failure.recipient_channel, 4);
+        assert_eq!(
+            failure.reason.code(),
+            ChannelOpenFailureReason::UnknownChannelType.code()
+        );
+        assert_eq!(&*failure.description, "no such type");
+        assert!(failure.language.is_empty());
+    }
+
+    #[test]
+    fn it_generates_random_fields() {
+        use rand_core::OsRng;
+
+        let cookie = ChannelRequestContext::fake_x11_cookie_with_rng(&mut OsRng);
+        assert_eq!(cookie.len(), 32);
+        assert!(cookie
+            .iter()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(byte)));
+    }
+}
@@ -0,0 +1,195 @@
+use std::net::{IpAddr, SocketAddr};
+
+use binrw::binrw;
+
+use crate::{arch, IntoOwned};
+
+/// Errors which can occur when creating an [`Endpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum EndpointError {
+    /// The host is neither empty, an IP address nor a host name.
+    #[error("The host wasn't an IP address or a host name")]
+    Host,
+
+    /// The port is out of the `0..=65535` range.
+    #[error("The port {0} is out of range")]
+    Port(u32),
+}
+
+/// A host and port pair, as used by the TCP/IP forwarding requests and channels.
+///
+/// The host is either empty, an IP address or a host name,
+/// and the port is in the `0..=65535` range, although encoded as an `uint32`.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4254#section-7>.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct Endpoint<'b> {
+    #[br(assert(Endpoint::is_valid_host(&host), "invalid host {host:?}"), args_raw(options))]
+    host: arch::Ascii<'b>,
+
+    #[br(assert(port <= u16::MAX.into(), "port {port} is out of range"))]
+    port: u32,
+}
+
+impl<'b> Endpoint<'b> {
+    /// The maximum length of a host name.
+    const MAX_HOST_SIZE: usize = 255;
+
+    /// Create an [`Endpoint`] from a `host` and a `port`.
+    pub fn new(host: arch::Ascii<'b>, port: u32) -> Result<Self, EndpointError> {
+        if !Self::is_valid_host(&host) {
+            return Err(EndpointError::Host);
+        }
+        if port > u16::MAX.into() {
+            return Err(EndpointError::Port(port));
+        }
+
+        Ok(Self { host, port })
+    }
+
+    /// Create an [`Endpoint`] from a `host` [`&str`] and a `port`.
+    pub fn borrowed(host: &'b str, port: u32) -> Result<Self, EndpointError> {
+        Self::new(
+            arch::Ascii::borrowed(host).map_err(|_| EndpointError::Host)?,
+            port,
+        )
+    }
+
+    /// Create an [`Endpoint`] from a `host` [`String`] and a `port`.
+    pub fn owned(host: String, port: u32) -> Result<Self, EndpointError> {
+        Self::new(
+            arch::Ascii::owned(host).map_err(|_| EndpointError::Host)?,
+            port,
+        )
+    }
+
+    /// The host, either empty, an IP address or a host name.
+    pub fn host(&self) -> &arch::Ascii<'b> {
+        &self.host
+    }
+
+    /// The port.
+    pub fn port(&self) -> u16 {
+        self.port as u16
+    }
+
+    /// The host as an IP address, if it is one.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.host.parse().ok()
+    }
+
+    /// Convert the [`Endpoint`] to a [`SocketAddr`], if the host is an IP address.
+    pub fn to_socket_addr(&self) -> Option<SocketAddr> {
+        self.ip().map(|ip| SocketAddr::new(ip, self.port()))
+    }
+
+    fn is_valid_host(host: &str) -> bool {
+        host.len() <= Self::MAX_HOST_SIZE
+            && (host.parse::<IpAddr>().is_ok()
+                || host
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_')))
+    }
+}
+
+impl From<SocketAddr> for Endpoint<'static> {
+    fn from(addr: SocketAddr) -> Self {
+        Self {
+            host: arch::Ascii::owned(addr.ip().to_string())
+                .expect("IP addresses are ASCII-formatted"),
+            port: addr.port().into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.ip() {
+            Some(IpAddr::V6(_)) => write!(f, "[{}]:{}", self.host, self.port),
+            _ => write!(f, "{}:{}", self.host, self.port),
+        }
+    }
+}
+
+impl IntoOwned for Endpoint<'_> {
+    type Owned = Endpoint<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Endpoint {
+            host: self.host.into_owned(),
+            port: self.port,
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Endpoint<'_> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        SocketAddr::arbitrary(u).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{connect, TryIntoPacket};
+
+    #[rstest]
+    #[case("", 0, None)]
+    #[case("localhost", 22, None)]
+    #[case("127.0.0.1", 8080, Some("127.0.0.1:8080"))]
+    #[case("::1", 443, Some("[::1]:443"))]
+    fn it_creates_endpoints(#[case] host: &str, #[case] port: u32, #[case] addr: Option<&str>) {
+        let endpoint = Endpoint::borrowed(host, port).unwrap();
+
+        assert_eq!(
+            endpoint.to_socket_addr(),
+            addr.map(|addr| addr.parse().unwrap())
+        );
+        if let Some(addr) = endpoint.to_socket_addr() {
+            assert_eq!(Endpoint::from(addr), endpoint);
+            assert_eq!(endpoint.to_string(), addr.to_string());
+        }
+    }
+
+    #[rstest]
+    #[case("localhost", 65536, EndpointError::Port(65536))]
+    #[case("local host", 22, EndpointError::Host)]
+    #[case("localhöst", 22, EndpointError::Host)]
+    fn it_rejects_invalid_endpoints(
+        #[case] host: &str,
+        #[case] port: u32,
+        #[case] error: EndpointError,
+    ) {
+        assert_eq!(Endpoint::borrowed(host, port).unwrap_err(), error);
+    }
+
+    #[test]
+    fn it_validates_decoded_endpoints() {
+        let open = connect::ChannelOpen::direct_tcpip(
+            0,
+            0,
+            0,
+            Endpoint::borrowed("example.com", 22).unwrap(),
+            Endpoint::borrowed("10.0.0.1", 50000).unwrap(),
+        );
+        let packet = (&open).try_into_packet().unwrap();
+        assert_eq!(packet.to::<connect::ChannelOpen>().unwrap(), open);
+
+        let mut payload = packet.payload.to_vec();
+        let at = payload.len() - 4;
+        payload[at..].copy_from_slice(&65536_u32.to_be_bytes());
+        crate::Packet::from(payload)
+            .to::<connect::ChannelOpen>()
+            .unwrap_err();
+    }
+}
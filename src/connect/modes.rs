@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+
+use binrw::{
+    meta::{EndianKind, ReadEndian, WriteEndian},
+    BinRead, BinResult, BinWrite,
+};
+
+use crate::arch;
+
+/// Declare the [`TerminalMode`] opcodes, along with the [`TerminalModes`] builder methods to set them.
+macro_rules! modes {
+    ($($(#[$meta:meta])* $variant:ident = $opcode:literal => $method:ident($ty:ty),)*) => {
+        /// An opcode of the encoded terminal modes, with it's `uint32` argument.
+        ///
+        /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-8>.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[repr(u8)]
+        pub enum TerminalMode {
+            $($(#[$meta])* $variant = $opcode,)*
+        }
+
+        impl TerminalMode {
+            /// Get the [`TerminalMode`] for the `opcode`, if known.
+            pub fn from_opcode(opcode: u8) -> Option<Self> {
+                match opcode {
+                    $($opcode => Some(Self::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl TerminalModes {
+            $(
+                #[doc = concat!("Set the [`TerminalMode::", stringify!($variant), "`] mode.")]
+                pub fn $method(self, value: $ty) -> Self {
+                    self.with(TerminalMode::$variant, u32::from(value))
+                }
+            )*
+        }
+    };
+}
+
+modes! {
+    /// Interrupt character, `255` if none.
+    Vintr = 1 => vintr(u8),
+    /// The quit character, sending `SIGQUIT`.
+    Vquit = 2 => vquit(u8),
+    /// Erase the character to the left of the cursor.
+    Verase = 3 => verase(u8),
+    /// Kill the current input line.
+    Vkill = 4 => vkill(u8),
+    /// End-of-file character, sending EOF from the terminal.
+    Veof = 5 => veof(u8),
+    /// End-of-line character in addition to carriage return and/or linefeed.
+    Veol = 6 => veol(u8),
+    /// Additional end-of-line character.
+    Veol2 = 7 => veol2(u8),
+    /// Continues paused output, normally `^Q`.
+    Vstart = 8 => vstart(u8),
+    /// Pauses output, normally `^S`.
+    Vstop = 9 => vstop(u8),
+    /// Suspends the current program.
+    Vsusp = 10 => vsusp(u8),
+    /// Another suspend character.
+    Vdsusp = 11 => vdsusp(u8),
+    /// Reprints the current input line.
+    Vreprint = 12 => vreprint(u8),
+    /// Erases a word left of the cursor.
+    Vwerase = 13 => vwerase(u8),
+    /// Enter the next character typed literally.
+    Vlnext = 14 => vlnext(u8),
+    /// Character to flush output.
+    Vflush = 15 => vflush(u8),
+    /// Switch to a different shell layer.
+    Vswtch = 16 => vswtch(u8),
+    /// Prints system status line.
+    Vstatus = 17 => vstatus(u8),
+    /// Toggles the flushing of terminal output.
+    Vdiscard = 18 => vdiscard(u8),
+    /// Ignore parity errors.
+    Ignpar = 30 => ignpar(bool),
+    /// Mark parity and framing errors.
+    Parmrk = 31 => parmrk(bool),
+    /// Enable checking of parity errors.
+    Inpck = 32 => inpck(bool),
+    /// Strip the 8th bit off characters.
+    Istrip = 33 => istrip(bool),
+    /// Map NL into CR on input.
+    Inlcr = 34 => inlcr(bool),
+    /// Ignore CR on input.
+    Igncr = 35 => igncr(bool),
+    /// Map CR to NL on input.
+    Icrnl = 36 => icrnl(bool),
+    /// Translate uppercase characters to lowercase.
+    Iuclc = 37 => iuclc(bool),
+    /// Enable output flow control.
+    Ixon = 38 => ixon(bool),
+    /// Any char will restart after stop.
+    Ixany = 39 => ixany(bool),
+    /// Enable input flow control.
+    Ixoff = 40 => ixoff(bool),
+    /// Ring bell on input queue full.
+    Imaxbel = 41 => imaxbel(bool),
+    /// The terminal input and output are assumed to be encoded in UTF-8,
+    /// see <https://datatracker.ietf.org/doc/html/rfc8160>.
+    Iutf8 = 42 => iutf8(bool),
+    /// Enable signals `INTR`, `QUIT`, `[D]SUSP`.
+    Isig = 50 => isig(bool),
+    /// Canonicalize input lines.
+    Icanon = 51 => icanon(bool),
+    /// Enable input and output of uppercase characters by preceding their lowercase equivalents with `\`.
+    Xcase = 52 => xcase(bool),
+    /// Enable echoing.
+    Echo = 53 => echo(bool),
+    /// Visually erase chars.
+    Echoe = 54 => echoe(bool),
+    /// Kill character discards current line.
+    Echok = 55 => echok(bool),
+    /// Echo NL even if `ECHO` is off.
+    Echonl = 56 => echonl(bool),
+    /// Don't flush after interrupt.
+    Noflsh = 57 => noflsh(bool),
+    /// Stop background jobs from output.
+    Tostop = 58 => tostop(bool),
+    /// Enable extensions.
+    Iexten = 59 => iexten(bool),
+    /// Echo control characters as `^(Char)`.
+    Echoctl = 60 => echoctl(bool),
+    /// Visual erase for line kill.
+    Echoke = 61 => echoke(bool),
+    /// Retype pending input.
+    Pendin = 62 => pendin(bool),
+    /// Enable output processing.
+    Opost = 70 => opost(bool),
+    /// Convert lowercase to uppercase.
+    Olcuc = 71 => olcuc(bool),
+    /// Map NL to CR-NL.
+    Onlcr = 72 => onlcr(bool),
+    /// Translate carriage return to newline on output.
+    Ocrnl = 73 => ocrnl(bool),
+    /// Translate newline to carriage return-newline on output.
+    Onocr = 74 => onocr(bool),
+    /// Newline performs a carriage return on output.
+    Onlret = 75 => onlret(bool),
+    /// 7 bit mode.
+    Cs7 = 90 => cs7(bool),
+    /// 8 bit mode.
+    Cs8 = 91 => cs8(bool),
+    /// Parity enable.
+    Parenb = 92 => parenb(bool),
+    /// Odd parity, else even.
+    Parodd = 93 => parodd(bool),
+    /// Specifies the input baud rate in bits per second.
+    TtyOpIspeed = 128 => ispeed(u32),
+    /// Specifies the output baud rate in bits per second.
+    TtyOpOspeed = 129 => ospeed(u32),
+}
+
+/// The encoded terminal modes of a [`ChannelRequestContext::Pty`](super::ChannelRequestContext::Pty) request,
+/// built from named options rather than hand-assembled opcodes.
+///
+/// Unknown opcodes are preserved when decoding, up to the first opcode
+/// in the `160..=255` range, which ends the parsing since their argument is undefined.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4254#section-8>.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct TerminalModes(BTreeMap<u8, u32>);
+
+impl TerminalModes {
+    /// The opcode ending the encoded terminal modes.
+    const TTY_OP_END: u8 = 0;
+
+    /// The first opcode whose argument is undefined.
+    const UNDEFINED: u8 = 160;
+
+    /// Create an empty set of [`TerminalModes`], leaving every mode to the server's default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the `mode` to `value`.
+    pub fn with(mut self, mode: TerminalMode, value: u32) -> Self {
+        self.0.insert(mode as u8, value);
+        self
+    }
+
+    /// Set both the input and output baud rates to `speed`, in bits per second.
+    pub fn speed(self, speed: u32) -> Self {
+        self.ispeed(speed).ospeed(speed)
+    }
+
+    /// Get the value of the `mode`, if set.
+    pub fn get(&self, mode: TerminalMode) -> Option<u32> {
+        self.0.get(&(mode as u8)).copied()
+    }
+
+    /// Iterate over the opcodes and their values, including unknown ones, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u32)> + '_ {
+        self.0.iter().map(|(opcode, value)| (*opcode, *value))
+    }
+}
+
+impl BinRead for TerminalModes {
+    type Args<'a> = ();
+
+    fn read_options<R: std::io::Read + std::io::Seek>(
+        reader: &mut R,
+        _endian: binrw::Endian,
+        _args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let mut modes = BTreeMap::new();
+
+        loop {
+            let opcode = u8::read_be(reader)?;
+            if opcode == Self::TTY_OP_END || opcode >= Self::UNDEFINED {
+                break;
+            }
+
+            modes.insert(opcode, u32::read_be(reader)?);
+        }
+
+        Ok(Self(modes))
+    }
+}
+
+impl ReadEndian for TerminalModes {
+    const ENDIAN: EndianKind = EndianKind::Endian(binrw::Endian::Big);
+}
+
+impl BinWrite for TerminalModes {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        _endian: binrw::Endian,
+        _args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        for (opcode, value) in self.iter() {
+            opcode.write_be(writer)?;
+            value.write_be(writer)?;
+        }
+
+        Self::TTY_OP_END.write_be(writer)
+    }
+}
+
+impl WriteEndian for TerminalModes {
+    const ENDIAN: EndianKind = EndianKind::Endian(binrw::Endian::Big);
+}
+
+impl From<&TerminalModes> for arch::Bytes<'static> {
+    fn from(modes: &TerminalModes) -> Self {
+        let mut buf = std::io::Cursor::new(Vec::with_capacity(modes.0.len() * 5 + 1));
+        modes
+            .write(&mut buf)
+            .expect("writing to a `Vec` is infallible");
+
+        arch::Bytes::owned(buf.into_inner())
+    }
+}
+
+impl From<TerminalModes> for arch::Bytes<'static> {
+    fn from(modes: TerminalModes) -> Self {
+        (&modes).into()
+    }
+}
+
+impl TryFrom<&[u8]> for TerminalModes {
+    type Error = binrw::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::read(&mut std::io::Cursor::new(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use super::*;
+    use crate::{connect, TryIntoPacket};
+
+    #[test]
+    fn it_builds_terminal_modes() {
+        let modes = TerminalModes::new()
+            .vintr(3)
+            .echo(true)
+            .icanon(false)
+            .speed(38400);
+
+        assert_eq!(
+            &*arch::Bytes::from(&modes),
+            &[
+                1, 0, 0, 0, 3, 51, 0, 0, 0, 0, 53, 0, 0, 0, 1, 128, 0, 0, 150, 0, 129, 0, 0, 150,
+                0, 0
+            ]
+        );
+        assert_eq!(modes.get(TerminalMode::Echo), Some(1));
+        assert_eq!(modes.get(TerminalMode::Isig), None);
+
+        let request = connect::ChannelRequest::pty(0, "xterm", (80, 24), (0, 0), &modes);
+        let packet = (&request).try_into_packet().unwrap();
+        let connect::ChannelRequestContext::Pty { modes: encoded, .. } =
+            packet.to::<connect::ChannelRequest>().unwrap().context
+        else {
+            unimplemented!()
+        };
+        assert_eq!(TerminalModes::try_from(&*encoded).unwrap(), modes);
+    }
+
+    #[test]
+    fn it_parses_unknown_opcodes() {
+        let modes = TerminalModes::try_from(&[99, 0, 0, 0, 7, 53, 0, 0, 0, 1, 200, 1][..]).unwrap();
+
+        assert_eq!(modes.iter().collect::<Vec<_>>(), [(53, 1), (99, 7)]);
+        assert_eq!(TerminalMode::from_opcode(53), Some(TerminalMode::Echo));
+        assert_eq!(TerminalMode::from_opcode(99), None);
+
+        TerminalModes::try_from(&[53, 0, 0][..]).unwrap_err();
+    }
+}
@@ -0,0 +1,207 @@
+use binrw::{
+    meta::{EndianKind, ReadEndian, WriteEndian},
+    BinRead, BinResult, BinWrite,
+};
+
+use crate::{arch, IntoOwned};
+
+/// The name of a signal in the [`ChannelRequestContext::Signal`](super::ChannelRequestContext::Signal)
+/// and [`ChannelRequestContext::ExitSignal`](super::ChannelRequestContext::ExitSignal) requests,
+/// without the `SIG` prefix.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4254#section-6.10>.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum SignalName<'b> {
+    /// `SIGABRT`, the process aborted.
+    Abrt,
+
+    /// `SIGALRM`, a timer expired.
+    Alrm,
+
+    /// `SIGFPE`, an erroneous arithmetic operation.
+    Fpe,
+
+    /// `SIGHUP`, the controlling terminal hung up.
+    Hup,
+
+    /// `SIGILL`, an illegal instruction.
+    Ill,
+
+    /// `SIGINT`, an interrupt from the terminal.
+    Int,
+
+    /// `SIGKILL`, the process is killed.
+    Kill,
+
+    /// `SIGPIPE`, a write to a pipe with no reader.
+    Pipe,
+
+    /// `SIGQUIT`, a quit from the terminal.
+    Quit,
+
+    /// `SIGSEGV`, an invalid memory reference.
+    Segv,
+
+    /// `SIGTERM`, the process is asked to terminate.
+    Term,
+
+    /// `SIGUSR1`, the first user-defined signal.
+    Usr1,
+
+    /// `SIGUSR2`, the second user-defined signal.
+    Usr2,
+
+    /// Any other signal name, such as the local ones in the `name@domain` form.
+    Other(arch::Ascii<'b>),
+}
+
+impl SignalName<'_> {
+    /// The signal name as encoded in the requests.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Abrt => "ABRT",
+            Self::Alrm => "ALRM",
+            Self::Fpe => "FPE",
+            Self::Hup => "HUP",
+            Self::Ill => "ILL",
+            Self::Int => "INT",
+            Self::Kill => "KILL",
+            Self::Pipe => "PIPE",
+            Self::Quit => "QUIT",
+            Self::Segv => "SEGV",
+            Self::Term => "TERM",
+            Self::Usr1 => "USR1",
+            Self::Usr2 => "USR2",
+            Self::Other(name) => name,
+        }
+    }
+}
+
+impl<'b> From<arch::Ascii<'b>> for SignalName<'b> {
+    fn from(name: arch::Ascii<'b>) -> Self {
+        match &*name {
+            "ABRT" => Self::Abrt,
+            "ALRM" => Self::Alrm,
+            "FPE" => Self::Fpe,
+            "HUP" => Self::Hup,
+            "ILL" => Self::Ill,
+            "INT" => Self::Int,
+            "KILL" => Self::Kill,
+            "PIPE" => Self::Pipe,
+            "QUIT" => Self::Quit,
+            "SEGV" => Self::Segv,
+            "TERM" => Self::Term,
+            "USR1" => Self::Usr1,
+            "USR2" => Self::Usr2,
+            _ => Self::Other(name),
+        }
+    }
+}
+
+impl<'b> TryFrom<&'b str> for SignalName<'b> {
+    type Error = arch::AsciiError;
+
+    fn try_from(name: &'b str) -> Result<Self, Self::Error> {
+        arch::Ascii::borrowed(name).map(Into::into)
+    }
+}
+
+impl std::fmt::Display for SignalName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl IntoOwned for SignalName<'_> {
+    type Owned = SignalName<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::Abrt => SignalName::Abrt,
+            Self::Alrm => SignalName::Alrm,
+            Self::Fpe => SignalName::Fpe,
+            Self::Hup => SignalName::Hup,
+            Self::Ill => SignalName::Ill,
+            Self::Int => SignalName::Int,
+            Self::Kill => SignalName::Kill,
+            Self::Pipe => SignalName::Pipe,
+            Self::Quit => SignalName::Quit,
+            Self::Segv => SignalName::Segv,
+            Self::Term => SignalName::Term,
+            Self::Usr1 => SignalName::Usr1,
+            Self::Usr2 => SignalName::Usr2,
+            Self::Other(name) => SignalName::Other(name.into_owned()),
+        }
+    }
+}
+
+impl BinRead for SignalName<'_> {
+    type Args<'a> = crate::DecodeOptions;
+
+    fn read_options<R: std::io::Read + std::io::Seek>(
+        reader: &mut R,
+        _endian: binrw::Endian,
+        options: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        arch::Ascii::read_options(reader, binrw::Endian::Big, options).map(Into::into)
+    }
+}
+
+impl ReadEndian for SignalName<'_> {
+    const ENDIAN: EndianKind = EndianKind::Endian(binrw::Endian::Big);
+}
+
+impl BinWrite for SignalName<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        _endian: binrw::Endian,
+        _args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        match self {
+            Self::Other(name) => name.write_options(writer, binrw::Endian::Big, ()),
+            name => arch::Bytes::borrowed(name.as_str().as_bytes()).write_options(
+                writer,
+                binrw::Endian::Big,
+                (),
+            ),
+        }
+    }
+}
+
+impl WriteEndian for SignalName<'_> {
+    const ENDIAN: EndianKind = EndianKind::Endian(binrw::Endian::Big);
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{connect, TryIntoPacket};
+
+    #[rstest]
+    #[case(SignalName::Hup, "HUP")]
+    #[case(SignalName::Usr2, "USR2")]
+    #[case(SignalName::Other(arch::ascii!("INFO@example.com")), "INFO@example.com")]
+    fn it_roundtrips_signal_names(#[case] name: SignalName<'static>, #[case] encoded: &str) {
+        assert_eq!(name.to_string(), encoded);
+        assert_eq!(SignalName::try_from(encoded).unwrap(), name);
+
+        let request = connect::ChannelRequest::exit_signal(0, name.clone(), false, "");
+        let packet = (&request).try_into_packet().unwrap();
+        let connect::ChannelRequestContext::ExitSignal { name: decoded, .. } =
+            packet.to::<connect::ChannelRequest>().unwrap().context
+        else {
+            unimplemented!()
+        };
+        assert_eq!(decoded, name);
+    }
+}
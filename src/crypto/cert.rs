@@ -0,0 +1,634 @@
+//! Facilities to build and sign the OpenSSH _certificates_.
+//!
+//! see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.certkeys>.
+
+use std::io::{Read, Seek, Write};
+
+use binrw::{binrw, BinRead, BinResult, BinWrite};
+
+use crate::arch;
+
+#[cfg(feature = "signature")]
+use super::signature::SignatureBlob;
+
+mod options;
+pub use options::{CriticalOption, Extension};
+
+/// The public key being certified, with the fields of it's algorithm.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import(options: crate::DecodeOptions, algorithm: arch::Ascii<'_>))]
+pub enum CertifiedKey<'b> {
+    /// A `ssh-ed25519-cert-v01@openssh.com` key.
+    #[br(pre_assert(algorithm == CertifiedKey::ED25519))]
+    Ed25519 {
+        /// The Ed25519 public key.
+        #[br(args_raw(options))]
+        key: arch::Bytes<'b>,
+    },
+
+    /// A `sk-ssh-ed25519-cert-v01@openssh.com` security key.
+    #[br(pre_assert(algorithm == CertifiedKey::SK_ED25519))]
+    SkEd25519 {
+        /// The Ed25519 public key.
+        #[br(args_raw(options))]
+        key: arch::Bytes<'b>,
+
+        /// The application the key was enrolled for.
+        #[br(args_raw(options))]
+        application: arch::Ascii<'b>,
+    },
+
+    /// A `ssh-rsa-cert-v01@openssh.com` key.
+    #[br(pre_assert(algorithm == CertifiedKey::RSA))]
+    Rsa {
+        /// The public exponent.
+        #[br(args_raw(options))]
+        e: arch::MpInt<'b>,
+
+        /// The modulus.
+        #[br(args_raw(options))]
+        n: arch::MpInt<'b>,
+    },
+
+    /// A `sk-ecdsa-sha2-nistp256-cert-v01@openssh.com` security key.
+    #[br(pre_assert(algorithm == CertifiedKey::SK_ECDSA))]
+    SkEcdsa {
+        #[br(temp, assert(curve == CertifiedKey::NISTP256), args_raw(options))]
+        #[bw(calc = CertifiedKey::NISTP256)]
+        curve: arch::Ascii<'b>,
+
+        /// The public point, in the SEC1 encoding.
+        #[br(args_raw(options))]
+        q: arch::Bytes<'b>,
+
+        /// The application the key was enrolled for.
+        #[br(args_raw(options))]
+        application: arch::Ascii<'b>,
+    },
+
+    /// A `ecdsa-sha2-<curve>-cert-v01@openssh.com` key.
+    #[br(pre_assert(CertifiedKey::ecdsa_curve(&algorithm).is_some()))]
+    Ecdsa {
+        /// The name of the curve, such as `nistp256`.
+        #[br(assert(Some(&*curve) == CertifiedKey::ecdsa_curve(&algorithm)), args_raw(options))]
+        curve: arch::Ascii<'b>,
+
+        /// The public point, in the SEC1 encoding.
+        #[br(args_raw(options))]
+        q: arch::Bytes<'b>,
+    },
+}
+
+impl CertifiedKey<'_> {
+    const ED25519: arch::Ascii<'static> = arch::ascii!("ssh-ed25519-cert-v01@openssh.com");
+    const SK_ED25519: arch::Ascii<'static> = arch::ascii!("sk-ssh-ed25519-cert-v01@openssh.com");
+    const RSA: arch::Ascii<'static> = arch::ascii!("ssh-rsa-cert-v01@openssh.com");
+    const SK_ECDSA: arch::Ascii<'static> =
+        arch::ascii!("sk-ecdsa-sha2-nistp256-cert-v01@openssh.com");
+    const NISTP256: arch::Ascii<'static> = arch::ascii!("nistp256");
+
+    const ECDSA_PREFIX: &'static str = "ecdsa-sha2-";
+    const SUFFIX: &'static str = "-cert-v01@openssh.com";
+
+    fn ecdsa_curve(algorithm: &str) -> Option<&str> {
+        algorithm
+            .strip_prefix(Self::ECDSA_PREFIX)?
+            .strip_suffix(Self::SUFFIX)
+    }
+
+    /// Get the name of the certificate's algorithm for the [`CertifiedKey`].
+    pub fn algorithm(&self) -> arch::Ascii<'static> {
+        match self {
+            Self::Ed25519 { .. } => Self::ED25519,
+            Self::SkEd25519 { .. } => Self::SK_ED25519,
+            Self::Rsa { .. } => Self::RSA,
+            Self::SkEcdsa { .. } => Self::SK_ECDSA,
+            Self::Ecdsa { curve, .. } => {
+                arch::Ascii::owned(format!("{}{curve}{}", Self::ECDSA_PREFIX, Self::SUFFIX))
+                    .expect("The curve name is ASCII-formatted")
+            }
+        }
+    }
+}
+
+/// The type of a [`Certificate`].
+#[binrw]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[brw(big)]
+pub enum CertificateType {
+    /// A certificate for a user, to authenticate to servers.
+    #[brw(magic = 1_u32)]
+    User,
+
+    /// A certificate for a host, to authenticate to users.
+    #[brw(magic = 2_u32)]
+    Host,
+}
+
+/// A critical option or an extension of a [`Certificate`], with it's raw `data`,
+/// see [`CriticalOption`] and [`Extension`] for the known ones.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct CertificateOption<'b> {
+    /// The name of the option.
+    #[br(args_raw(options))]
+    pub name: arch::Ascii<'b>,
+
+    /// The data of the option, empty if it has none.
+    #[br(args_raw(options))]
+    pub data: arch::Bytes<'b>,
+}
+
+/// An OpenSSH certificate, of type `*-cert-v01@openssh.com`.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct Certificate<'b> {
+    #[bw(calc = key.algorithm())]
+    #[br(args_raw(options))]
+    algorithm: arch::Ascii<'b>,
+
+    /// A random nonce, to make the hash collisions impractical.
+    #[br(args_raw(options))]
+    pub nonce: arch::Bytes<'b>,
+
+    /// The public key being certified.
+    #[br(args(options, algorithm))]
+    pub key: CertifiedKey<'b>,
+
+    /// The serial number of the certificate, chosen by the CA.
+    pub serial: u64,
+
+    /// The type of the certificate.
+    pub kind: CertificateType,
+
+    /// A free-form identifier of the certificate, to be logged by the server.
+    #[br(args_raw(options))]
+    pub key_id: arch::Utf8<'b>,
+
+    /// The names the certificate is valid for, any if empty.
+    #[br(parse_with = read_packed, args_raw(options))]
+    #[bw(write_with = write_packed)]
+    pub valid_principals: Vec<arch::Utf8<'b>>,
+
+    /// The start of the validity period, in seconds since the UNIX epoch.
+    pub valid_after: u64,
+
+    /// The end of the validity period, in seconds since the UNIX epoch.
+    pub valid_before: u64,
+
+    /// The critical options, ordered by name.
+    #[br(parse_with = read_packed, args_raw(options))]
+    #[bw(write_with = write_packed)]
+    pub critical_options: Vec<CriticalOption<'b>>,
+
+    /// The extensions, ordered by name.
+    #[br(parse_with = read_packed, args_raw(options))]
+    #[bw(write_with = write_packed)]
+    pub extensions: Vec<Extension<'b>>,
+
+    /// Reserved, currently empty.
+    #[br(args_raw(options))]
+    pub reserved: arch::Bytes<'b>,
+
+    /// The public key blob of the CA that signed the certificate.
+    #[br(args_raw(options))]
+    pub signature_key: arch::Bytes<'b>,
+
+    /// The signature blob of the CA over the [`Certificate::signed_data`].
+    #[br(args_raw(options))]
+    pub signature: arch::Bytes<'b>,
+}
+
+impl Certificate<'_> {
+    /// The data that gets _signed_ and _verified_ by the CA,
+    /// which is the encoding of the certificate up to the signature.
+    pub fn signed_data(&self) -> Vec<u8> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        self.write(&mut buffer)
+            .expect("The binrw structure serialization failed");
+
+        let mut buffer = buffer.into_inner();
+        buffer.truncate(buffer.len() - std::mem::size_of::<u32>() - self.signature.len());
+
+        buffer
+    }
+
+    /// Verify the certificate was signed by the CA whose public key blob is `signature_key`,
+    /// decoding it's [`Certificate::signature`] blob as `S` to be verified with the CA's `key`.
+    ///
+    /// On success, the signature algorithm's name is returned, for the caller to reject
+    /// unwanted algorithms, in example `ssh-rsa` (with SHA-1) when expecting `rsa-sha2-*`.
+    #[cfg(feature = "signature")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "signature")))]
+    pub fn verify<S, K>(
+        &self,
+        signature_key: &[u8],
+        key: &K,
+    ) -> signature::Result<arch::Ascii<'static>>
+    where
+        S: for<'s> TryFrom<&'s [u8], Error = signature::Error>,
+        K: signature::Verifier<S>,
+    {
+        if *self.signature_key != *signature_key {
+            return Err(signature::Error::new());
+        }
+
+        let signature = SignatureBlob::decode(&self.signature)?;
+        signature.verify(&self.signed_data(), key)?;
+
+        Ok(signature.algorithm)
+    }
+}
+
+/// A builder of [`Certificate`]s, to be signed by a CA.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CertificateBuilder<'b>(Certificate<'b>);
+
+impl<'b> CertificateBuilder<'b> {
+    /// The size of the generated nonces.
+    const NONCE_SIZE: usize = 32;
+
+    #[cfg(feature = "rand")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+    /// Create a [`CertificateBuilder`] of type `kind` certifying the `key`, with a nonce
+    /// generated from the operating system, see [`CertificateBuilder::with_rng`].
+    pub fn new(key: CertifiedKey<'b>, kind: CertificateType) -> Self {
+        Self::with_rng(key, kind, &mut rand_core::OsRng)
+    }
+
+    /// Create a [`CertificateBuilder`] of type `kind` certifying the `key`, with a nonce generated from `rng`,
+    /// valid forever for any principal, and without any option or extension.
+    pub fn with_rng(
+        key: CertifiedKey<'b>,
+        kind: CertificateType,
+        rng: &mut impl rand_core::CryptoRngCore,
+    ) -> Self {
+        let mut nonce = vec![0; Self::NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+
+        Self(Certificate {
+            nonce: nonce.into(),
+            key,
+            serial: 0,
+            kind,
+            key_id: Default::default(),
+            valid_principals: Default::default(),
+            valid_after: 0,
+            valid_before: u64::MAX,
+            critical_options: Default::default(),
+            extensions: Default::default(),
+            reserved: Default::default(),
+            signature_key: Default::default(),
+            signature: Default::default(),
+        })
+    }
+
+    /// Set the `serial` number of the certificate.
+    pub fn serial(mut self, serial: u64) -> Self {
+        self.0.serial = serial;
+        self
+    }
+
+    /// Set the `key_id` of the certificate.
+    pub fn key_id(mut self, key_id: impl Into<arch::Utf8<'b>>) -> Self {
+        self.0.key_id = key_id.into();
+        self
+    }
+
+    /// Add a `principal` the certificate is valid for.
+    pub fn principal(mut self, principal: impl Into<arch::Utf8<'b>>) -> Self {
+        self.0.valid_principals.push(principal.into());
+        self
+    }
+
+    /// Set the validity period of the certificate, in seconds since the UNIX epoch.
+    pub fn validity(mut self, valid_after: u64, valid_before: u64) -> Self {
+        self.0.valid_after = valid_after;
+        self.0.valid_before = valid_before;
+        self
+    }
+
+    /// Set the critical `option`, replacing any with the same name and keeping the options ordered by name.
+    pub fn critical_option(mut self, option: CriticalOption<'b>) -> Self {
+        insert(&mut self.0.critical_options, option, CriticalOption::name);
+        self
+    }
+
+    /// Set the `extension`, replacing any with the same name and keeping the extensions ordered by name.
+    pub fn extension(mut self, extension: Extension<'b>) -> Self {
+        insert(&mut self.0.extensions, extension, Extension::name);
+        self
+    }
+
+    /// Set the public key blob of the CA signing the certificate.
+    pub fn signature_key(mut self, signature_key: impl Into<arch::Bytes<'b>>) -> Self {
+        self.0.signature_key = signature_key.into();
+        self
+    }
+
+    /// The data to be _signed_ by the CA, see [`Certificate::signed_data`].
+    pub fn signed_data(&self) -> Vec<u8> {
+        self.0.signed_data()
+    }
+
+    /// Finalize the [`Certificate`] with the `signature` blob of the [`CertificateBuilder::signed_data`].
+    pub fn finish(mut self, signature: impl Into<arch::Bytes<'b>>) -> Certificate<'b> {
+        self.0.signature = signature.into();
+        self.0
+    }
+
+    /// Sign the [`CertificateBuilder::signed_data`] with the CA's `key` to finalize the [`Certificate`],
+    /// the signature `S` being encoded as a signature blob, along with it's `algorithm` name.
+    ///
+    /// This fails if the `key` fails to sign, in example when it's held by an unavailable agent.
+    #[cfg(feature = "signature")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "signature")))]
+    pub fn sign<S, K>(
+        self,
+        algorithm: arch::Ascii<'_>,
+        key: &K,
+    ) -> signature::Result<Certificate<'b>>
+    where
+        S: signature::SignatureEncoding,
+        K: signature::Signer<S>,
+    {
+        let signature = key.try_sign(&self.signed_data())?;
+
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        SignatureBlob {
+            algorithm,
+            blob: arch::Bytes::borrowed(signature.to_bytes().as_ref()),
+        }
+        .write(&mut buffer)
+        .expect("The binrw structure serialization failed");
+
+        Ok(self.finish(buffer.into_inner()))
+    }
+}
+
+/// Insert the `option` in the `options` ordered by `name`, replacing any with the same name.
+fn insert<T>(options: &mut Vec<T>, option: T, name: impl Fn(&T) -> &str) {
+    match options.binary_search_by(|other| name(other).cmp(name(&option))) {
+        Ok(index) => options[index] = option,
+        Err(index) => options.insert(index, option),
+    }
+}
+
+/// Read the values packed in a `string`.
+fn read_packed<R, T>(
+    reader: &mut R,
+    endian: binrw::Endian,
+    options: crate::DecodeOptions,
+) -> BinResult<Vec<T>>
+where
+    R: Read + Seek,
+    T: for<'a> BinRead<Args<'a> = crate::DecodeOptions>,
+{
+    arch::Lengthed::<Packed<Vec<T>>>::read_options(reader, endian, options)
+        .map(|packed| packed.0 .0)
+}
+
+/// Write the values packed in a `string`.
+fn write_packed<W, T>(
+    values: &Vec<T>,
+    writer: &mut W,
+    endian: binrw::Endian,
+    _: (),
+) -> BinResult<()>
+where
+    W: Write + Seek,
+    T: for<'a> BinWrite<Args<'a> = ()>,
+{
+    arch::Lengthed(Packed(values.as_slice())).write_options(writer, endian, ())
+}
+
+/// Values packed one after the other, read until the end of the enclosing [`arch::Lengthed`].
+struct Packed<T>(T);
+
+impl<T> BinRead for Packed<Vec<T>>
+where
+    T: for<'a> BinRead<Args<'a> = crate::DecodeOptions>,
+{
+    type Args<'a> = crate::DecodeOptions;
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        binrw::helpers::until_eof(reader, endian, args).map(Self)
+    }
+}
+
+impl<T> BinWrite for Packed<&[T]>
+where
+    T: for<'a> BinWrite<Args<'a> = ()>,
+{
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        self.0
+            .iter()
+            .try_for_each(|value| value.write_options(writer, endian, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rand_core::OsRng;
+
+    use super::*;
+
+    fn builder() -> CertificateBuilder<'static> {
+        CertificateBuilder::with_rng(
+            CertifiedKey::Ed25519 {
+                key: vec![0x42; 32].into(),
+            },
+            CertificateType::User,
+            &mut OsRng,
+        )
+        .serial(7)
+        .key_id("user@example.com")
+        .principal("user")
+        .validity(1_700_000_000, 1_800_000_000)
+        .extension(Extension::PermitPty)
+        .extension(Extension::PermitAgentForwarding)
+        .critical_option(CriticalOption::ForceCommand("true".into()))
+        .signature_key(&b"\0\0\0\x0bssh-ed25519\0\0\0\x20"[..])
+    }
+
+    #[test]
+    fn it_builds_certificates() {
+        let builder = builder();
+        let signed_data = builder.signed_data();
+        let certificate = builder.finish(&b"signature"[..]);
+
+        assert_eq!(certificate.signed_data(), signed_data);
+        assert_eq!(
+            &signed_data[..36],
+            b"\0\0\0\x20ssh-ed25519-cert-v01@openssh.com"
+        );
+        assert_eq!(
+            certificate
+                .extensions
+                .iter()
+                .map(Extension::name)
+                .collect::<Vec<_>>(),
+            ["permit-agent-forwarding", "permit-pty"]
+        );
+
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        certificate.write(&mut buffer).unwrap();
+        let buffer = buffer.into_inner();
+        assert_eq!(&buffer[..signed_data.len()], signed_data);
+        assert_eq!(&buffer[signed_data.len()..], b"\0\0\0\x09signature");
+
+        assert_eq!(
+            Certificate::read(&mut std::io::Cursor::new(&buffer)).unwrap(),
+            certificate
+        );
+    }
+
+    #[test]
+    fn it_parses_ecdsa_certificates() {
+        let certificate = builder().0;
+        let certificate = Certificate {
+            key: CertifiedKey::Ecdsa {
+                curve: arch::ascii!("nistp384"),
+                q: vec![0x04; 97].into(),
+            },
+            ..certificate
+        };
+
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        certificate.write(&mut buffer).unwrap();
+        let mut buffer = buffer.into_inner();
+        assert_eq!(&buffer[4..44], b"ecdsa-sha2-nistp384-cert-v01@openssh.com");
+        assert_eq!(
+            Certificate::read(&mut std::io::Cursor::new(&buffer)).unwrap(),
+            certificate
+        );
+
+        // The curve follows the algorithm name and the nonce
+        let at = 4 + 40 + 4 + 32 + 4;
+        buffer[at..][..8].copy_from_slice(b"nistp521");
+        Certificate::read(&mut std::io::Cursor::new(&buffer)).unwrap_err();
+    }
+
+    /// A toy signature, valid when it equals the signed message.
+    #[cfg(feature = "signature")]
+    #[derive(Clone)]
+    struct Echo(Vec<u8>);
+
+    #[cfg(feature = "signature")]
+    impl TryFrom<&[u8]> for Echo {
+        type Error = signature::Error;
+
+        fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+            Ok(Self(value.to_vec()))
+        }
+    }
+
+    #[cfg(feature = "signature")]
+    impl From<Echo> for Vec<u8> {
+        fn from(value: Echo) -> Self {
+            value.0
+        }
+    }
+
+    #[cfg(feature = "signature")]
+    impl signature::SignatureEncoding for Echo {
+        type Repr = Vec<u8>;
+    }
+
+    #[cfg(feature = "signature")]
+    struct EchoKey;
+
+    #[cfg(feature = "signature")]
+    impl signature::Signer<Echo> for EchoKey {
+        fn try_sign(&self, msg: &[u8]) -> Result<Echo, signature::Error> {
+            Ok(Echo(msg.to_vec()))
+        }
+    }
+
+    #[cfg(feature = "signature")]
+    impl signature::Verifier<Echo> for EchoKey {
+        fn verify(&self, msg: &[u8], signature: &Echo) -> Result<(), signature::Error> {
+            if msg == signature.0 {
+                Ok(())
+            } else {
+                Err(signature::Error::new())
+            }
+        }
+    }
+
+    #[cfg(feature = "signature")]
+    #[test]
+    fn it_signs_and_verifies_certificates() {
+        let signature_key = &b"\0\0\0\x0bssh-ed25519\0\0\0\x20"[..];
+        let mut certificate = builder()
+            .sign::<Echo, _>(arch::ascii!("echo"), &EchoKey)
+            .unwrap();
+
+        assert_eq!(&certificate.signature[..8], b"\0\0\0\x04echo");
+        assert_eq!(&certificate.signature[12..], certificate.signed_data());
+        assert_eq!(
+            certificate
+                .verify::<Echo, _>(signature_key, &EchoKey)
+                .unwrap(),
+            arch::ascii!("echo")
+        );
+
+        // The certificate was signed by another CA
+        certificate
+            .verify::<Echo, _>(b"\0\0\0\x0bssh-ed25519", &EchoKey)
+            .unwrap_err();
+
+        // The signature blob is followed by trailing data
+        let mut signature = certificate.signature.to_vec();
+        signature.push(0);
+        certificate.signature = signature.into();
+        certificate
+            .verify::<Echo, _>(signature_key, &EchoKey)
+            .unwrap_err();
+
+        // The signature is over other data
+        let forged = builder()
+            .serial(8)
+            .sign::<Echo, _>(arch::ascii!("echo"), &EchoKey)
+            .unwrap();
+        certificate.signature = forged.signature;
+        certificate
+            .verify::<Echo, _>(signature_key, &EchoKey)
+            .unwrap_err();
+    }
+
+    #[cfg(feature = "signature")]
+    #[test]
+    fn it_reports_signing_failures() {
+        struct Unavailable;
+
+        impl signature::Signer<Echo> for Unavailable {
+            fn try_sign(&self, _msg: &[u8]) -> Result<Echo, signature::Error> {
+                Err(signature::Error::new())
+            }
+        }
+
+        builder()
+            .sign::<Echo, _>(arch::ascii!("echo"), &Unavailable)
+            .unwrap_err();
+    }
+}
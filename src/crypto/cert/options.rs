@@ -0,0 +1,287 @@
+use std::io::{Read, Seek, Write};
+
+use binrw::{BinRead, BinResult, BinWrite};
+
+use super::CertificateOption;
+use crate::arch;
+
+/// A critical option of a [`Certificate`](super::Certificate),
+/// to be recognized by the server for the certificate to be accepted.
+///
+/// see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.certkeys>.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CriticalOption<'b> {
+    /// The `force-command` option, replacing any command requested by the user.
+    ForceCommand(arch::Utf8<'b>),
+
+    /// The `source-address` option, as a comma-separated list of addresses in the CIDR format
+    /// the certificate is allowed to be used from.
+    SourceAddress(arch::Ascii<'b>),
+
+    /// The `verify-required` option, requiring the signatures of security keys to attest user verification.
+    VerifyRequired,
+
+    /// Any other option, with it's raw data.
+    Other(CertificateOption<'b>),
+}
+
+impl CriticalOption<'_> {
+    /// The name of the option.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::ForceCommand(_) => "force-command",
+            Self::SourceAddress(_) => "source-address",
+            Self::VerifyRequired => "verify-required",
+            Self::Other(option) => &option.name,
+        }
+    }
+}
+
+impl BinRead for CriticalOption<'_> {
+    type Args<'a> = crate::DecodeOptions;
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let pos = reader.stream_position()?;
+        let option = CertificateOption::read_options(reader, endian, args)?;
+
+        Ok(match &*option.name {
+            "force-command" => Self::ForceCommand(nested(&option, pos, args)?),
+            "source-address" => Self::SourceAddress(nested(&option, pos, args)?),
+            "verify-required" => {
+                flag(&option, pos)?;
+                Self::VerifyRequired
+            }
+            _ => Self::Other(option),
+        })
+    }
+}
+
+impl BinWrite for CriticalOption<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        match self {
+            Self::ForceCommand(command) => write_nested(self.name(), command, writer, endian),
+            Self::SourceAddress(addresses) => write_nested(self.name(), addresses, writer, endian),
+            Self::VerifyRequired => write_nested(self.name(), &(), writer, endian),
+            Self::Other(option) => option.write_options(writer, endian, args),
+        }
+    }
+}
+
+/// An extension of a [`Certificate`](super::Certificate), to be ignored by the server if unrecognized.
+///
+/// see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.certkeys>.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Extension<'b> {
+    /// The `no-touch-required` extension, not requiring the signatures of security keys to attest user presence.
+    NoTouchRequired,
+
+    /// The `permit-X11-forwarding` extension.
+    PermitX11Forwarding,
+
+    /// The `permit-agent-forwarding` extension.
+    PermitAgentForwarding,
+
+    /// The `permit-port-forwarding` extension.
+    PermitPortForwarding,
+
+    /// The `permit-pty` extension.
+    PermitPty,
+
+    /// The `permit-user-rc` extension, allowing the execution of `~/.ssh/rc`.
+    PermitUserRc,
+
+    /// Any other extension, with it's raw data.
+    Other(CertificateOption<'b>),
+}
+
+impl Extension<'_> {
+    /// The extensions granted to user certificates by default by OpenSSH's `ssh-keygen`.
+    pub const PERMIT_ALL: [Extension<'static>; 5] = [
+        Extension::PermitX11Forwarding,
+        Extension::PermitAgentForwarding,
+        Extension::PermitPortForwarding,
+        Extension::PermitPty,
+        Extension::PermitUserRc,
+    ];
+
+    /// The name of the extension.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::NoTouchRequired => "no-touch-required",
+            Self::PermitX11Forwarding => "permit-X11-forwarding",
+            Self::PermitAgentForwarding => "permit-agent-forwarding",
+            Self::PermitPortForwarding => "permit-port-forwarding",
+            Self::PermitPty => "permit-pty",
+            Self::PermitUserRc => "permit-user-rc",
+            Self::Other(option) => &option.name,
+        }
+    }
+}
+
+impl BinRead for Extension<'_> {
+    type Args<'a> = crate::DecodeOptions;
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let pos = reader.stream_position()?;
+        let option = CertificateOption::read_options(reader, endian, args)?;
+
+        let extension = match &*option.name {
+            "no-touch-required" => Self::NoTouchRequired,
+            "permit-X11-forwarding" => Self::PermitX11Forwarding,
+            "permit-agent-forwarding" => Self::PermitAgentForwarding,
+            "permit-port-forwarding" => Self::PermitPortForwarding,
+            "permit-pty" => Self::PermitPty,
+            "permit-user-rc" => Self::PermitUserRc,
+            _ => return Ok(Self::Other(option)),
+        };
+        flag(&option, pos)?;
+
+        Ok(extension)
+    }
+}
+
+impl BinWrite for Extension<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        match self {
+            Self::Other(option) => option.write_options(writer, endian, args),
+            flag => write_nested(flag.name(), &(), writer, endian),
+        }
+    }
+}
+
+/// Parse the `string` nested in the `data` of the `option`.
+fn nested<T: for<'a> BinRead<Args<'a> = crate::DecodeOptions>>(
+    option: &CertificateOption<'_>,
+    pos: u64,
+    options: crate::DecodeOptions,
+) -> BinResult<T> {
+    let mut reader = std::io::Cursor::new(&*option.data);
+    let value = T::read_options(&mut reader, binrw::Endian::Big, options)?;
+
+    if reader.position() != option.data.len() as u64 {
+        return Err(binrw::Error::AssertFail {
+            pos,
+            message: format!("trailing bytes in the `{}` option", &*option.name),
+        });
+    }
+
+    Ok(value)
+}
+
+/// Check that the `option` is a flag, without any `data`.
+fn flag(option: &CertificateOption<'_>, pos: u64) -> BinResult<()> {
+    if option.data.is_empty() {
+        Ok(())
+    } else {
+        Err(binrw::Error::AssertFail {
+            pos,
+            message: format!("unexpected data in the `{}` option", &*option.name),
+        })
+    }
+}
+
+/// Write the option `name` with the `value` nested in a `string` as it's data,
+/// or without any data for the `()` value of the flags.
+fn write_nested<W: Write + Seek>(
+    name: &str,
+    value: &impl for<'a> BinWrite<Args<'a> = ()>,
+    writer: &mut W,
+    endian: binrw::Endian,
+) -> BinResult<()> {
+    let mut data = std::io::Cursor::new(Vec::new());
+    value.write_options(&mut data, binrw::Endian::Big, ())?;
+
+    arch::Bytes::borrowed(name.as_bytes()).write_options(writer, endian, ())?;
+    arch::Bytes::owned(data.into_inner()).write_options(writer, endian, ())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(
+        CriticalOption::ForceCommand("true".into()),
+        b"\0\0\0\x0dforce-command\0\0\0\x08\0\0\0\x04true"
+    )]
+    #[case(
+        CriticalOption::SourceAddress(arch::ascii!("10.0.0.0/8")),
+        b"\0\0\0\x0esource-address\0\0\0\x0e\0\0\0\x0a10.0.0.0/8"
+    )]
+    #[case(CriticalOption::VerifyRequired, b"\0\0\0\x0fverify-required\0\0\0\0")]
+    #[case(
+        CriticalOption::Other(CertificateOption {
+            name: arch::ascii!("custom@example.com"),
+            data: (&b"raw"[..]).into(),
+        }),
+        b"\0\0\0\x12custom@example.com\0\0\0\x03raw"
+    )]
+    fn it_encodes_critical_options(#[case] option: CriticalOption<'static>, #[case] wire: &[u8]) {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        option.write_be(&mut buffer).unwrap();
+        assert_eq!(buffer.get_ref(), wire);
+
+        assert_eq!(
+            CriticalOption::read_be(&mut std::io::Cursor::new(wire)).unwrap(),
+            option
+        );
+    }
+
+    #[rstest]
+    #[case(Extension::PermitPty, b"\0\0\0\x0apermit-pty\0\0\0\0")]
+    #[case(
+        Extension::PermitX11Forwarding,
+        b"\0\0\0\x15permit-X11-forwarding\0\0\0\0"
+    )]
+    #[case(
+        Extension::Other(CertificateOption {
+            name: arch::ascii!("permit-x11-forwarding"),
+            data: Default::default(),
+        }),
+        b"\0\0\0\x15permit-x11-forwarding\0\0\0\0"
+    )]
+    fn it_encodes_extensions(#[case] extension: Extension<'static>, #[case] wire: &[u8]) {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        extension.write_be(&mut buffer).unwrap();
+        assert_eq!(buffer.get_ref(), wire);
+
+        assert_eq!(
+            Extension::read_be(&mut std::io::Cursor::new(wire)).unwrap(),
+            extension
+        );
+    }
+
+    #[rstest]
+    #[case(b"\0\0\0\x0dforce-command\0\0\0\x04true")]
+    #[case(b"\0\0\0\x0dforce-command\0\0\0\x09\0\0\0\x04true!")]
+    #[case(b"\0\0\0\x0fverify-required\0\0\0\x01\0")]
+    fn it_rejects_malformed_options(#[case] wire: &[u8]) {
+        CriticalOption::read_be(&mut std::io::Cursor::new(wire)).unwrap_err();
+    }
+}
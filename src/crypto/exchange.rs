@@ -1,43 +1,73 @@
 //! Facilities to produce some of the _exchange hashes_.
 
-use binrw::binwrite;
+use std::borrow::Cow;
+
+use binrw::binrw;
 
-use super::Lengthed;
 use crate::{arch, trans};
 
 /// The exchange hash for ECDH, computed as the
 /// hash of the concatenation of the following.
 ///
+/// It's also decodable, to inspect captured hash inputs.
+///
 /// see <https://datatracker.ietf.org/doc/html/rfc5656#section-4>.
-#[binwrite]
-#[derive(Debug)]
-#[bw(big)]
+#[binrw]
+#[derive(Clone, PartialEq, Eq)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct Ecdh<'b> {
     /// Client's identification string (`\r` and `\n` excluded).
+    #[br(args_raw(options))]
     pub v_c: arch::Bytes<'b>,
 
     /// Server's identification string (`\r` and `\n` excluded).
+    #[br(args_raw(options))]
     pub v_s: arch::Bytes<'b>,
 
     /// Payload of the client's `SSH_MSG_KEXINIT` message.
-    pub i_c: Lengthed<&'b trans::KexInit<'b>>,
+    #[br(args_raw(options), map = |i_c: arch::Lengthed<trans::KexInit<'b>>| Cow::Owned(i_c.0))]
+    #[bw(map = |i_c| arch::Lengthed(i_c.as_ref()))]
+    pub i_c: Cow<'b, trans::KexInit<'b>>,
 
     /// Payload of the server's `SSH_MSG_KEXINIT` message.
-    pub i_s: Lengthed<&'b trans::KexInit<'b>>,
+    #[br(args_raw(options), map = |i_s: arch::Lengthed<trans::KexInit<'b>>| Cow::Owned(i_s.0))]
+    #[bw(map = |i_s| arch::Lengthed(i_s.as_ref()))]
+    pub i_s: Cow<'b, trans::KexInit<'b>>,
 
     /// Server's public host key.
+    #[br(args_raw(options))]
     pub k_s: arch::Bytes<'b>,
 
     /// Client's ephemeral public key octet string.
+    #[br(args_raw(options))]
     pub q_c: arch::Bytes<'b>,
 
     /// Server's ephemeral public key octet string.
+    #[br(args_raw(options))]
     pub q_s: arch::Bytes<'b>,
 
     /// Computed shared secret.
+    #[br(args_raw(options))]
     pub k: arch::MpInt<'b>,
 }
 
+/// The shared secret is redacted.
+impl std::fmt::Debug for Ecdh<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ecdh")
+            .field("v_c", &self.v_c)
+            .field("v_s", &self.v_s)
+            .field("i_c", &self.i_c)
+            .field("i_s", &self.i_s)
+            .field("k_s", &self.k_s)
+            .field("q_c", &self.q_c)
+            .field("q_s", &self.q_s)
+            .field("k", &arch::Redacted(&self.k))
+            .finish()
+    }
+}
+
 impl Ecdh<'_> {
     /// Produce the exchange hash with the specified digest algorithm.
     #[cfg(feature = "digest")]
@@ -52,3 +82,44 @@ impl Ecdh<'_> {
         D::digest(&buffer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use binrw::{BinRead, BinWrite};
+
+    use super::*;
+    use crate::Packet;
+
+    #[test]
+    fn it_roundtrips_ecdh_exchanges() {
+        let kexinit: trans::KexInit = Packet {
+            payload: arch::Bytes::owned(
+                [&[20][..], &[0x42; 16], &[0; 4 * 10], &[1], &[0; 4]].concat(),
+            ),
+        }
+        .to()
+        .unwrap();
+        let exchange = Ecdh {
+            v_c: arch::Bytes::borrowed(b"SSH-2.0-client"),
+            v_s: arch::Bytes::borrowed(b"SSH-2.0-server"),
+            i_c: Cow::Borrowed(&kexinit),
+            i_s: Cow::Borrowed(&kexinit),
+            k_s: arch::Bytes::borrowed(b"host key"),
+            q_c: arch::Bytes::borrowed(b"client ephemeral"),
+            q_s: arch::Bytes::borrowed(b"server ephemeral"),
+            k: arch::MpInt::positive(b"secret"),
+        };
+
+        let mut buf = std::io::Cursor::new(Vec::new());
+        exchange.write(&mut buf).unwrap();
+        assert_eq!(&buf.get_ref()[36..40], &[0, 0, 0, 62]);
+
+        buf.set_position(0);
+        let decoded = Ecdh::read(&mut buf).unwrap();
+        assert_eq!(buf.position(), buf.get_ref().len() as u64);
+        assert!(matches!(decoded.i_c, Cow::Owned(_)));
+        assert_eq!(decoded, exchange);
+    }
+}
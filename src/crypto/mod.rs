@@ -1,7 +1,9 @@
 //! Collection of _exchange hashes_ and _signatures_ present in the protocol.
 
-mod lengthed;
-pub use lengthed::Lengthed;
+/// Re-export of [`arch::Lengthed`](crate::arch::Lengthed) for compatibility.
+pub use crate::arch::Lengthed;
 
+pub mod cert;
 pub mod exchange;
 pub mod signature;
+pub mod sk;
@@ -1,9 +1,59 @@
 //! Facilities to use some of the _signature algorithms_.
 
-use binrw::binwrite;
+use binrw::{binrw, binwrite};
 
 use crate::arch;
 
+/// A signature blob, made of the signature algorithm's name followed by the signature itself,
+/// in example as found in the `SSH_MSG_KEXDH_REPLY` message or in certificates.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.6>.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct SignatureBlob<'b> {
+    /// The signature algorithm's name, in example `rsa-sha2-256`.
+    #[br(args_raw(options))]
+    pub algorithm: arch::Ascii<'b>,
+
+    /// The signature, encoded as specified by the algorithm.
+    #[br(args_raw(options))]
+    pub blob: arch::Bytes<'b>,
+}
+
+impl SignatureBlob<'_> {
+    /// Decode a [`SignatureBlob`] from `buf`, failing if it is followed by trailing data.
+    #[cfg(feature = "signature")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "signature")))]
+    pub fn decode(buf: &[u8]) -> signature::Result<SignatureBlob<'static>> {
+        use binrw::BinRead;
+
+        let mut reader = std::io::Cursor::new(buf);
+        let signature = SignatureBlob::read_args(&mut reader, Default::default())
+            .map_err(|_| signature::Error::new())?;
+        if reader.position() != buf.len() as u64 {
+            return Err(signature::Error::new());
+        }
+
+        Ok(signature)
+    }
+
+    /// Verify the signature of `msg` with the `key`, decoding the blob as `S`.
+    ///
+    /// The algorithm name isn't checked here, and is to be matched by the caller
+    /// against the expected algorithm beforehand.
+    #[cfg(feature = "signature")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "signature")))]
+    pub fn verify<S, K>(&self, msg: &[u8], key: &K) -> signature::Result<()>
+    where
+        S: for<'s> TryFrom<&'s [u8], Error = signature::Error>,
+        K: signature::Verifier<S>,
+    {
+        key.verify(msg, &S::try_from(&self.blob)?)
+    }
+}
+
 /// The data that gets _signed_ and _verified_ to prove the possession of the said private key in
 /// the `publickey` authentication method, computed from the concatenation of the following.
 ///
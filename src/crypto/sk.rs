@@ -0,0 +1,317 @@
+//! Facilities to use the FIDO/U2F _security key_ algorithms.
+//!
+//! see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.u2f>.
+
+use binrw::binrw;
+
+use crate::arch;
+
+/// The flag set in the signatures when the user was present, by touching the key.
+pub const USER_PRESENT: u8 = 0x01;
+
+/// The flag set in the signatures when the user was verified, with a PIN or biometrics.
+pub const USER_VERIFIED: u8 = 0x04;
+
+/// The `sk-ssh-ed25519@openssh.com` public key blob.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct Ed25519PublicKey<'b> {
+    #[br(temp, assert(algorithm == Ed25519PublicKey::ALGORITHM), args_raw(options))]
+    #[bw(calc = Ed25519PublicKey::ALGORITHM)]
+    algorithm: arch::Ascii<'b>,
+
+    /// The Ed25519 public key.
+    #[br(assert(key.len() == Ed25519PublicKey::KEY_SIZE), args_raw(options))]
+    pub key: arch::Bytes<'b>,
+
+    /// The application the key was enrolled for, usually `ssh:`.
+    #[br(args_raw(options))]
+    pub application: arch::Ascii<'b>,
+}
+
+impl Ed25519PublicKey<'_> {
+    /// The name of the public key algorithm.
+    pub const ALGORITHM: arch::Ascii<'static> = arch::ascii!("sk-ssh-ed25519@openssh.com");
+
+    const KEY_SIZE: usize = 32;
+}
+
+/// The `sk-ecdsa-sha2-nistp256@openssh.com` public key blob.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct EcdsaPublicKey<'b> {
+    #[br(temp, assert(algorithm == EcdsaPublicKey::ALGORITHM), args_raw(options))]
+    #[bw(calc = EcdsaPublicKey::ALGORITHM)]
+    algorithm: arch::Ascii<'b>,
+
+    #[br(temp, assert(curve == EcdsaPublicKey::CURVE), args_raw(options))]
+    #[bw(calc = EcdsaPublicKey::CURVE)]
+    curve: arch::Ascii<'b>,
+
+    /// The public point, in the SEC1 encoding.
+    #[br(args_raw(options))]
+    pub q: arch::Bytes<'b>,
+
+    /// The application the key was enrolled for, usually `ssh:`.
+    #[br(args_raw(options))]
+    pub application: arch::Ascii<'b>,
+}
+
+impl EcdsaPublicKey<'_> {
+    /// The name of the public key algorithm.
+    pub const ALGORITHM: arch::Ascii<'static> = arch::ascii!("sk-ecdsa-sha2-nistp256@openssh.com");
+
+    const CURVE: arch::Ascii<'static> = arch::ascii!("nistp256");
+}
+
+/// The `sk-ssh-ed25519@openssh.com` signature blob.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct Ed25519Signature<'b> {
+    #[br(temp, assert(algorithm == Ed25519PublicKey::ALGORITHM), args_raw(options))]
+    #[bw(calc = Ed25519PublicKey::ALGORITHM)]
+    algorithm: arch::Ascii<'b>,
+
+    /// The Ed25519 signature of the [`SignedData`].
+    #[br(args_raw(options))]
+    pub signature: arch::Bytes<'b>,
+
+    /// The flags reported by the key, such as [`USER_PRESENT`] and [`USER_VERIFIED`].
+    pub flags: u8,
+
+    /// The signature counter of the key.
+    pub counter: u32,
+}
+
+impl<'b> Ed25519Signature<'b> {
+    /// The data signed by the security key for the `message`, such as a
+    /// [`signature::Publickey`](super::signature::Publickey), to be verified against the `key`.
+    pub fn signed_data<'a>(
+        &'a self,
+        key: &'a Ed25519PublicKey<'_>,
+        message: &'a [u8],
+    ) -> SignedData<'a> {
+        SignedData {
+            application: &key.application,
+            flags: self.flags,
+            counter: self.counter,
+            message,
+        }
+    }
+}
+
+/// The `r` and `s` integers of an ECDSA signature.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct EcdsaSignatureValue<'b> {
+    /// The `r` integer.
+    #[br(args_raw(options))]
+    pub r: arch::MpInt<'b>,
+
+    /// The `s` integer.
+    #[br(args_raw(options))]
+    pub s: arch::MpInt<'b>,
+}
+
+/// The `sk-ecdsa-sha2-nistp256@openssh.com` signature blob.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
+pub struct EcdsaSignature<'b> {
+    #[br(temp, assert(algorithm == EcdsaPublicKey::ALGORITHM), args_raw(options))]
+    #[bw(calc = EcdsaPublicKey::ALGORITHM)]
+    algorithm: arch::Ascii<'b>,
+
+    /// The ECDSA signature of the SHA-256 hash of the [`SignedData`].
+    #[br(args_raw(options))]
+    pub signature: arch::Lengthed<EcdsaSignatureValue<'b>>,
+
+    /// The flags reported by the key, such as [`USER_PRESENT`] and [`USER_VERIFIED`].
+    pub flags: u8,
+
+    /// The signature counter of the key.
+    pub counter: u32,
+}
+
+impl<'b> EcdsaSignature<'b> {
+    /// The data signed by the security key for the `message`, such as a
+    /// [`signature::Publickey`](super::signature::Publickey), to be verified against the `key`.
+    pub fn signed_data<'a>(
+        &'a self,
+        key: &'a EcdsaPublicKey<'_>,
+        message: &'a [u8],
+    ) -> SignedData<'a> {
+        SignedData {
+            application: &key.application,
+            flags: self.flags,
+            counter: self.counter,
+            message,
+        }
+    }
+}
+
+/// The data that gets _signed_ and _verified_ by a security key, computed from the
+/// concatenation of the SHA-256 hash of the `application`, the `flags`, the `counter`
+/// and the SHA-256 hash of the `message`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignedData<'b> {
+    /// The application the key was enrolled for.
+    pub application: &'b str,
+
+    /// The flags reported by the key.
+    pub flags: u8,
+
+    /// The signature counter of the key.
+    pub counter: u32,
+
+    /// The message that was requested to be signed.
+    pub message: &'b [u8],
+}
+
+impl SignedData<'_> {
+    /// Whether the [`USER_PRESENT`] flag is set.
+    pub fn is_user_present(&self) -> bool {
+        self.flags & USER_PRESENT != 0
+    }
+
+    /// Whether the [`USER_VERIFIED`] flag is set.
+    pub fn is_user_verified(&self) -> bool {
+        self.flags & USER_VERIFIED != 0
+    }
+
+    /// Produce the data with the specified digest algorithm, which is SHA-256 for both algorithms.
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    pub fn to_bytes<D: digest::Digest>(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(2 * <D as digest::Digest>::output_size() + 5);
+        buffer.extend_from_slice(&D::digest(self.application));
+        buffer.push(self.flags);
+        buffer.extend_from_slice(&self.counter.to_be_bytes());
+        buffer.extend_from_slice(&D::digest(self.message));
+
+        buffer
+    }
+
+    /// Verify the data, produced with the specified digest algorithm,
+    /// against the provided `signature` with the `key`.
+    #[cfg(all(feature = "digest", feature = "signature"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "digest", feature = "signature"))))]
+    pub fn verify<D: digest::Digest, S, K: signature::Verifier<S>>(
+        &self,
+        key: &K,
+        signature: &S,
+    ) -> signature::Result<()> {
+        K::verify(key, &self.to_bytes::<D>(), signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use binrw::{BinRead, BinWrite};
+
+    use super::*;
+
+    fn encode(value: &impl for<'a> BinWrite<Args<'a> = ()>) -> Vec<u8> {
+        let mut buf = std::io::Cursor::new(Vec::new());
+        value.write_be(&mut buf).unwrap();
+
+        buf.into_inner()
+    }
+
+    #[test]
+    fn it_roundtrips_ed25519_keys_and_signatures() {
+        let key = Ed25519PublicKey {
+            key: vec![0x42; 32].into(),
+            application: arch::ascii!("ssh:"),
+        };
+        let blob = encode(&key);
+        assert_eq!(&blob[..30], b"\0\0\0\x1ask-ssh-ed25519@openssh.com");
+        assert_eq!(&blob[blob.len() - 8..], b"\0\0\0\x04ssh:");
+        assert_eq!(
+            Ed25519PublicKey::read_be(&mut std::io::Cursor::new(&blob)).unwrap(),
+            key
+        );
+
+        let signature = Ed25519Signature {
+            signature: vec![0x13; 64].into(),
+            flags: USER_PRESENT | USER_VERIFIED,
+            counter: 7,
+        };
+        let blob = encode(&signature);
+        assert_eq!(&blob[blob.len() - 5..], &[0x05, 0, 0, 0, 7]);
+        assert_eq!(
+            Ed25519Signature::read_be(&mut std::io::Cursor::new(&blob)).unwrap(),
+            signature
+        );
+
+        let data = signature.signed_data(&key, b"message");
+        assert!(data.is_user_present() && data.is_user_verified());
+    }
+
+    #[test]
+    fn it_roundtrips_ecdsa_keys_and_signatures() {
+        let key = EcdsaPublicKey {
+            q: vec![0x04; 65].into(),
+            application: arch::ascii!("ssh:"),
+        };
+        let blob = encode(&key);
+        assert_eq!(
+            EcdsaPublicKey::read_be(&mut std::io::Cursor::new(&blob)).unwrap(),
+            key
+        );
+        Ed25519PublicKey::read_be(&mut std::io::Cursor::new(&blob)).unwrap_err();
+
+        let signature = EcdsaSignature {
+            signature: arch::Lengthed(EcdsaSignatureValue {
+                r: arch::MpInt::positive(&[0x80, 1]),
+                s: arch::MpInt::positive(&[0x7f]),
+            }),
+            flags: USER_PRESENT,
+            counter: 1,
+        };
+        let blob = encode(&signature);
+        assert_eq!(
+            &blob[38..],
+            &[0, 0, 0, 12, 0, 0, 0, 3, 0, 0x80, 1, 0, 0, 0, 1, 0x7f, 1, 0, 0, 0, 1]
+        );
+        assert_eq!(
+            EcdsaSignature::read_be(&mut std::io::Cursor::new(&blob)).unwrap(),
+            signature
+        );
+        assert!(!signature.signed_data(&key, b"").is_user_verified());
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn it_produces_the_signed_data() {
+        use sha2::{Digest, Sha256};
+
+        let data = SignedData {
+            application: "ssh:",
+            flags: USER_PRESENT,
+            counter: 0x01020304,
+            message: b"message",
+        };
+
+        assert_eq!(
+            data.to_bytes::<Sha256>(),
+            [
+                &Sha256::digest("ssh:")[..],
+                &[1, 1, 2, 3, 4],
+                &Sha256::digest("message")[..],
+            ]
+            .concat()
+        );
+    }
+}
@@ -1,18 +1,383 @@
 use thiserror::Error;
 
 /// The error types that can occur when manipulating this crate.
+///
+/// Errors of the cipher used to read or write packets are boxed, and can be retrieved with
+/// `downcast_ref` on the variant's inner error.
 #[non_exhaustive]
 #[derive(Debug, Error)]
 pub enum Error {
     /// An error occured while performing I/O operations.
     #[error(transparent)]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
 
     /// The parsed identifier was not conformant.
     #[error("The SSH identifier was either misformatted or misprefixed")]
     BadIdentifer(String),
 
-    /// An EOF occured while parsing.
-    #[error("Unexpected EOF while waiting for SSH identifer")]
+    /// The peer closed the connection while reading the SSH identifier or a packet.
+    #[error("Unexpected EOF while reading from the peer")]
     UnexpectedEof,
+
+    /// The cipher failed to process the packet.
+    #[error("The cipher failed to process the packet")]
+    Cipher(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// The packet failed the integrity checks, either by failing to be authenticated
+    /// by it's _Message Authentication Code_, to be decrypted, or by having an invalid padding
+    /// or an out of bounds length while the length isn't authenticated before being decrypted.
+    ///
+    /// These failures are made indistinguishable to avoid providing a padding or MAC oracle,
+    /// and their actual cause is only reported through `tracing`, if enabled.
+    #[error("The packet failed the integrity checks")]
+    Integrity,
+
+    /// The packet failed to be compressed or decompressed.
+    #[error("The packet failed to be compressed or decompressed")]
+    Compression(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// The packet was larger than the maximum size for this direction.
+    #[error("Packet size too large, {size} > {max}")]
+    PacketTooLarge {
+        /// The size of the packet.
+        size: usize,
+
+        /// The maximum size of a packet for this direction.
+        max: usize,
+    },
+
+    /// The packet was too small to contain it's padding length.
+    #[error("Packet size too small ({size})")]
+    PacketTooSmall {
+        /// The size of the packet.
+        size: usize,
+    },
+
+    /// The padding of the packet didn't respect the protocol's rules.
+    #[error(transparent)]
+    Padding(#[from] PaddingError),
+
+    /// A string failed to be converted to an ASCII string.
+    #[error(transparent)]
+    Ascii(#[from] crate::arch::AsciiError),
+
+    /// The payload of the packet failed to be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    /// The message failed to be encoded into a packet.
+    #[error("The message failed to be encoded")]
+    Encode(#[source] binrw::Error),
+
+    /// The packet failed to be sealed by the local end to be sent, by being too large
+    /// for this direction or failing to be compressed, padded or encrypted.
+    #[error("The packet failed to be sealed")]
+    Seal(#[source] Box<Error>),
+}
+
+impl Error {
+    /// Whether the error leaves the connection unusable, either because the stream of packets
+    /// is desynchronized or because it's integrity cannot be trusted anymore.
+    ///
+    /// Non-fatal errors, such as a message failing to decode from a well-formed packet,
+    /// may be handled without tearing down the connection.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::Decode(_) | Self::Encode(_) | Self::Ascii(_) => false,
+            Self::Seal(err) => err.is_fatal(),
+            _ => true,
+        }
+    }
+
+    /// The [`DisconnectReason`](crate::trans::DisconnectReason) to report to the peer
+    /// in a `SSH_MSG_DISCONNECT` message, or [`None`] if the connection is already lost
+    /// or the error is local to this side, as for the [`Error::Seal`] of outbound packets.
+    ///
+    /// The non-fatal [`Error::Decode`] of a malformed inbound message still has a reason,
+    /// for callers choosing to disconnect rather than to handle it.
+    pub fn disconnect_reason(&self) -> Option<crate::trans::DisconnectReason> {
+        use crate::trans::DisconnectReason;
+
+        match self {
+            Self::Io(_)
+            | Self::UnexpectedEof
+            | Self::Ascii(_)
+            | Self::Encode(_)
+            | Self::Seal(_) => None,
+            Self::Integrity => Some(DisconnectReason::MacError),
+            Self::Compression(_) => Some(DisconnectReason::CompressionError),
+            Self::BadIdentifer(_)
+            | Self::Cipher(_)
+            | Self::PacketTooLarge { .. }
+            | Self::PacketTooSmall { .. }
+            | Self::Padding(_)
+            | Self::Decode(_) => Some(DisconnectReason::ProtocolError),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::UnexpectedEof {
+            Self::UnexpectedEof
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            Error::UnexpectedEof => std::io::ErrorKind::UnexpectedEof.into(),
+            err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+impl From<binrw::Error> for Error {
+    fn from(err: binrw::Error) -> Self {
+        Self::Decode(err.into())
+    }
+}
+
+/// The inner errors, which aren't [`defmt::Format`], are formatted with [`core::fmt`].
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Io(err) => defmt::write!(f, "Io({})", defmt::Display2Format(err)),
+            Self::BadIdentifer(id) => defmt::write!(f, "BadIdentifer({=str})", id.as_str()),
+            Self::UnexpectedEof => defmt::write!(f, "UnexpectedEof"),
+            Self::Cipher(err) => defmt::write!(f, "Cipher({})", defmt::Display2Format(err)),
+            Self::Integrity => defmt::write!(f, "Integrity"),
+            Self::Compression(err) => {
+                defmt::write!(f, "Compression({})", defmt::Display2Format(err))
+            }
+            Self::PacketTooLarge { size, max } => defmt::write!(
+                f,
+                "PacketTooLarge {{ size: {=usize}, max: {=usize} }}",
+                size,
+                max
+            ),
+            Self::PacketTooSmall { size } => {
+                defmt::write!(f, "PacketTooSmall {{ size: {=usize} }}", size)
+            }
+            Self::Padding(err) => defmt::write!(f, "Padding({})", err),
+            Self::Ascii(err) => defmt::write!(f, "Ascii({})", err),
+            Self::Decode(err) => defmt::write!(f, "Decode({})", err),
+            Self::Encode(err) => defmt::write!(f, "Encode({})", defmt::Display2Format(err)),
+            Self::Seal(err) => defmt::write!(f, "Seal({})", err.as_ref()),
+        }
+    }
+}
+
+/// A message failed to be decoded from a packet's payload, reported with the path
+/// of the field that failed to decode, such as `ChannelRequest::context`.
+#[derive(Debug, Error)]
+pub struct DecodeError {
+    path: Vec<String>,
+
+    #[source]
+    inner: binrw::Error,
+}
+
+impl DecodeError {
+    /// The path of the field that failed to decode, from the outermost to the innermost field.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// The position in the payload where the decoding failed, if known.
+    pub fn pos(&self) -> Option<u64> {
+        match self.inner.root_cause() {
+            binrw::Error::BadMagic { pos, .. }
+            | binrw::Error::AssertFail { pos, .. }
+            | binrw::Error::Custom { pos, .. }
+            | binrw::Error::NoVariantMatch { pos }
+            | binrw::Error::EnumErrors { pos, .. } => Some(*pos),
+            _ => None,
+        }
+    }
+
+    /// Access the underlying [`binrw::Error`], with it's full backtrace.
+    pub fn inner(&self) -> &binrw::Error {
+        &self.inner
+    }
+
+    /// Consume the [`DecodeError`], returning the underlying [`binrw::Error`].
+    pub fn into_inner(self) -> binrw::Error {
+        self.inner
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DecodeError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", defmt::Display2Format(self))
+    }
+}
+
+impl From<binrw::Error> for DecodeError {
+    fn from(inner: binrw::Error) -> Self {
+        let mut path = match &inner {
+            binrw::Error::Backtrace(backtrace) => backtrace
+                .frames
+                .iter()
+                .filter_map(|frame| match frame {
+                    binrw::error::BacktraceFrame::Full { message, .. }
+                    | binrw::error::BacktraceFrame::Message(message) => {
+                        // Frames generated by `binrw` read as `While parsing field 'x' in Y`
+                        let (field, ty) = message
+                            .strip_prefix("While parsing field '")?
+                            .split_once("' in ")?;
+
+                        // Fields of tuple structs are named `self_N` by `binrw`
+                        let field = field.strip_prefix("self_").unwrap_or(field);
+
+                        Some(format!("{ty}::{field}"))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        // Frames are ordered from the innermost to the outermost
+        path.reverse();
+
+        Self { path, inner }
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join(" > "))?;
+        }
+
+        match self.inner.root_cause() {
+            binrw::Error::BadMagic { found, .. } => write!(f, "unexpected magic {found:?}")?,
+            binrw::Error::AssertFail { message, .. } => write!(f, "{message}")?,
+            binrw::Error::Custom { err, .. } => write!(f, "{err}")?,
+            binrw::Error::NoVariantMatch { .. } | binrw::Error::EnumErrors { .. } => {
+                write!(f, "unexpected variant")?
+            }
+            binrw::Error::Io(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                write!(f, "unexpected end of payload")?
+            }
+            err => write!(f, "{err}")?,
+        }
+
+        match self.pos() {
+            Some(pos) => write!(f, " at offset {pos}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A length read or written exceeded it's limit, as for a length-prefixed
+/// field or a decompressed payload larger than the maximum packet size.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[error("Size too large, {size} > {max}")]
+pub struct SizeError {
+    /// The offending size.
+    pub size: usize,
+
+    /// The maximum allowed size.
+    pub max: usize,
+}
+
+/// The padding of a packet didn't respect the protocol's rules, either by being out
+/// of the `4..=255` range, by exceeding the packet or by not aligning the packet to the cipher's block size.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6>.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[error("Invalid padding of {padding} bytes for a packet of {size} bytes aligned on {align} bytes")]
+pub struct PaddingError {
+    /// The size of the padding of the packet.
+    pub padding: u8,
+
+    /// The size of the packet subject to alignment.
+    pub size: usize,
+
+    /// The required alignment of the packet.
+    pub align: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{arch, connect, trans::DisconnectReason, Packet};
+
+    #[rstest]
+    #[case(
+        &[98, 0, 0, 0, 1, 0, 0, 0, 3, b'f', b'o', b'o', 1],
+        "ChannelRequest::context: unexpected variant at offset 13"
+    )]
+    #[case(&[98, 0, 0], "ChannelRequest::recipient_channel: unexpected end of payload")]
+    #[case(&[97], "unexpected magic 97 at offset 0")]
+    fn it_describes_decode_errors(#[case] payload: &[u8], #[case] message: &str) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        let err = packet.to::<connect::ChannelRequest>().unwrap_err();
+
+        assert_eq!(err.to_string(), message);
+    }
+
+    #[test]
+    fn it_interoperates_with_io_errors() {
+        let err = Error::from(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        assert!(matches!(err, Error::UnexpectedEof));
+
+        let err = Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(matches!(err, Error::Io(_)));
+
+        let err = std::io::Error::from(Error::Integrity);
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[rstest]
+    #[case(Error::UnexpectedEof, true, None)]
+    #[case(Error::Integrity, true, Some(DisconnectReason::MacError))]
+    #[case(
+        Error::Compression("stalled".into()),
+        true,
+        Some(DisconnectReason::CompressionError)
+    )]
+    #[case(
+        Error::PacketTooLarge { size: 1 << 20, max: 1 << 16 },
+        true,
+        Some(DisconnectReason::ProtocolError)
+    )]
+    #[case(
+        Error::from(binrw::Error::NoVariantMatch { pos: 0 }),
+        false,
+        Some(DisconnectReason::ProtocolError)
+    )]
+    #[case(
+        Error::Seal(Box::new(Error::PacketTooLarge { size: 1 << 20, max: 1 << 16 })),
+        true,
+        None
+    )]
+    #[case(
+        Error::Seal(Box::new(Error::Compression("stalled".into()))),
+        true,
+        None
+    )]
+    fn it_classifies_errors(
+        #[case] err: Error,
+        #[case] fatal: bool,
+        #[case] reason: Option<DisconnectReason>,
+    ) {
+        assert_eq!(err.is_fatal(), fatal);
+        assert_eq!(err.disconnect_reason(), reason);
+    }
 }
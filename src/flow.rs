@@ -0,0 +1,359 @@
+//! Validation of the flow of messages through the phases of the protocol,
+//! to reject messages that are illegal in the current state of the connection.
+//!
+//! see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.1>,
+//! <https://datatracker.ietf.org/doc/html/rfc4252#section-5.1> and
+//! <https://datatracker.ietf.org/doc/html/rfc4254#section-1>.
+
+use crate::{trans, Direction, MessageId, Packet};
+
+pub use crate::negotiate::Role;
+
+/// The phase of the protocol the connection is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// The identification strings are being exchanged, before any packet.
+    VersionExchange,
+
+    /// The initial key exchange is in progress.
+    Kex,
+
+    /// The client is to request the authentication service.
+    Service,
+
+    /// The client is authenticating.
+    Userauth,
+
+    /// The client is authenticated, and the connection protocol is running.
+    Connection,
+
+    /// A `SSH_MSG_DISCONNECT` message has been exchanged.
+    Disconnected,
+}
+
+/// A message that is illegal in the current state of the connection.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("{direction:?} message {id} is illegal in the {phase:?} phase{}", if *.kex { " during a key exchange" } else { "" })]
+pub struct Violation {
+    /// The reason to disconnect from the peer with.
+    pub reason: trans::DisconnectReason,
+
+    /// The number of the offending message, if any.
+    pub id: MessageId,
+
+    /// The direction of the offending message.
+    pub direction: Direction,
+
+    /// The phase the connection was in.
+    pub phase: Phase,
+
+    /// Whether a key exchange was in progress.
+    pub kex: bool,
+}
+
+impl Violation {
+    /// Create a [`trans::Disconnect`] message to be sent to the peer for this [`Violation`].
+    pub fn to_disconnect(&self) -> trans::Disconnect<'static> {
+        trans::Disconnect::new(self.reason, self.to_string())
+    }
+}
+
+/// The progress of a key exchange, in each direction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+struct Kex {
+    kexinit: [bool; 2],
+    newkeys: [bool; 2],
+}
+
+impl Kex {
+    /// Whether only the key exchange messages are allowed in `direction`,
+    /// after it's `SSH_MSG_KEXINIT` and until it's `SSH_MSG_NEWKEYS`.
+    fn is_locked(&self, direction: Direction) -> bool {
+        self.kexinit[direction as usize] && !self.newkeys[direction as usize]
+    }
+}
+
+/// A validator of the flow of messages through the phases of the protocol.
+///
+/// Each message sent or received is to be [`Flow::check`]ed, which advances the state
+/// of the connection, or returns the [`Violation`] to disconnect from the peer with.
+///
+/// The transport generic messages are allowed in every phase, while key exchanges only allow
+/// the messages of the key exchange in a direction from it's `SSH_MSG_KEXINIT` to it's `SSH_MSG_NEWKEYS`,
+/// and the authentication and connection messages are only allowed once the service is requested
+/// and the client authenticated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Flow {
+    role: Role,
+    phase: Phase,
+    kex: Option<Kex>,
+    service_requested: bool,
+}
+
+impl Flow {
+    /// Create a [`Flow`] for the local `role`, in the [`Phase::VersionExchange`] phase.
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            phase: Phase::VersionExchange,
+            kex: None,
+            service_requested: false,
+        }
+    }
+
+    /// The local role.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// The current phase of the connection.
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Whether a key exchange is in progress, initial or not.
+    pub fn is_kex(&self) -> bool {
+        self.kex.is_some()
+    }
+
+    /// Mark the identification strings as exchanged, entering the [`Phase::Kex`] phase.
+    pub fn version_exchanged(&mut self) {
+        if self.phase == Phase::VersionExchange {
+            self.phase = Phase::Kex;
+            self.kex = Some(Kex::default());
+        }
+    }
+
+    /// Check the `packet` travelling in `direction`, see [`Flow::check`].
+    pub fn check_packet(
+        &mut self,
+        direction: Direction,
+        packet: &Packet<'_>,
+    ) -> Result<(), Violation> {
+        // An empty payload has no message number, which can't be legal
+        let id = packet.peek_message_id().unwrap_or(MessageId(0));
+
+        self.check(direction, id)
+    }
+
+    /// Check that the message numbered `id` travelling in `direction` is legal
+    /// in the current state, and advance the state accordingly.
+    pub fn check(&mut self, direction: Direction, id: MessageId) -> Result<(), Violation> {
+        let (phase, kex) = (self.phase, self.kex.is_some());
+        let violation = |reason| Violation {
+            reason,
+            id,
+            direction,
+            phase,
+            kex,
+        };
+        let from_client = (direction == Direction::Outbound) == (self.role == Role::Client);
+
+        if matches!(self.phase, Phase::VersionExchange | Phase::Disconnected) {
+            return Err(violation(trans::DisconnectReason::ProtocolError));
+        }
+
+        let legal = match id.0 {
+            1 => {
+                self.phase = Phase::Disconnected;
+                true
+            }
+            // Transport layer generic messages
+            2..=4 | 7..=19 => true,
+            20 => self.kexinit(direction),
+            21 => self.newkeys(direction),
+            22..=49 => self.kex.is_some(),
+            // Anything else is forbidden in a direction between it's `SSH_MSG_KEXINIT` and `SSH_MSG_NEWKEYS`
+            _ if self.kex.is_some_and(|kex| kex.is_locked(direction)) => false,
+            5 => {
+                let legal = self.phase == Phase::Service && from_client && !self.service_requested;
+                if legal {
+                    self.service_requested = true;
+                }
+                legal
+            }
+            6 => {
+                let legal = self.phase == Phase::Service && !from_client && self.service_requested;
+                if legal {
+                    self.phase = Phase::Userauth;
+                }
+                legal
+            }
+            // Further requests after a success are to be ignored by the server
+            50 => matches!(self.phase, Phase::Userauth | Phase::Connection) && from_client,
+            51 | 53 => self.phase == Phase::Userauth && !from_client,
+            52 => {
+                let legal = self.phase == Phase::Userauth && !from_client;
+                if legal {
+                    self.phase = Phase::Connection;
+                }
+                legal
+            }
+            54..=79 => self.phase == Phase::Userauth,
+            80..=191 => self.phase == Phase::Connection,
+            // Local extensions, such as `SSH_MSG_PING`
+            192..=255 => true,
+            0 => false,
+        };
+
+        if legal {
+            Ok(())
+        } else {
+            Err(violation(trans::DisconnectReason::ProtocolError))
+        }
+    }
+
+    fn kexinit(&mut self, direction: Direction) -> bool {
+        let kex = self.kex.get_or_insert_with(Default::default);
+        let sent = &mut kex.kexinit[direction as usize];
+
+        !std::mem::replace(sent, true)
+    }
+
+    fn newkeys(&mut self, direction: Direction) -> bool {
+        let Some(kex) = &mut self.kex else {
+            return false;
+        };
+        if kex.kexinit != [true; 2] || std::mem::replace(&mut kex.newkeys[direction as usize], true)
+        {
+            return false;
+        }
+
+        if kex.newkeys == [true; 2] {
+            self.kex = None;
+            if self.phase == Phase::Kex {
+                self.phase = Phase::Service;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use super::*;
+
+    const IN: Direction = Direction::Inbound;
+    const OUT: Direction = Direction::Outbound;
+
+    /// A server in the state after the exchange of the `messages`.
+    fn server(messages: &[(Direction, u8)]) -> Flow {
+        let mut flow = Flow::new(Role::Server);
+        flow.version_exchanged();
+
+        for (direction, id) in messages {
+            flow.check(*direction, MessageId(*id)).unwrap();
+        }
+
+        flow
+    }
+
+    const KEX: &[(Direction, u8)] = &[
+        (OUT, 20),
+        (IN, 2),
+        (IN, 20),
+        (IN, 30),
+        (OUT, 31),
+        (OUT, 21),
+        (IN, 21),
+    ];
+
+    #[test]
+    fn it_follows_a_full_session() {
+        let flow = server(KEX);
+        assert_eq!(flow.phase(), Phase::Service);
+
+        let mut flow = server(&[KEX, &[(IN, 5), (OUT, 6), (IN, 50), (OUT, 51), (IN, 50)]].concat());
+        assert_eq!(flow.phase(), Phase::Userauth);
+
+        flow.check(OUT, MessageId::USERAUTH_SUCCESS).unwrap();
+        assert_eq!(flow.phase(), Phase::Connection);
+        flow.check(IN, MessageId::CHANNEL_OPEN).unwrap();
+        flow.check(OUT, MessageId::CHANNEL_OPEN_CONFIRMATION)
+            .unwrap();
+
+        // A re-exchange of keys only allows the transport messages
+        flow.check(IN, MessageId::KEXINIT).unwrap();
+        assert!(flow.is_kex());
+        assert_eq!(
+            flow.check(IN, MessageId::CHANNEL_DATA).unwrap_err().reason,
+            trans::DisconnectReason::ProtocolError
+        );
+        for (direction, id) in KEX {
+            if (*direction, *id) != (IN, 20) {
+                flow.check(*direction, MessageId(*id)).unwrap();
+            }
+        }
+        assert!(!flow.is_kex());
+        flow.check(IN, MessageId::CHANNEL_DATA).unwrap();
+
+        flow.check(IN, MessageId::DISCONNECT).unwrap();
+        assert_eq!(flow.phase(), Phase::Disconnected);
+    }
+
+    #[rstest]
+    #[case(&[], IN, 90)]
+    #[case(&[], IN, 5)]
+    #[case(&[(IN, 20)], IN, 20)]
+    #[case(&[(IN, 20)], IN, 21)]
+    #[case(KEX, IN, 50)]
+    #[case(KEX, IN, 90)]
+    #[case(KEX, OUT, 5)]
+    #[case(&[KEX, &[(IN, 5)]].concat(), IN, 6)]
+    #[case(&[KEX, &[(IN, 5), (OUT, 6)]].concat(), IN, 52)]
+    #[case(&[KEX, &[(IN, 5), (OUT, 6)]].concat(), OUT, 50)]
+    #[case(&[KEX, &[(IN, 5), (OUT, 6)]].concat(), IN, 90)]
+    #[case(&[KEX, &[(IN, 5), (OUT, 6), (OUT, 52)]].concat(), OUT, 51)]
+    #[case(&[KEX, &[(IN, 1)]].concat(), IN, 2)]
+    fn it_rejects_illegal_messages(
+        #[case] messages: &[(Direction, u8)],
+        #[case] direction: Direction,
+        #[case] id: u8,
+    ) {
+        let mut flow = server(messages);
+
+        let violation = flow.check(direction, MessageId(id)).unwrap_err();
+        assert_eq!(violation.id, MessageId(id));
+        assert_eq!(
+            violation.to_disconnect().reason,
+            trans::DisconnectReason::ProtocolError
+        );
+    }
+
+    #[rstest]
+    #[case(&[(OUT, 20)], IN, 94)]
+    #[case(&[(OUT, 20), (IN, 20), (IN, 30), (OUT, 31), (OUT, 21)], OUT, 94)]
+    #[case(&[(IN, 20), (OUT, 20), (IN, 30), (OUT, 31), (IN, 21)], IN, 94)]
+    fn it_allows_messages_outside_of_the_kex_lockout(
+        #[case] rekey: &[(Direction, u8)],
+        #[case] direction: Direction,
+        #[case] id: u8,
+    ) {
+        let session = [KEX, &[(IN, 5), (OUT, 6), (IN, 50), (OUT, 52)]].concat();
+        let mut flow = server(&[&session, rekey].concat());
+        assert!(flow.is_kex());
+
+        flow.check(direction, MessageId(id)).unwrap();
+
+        let locked = match direction {
+            Direction::Inbound => OUT,
+            Direction::Outbound => IN,
+        };
+        flow.check(locked, MessageId(id)).unwrap_err();
+    }
+
+    #[test]
+    fn it_rejects_packets_before_the_version_exchange() {
+        let mut flow = Flow::new(Role::Client);
+
+        flow.check_packet(OUT, &Packet::from(vec![20])).unwrap_err();
+        flow.version_exchanged();
+        flow.check_packet(OUT, &Packet::from(vec![20])).unwrap();
+        flow.check_packet(IN, &Packet::from(vec![])).unwrap_err();
+    }
+}
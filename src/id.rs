@@ -2,6 +2,12 @@ use crate::Error;
 
 const VERSION: &str = "2.0";
 
+/// Maximum size of the identification line, including the trailing `\r\n`.
+const MAX_SIZE: usize = 255;
+
+/// Prefix of the identification line, distinguishing it from the banner lines.
+const PREFIX: &str = "SSH-";
+
 /// The SSH identification string as defined in the SSH protocol.
 ///
 /// The format must match the following pattern:
@@ -9,6 +15,8 @@ const VERSION: &str = "2.0";
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-4.2>.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Id {
     /// The SSH's protocol version, should be `2.0` in our case.
     pub protoversion: String,
@@ -30,10 +38,107 @@ impl Id {
         }
     }
 
+    /// Split the `softwareversion` into the software's _name_ and it's optional _version_,
+    /// in example `OpenSSH_8.9p1` yields `("OpenSSH", Some("8.9p1"))`.
+    pub fn software(&self) -> (&str, Option<&str>) {
+        self.softwareversion
+            .split_once('_')
+            .map_or((&self.softwareversion, None), |(name, version)| {
+                (name, Some(version))
+            })
+    }
+
+    /// Whether the peer identifies as `OpenSSH`, in a version greater or equal to `major.minor`.
+    pub fn is_openssh_at_least(&self, major: u32, minor: u32) -> bool {
+        match self.software() {
+            ("OpenSSH", Some(version)) => {
+                let mut numbers = version.split('.').map(|number| {
+                    number
+                        .split(|c: char| !c.is_ascii_digit())
+                        .next()
+                        .and_then(|digits| digits.parse::<u32>().ok())
+                });
+
+                match (numbers.next().flatten(), numbers.next().flatten()) {
+                    (Some(peer_major), peer_minor) => {
+                        (peer_major, peer_minor.unwrap_or(0)) >= (major, minor)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Ensure the [`Id`] is conformant to the size and charset limits of the protocol.
+    fn validate(&self) -> Result<(), Error> {
+        let text = self.to_string();
+
+        let is_valid = text.len() + b"\r\n".len() <= MAX_SIZE
+            && !text.chars().any(char::is_control)
+            && !self.protoversion.is_empty()
+            && !self.softwareversion.is_empty()
+            && self
+                .softwareversion
+                .bytes()
+                .all(|byte| byte.is_ascii_graphic() && byte != b'-');
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(Error::BadIdentifer(text))
+        }
+    }
+
+    /// Parse the identification `line`, stripped of it's trailing `\n`.
+    #[cfg(any(feature = "tokio", feature = "embedded-io"))]
+    fn from_line(mut line: Vec<u8>) -> Result<Self, Error> {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        String::from_utf8(line)
+            .map_err(|err| {
+                Error::BadIdentifer(String::from_utf8_lossy(err.as_bytes()).into_owned())
+            })?
+            .parse()
+    }
+
+    /// Encode the identification line, preceded by the human-readable `banner` lines,
+    /// each terminated by a `\r\n`, to be written to any kind of writer.
+    ///
+    /// Banner lines are only to be sent by the server, and must neither start with `SSH-`,
+    /// contain line breaks, nor exceed the maximum size of the identification line.
+    pub fn encode(&self, banner: &[&str]) -> Result<Vec<u8>, Error> {
+        self.validate()?;
+
+        let mut buf = Vec::new();
+        for line in banner {
+            if line.starts_with(PREFIX)
+                || line.contains(['\r', '\n'])
+                || line.len() + b"\r\n".len() > MAX_SIZE
+            {
+                return Err(Error::BadIdentifer(line.to_string()));
+            }
+
+            buf.extend_from_slice(line.as_bytes());
+            buf.extend_from_slice(b"\r\n");
+        }
+
+        buf.extend_from_slice(self.to_string().as_bytes());
+        buf.extend_from_slice(b"\r\n");
+
+        Ok(buf)
+    }
+
     #[cfg(feature = "futures")]
     #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
     /// Read an [`Id`], discarding any _extra lines_ sent by the server
     /// from the provided asynchronous `reader`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, ret)
+    )]
     pub async fn from_reader<R>(reader: &mut R) -> Result<Self, Error>
     where
         R: futures::io::AsyncBufRead + Unpin,
@@ -42,7 +147,7 @@ impl Id {
 
         let text = futures::io::AsyncBufReadExt::lines(reader)
             // Skip extra lines the server can send before identifying
-            .try_skip_while(|line| futures::future::ok(!line.starts_with("SSH")))
+            .try_skip_while(|line| futures::future::ok(!line.starts_with(PREFIX)))
             .try_next()
             .await?
             .ok_or(Error::UnexpectedEof)?;
@@ -53,14 +158,202 @@ impl Id {
     #[cfg(feature = "futures")]
     #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
     /// Write the [`Id`] to the provided asynchronous `writer`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(id = %self))
+    )]
     pub async fn to_writer<W>(&self, writer: &mut W) -> Result<(), Error>
+    where
+        W: futures::io::AsyncWrite + Unpin,
+    {
+        self.to_writer_with_banner(writer, &[]).await
+    }
+
+    #[cfg(feature = "futures")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+    /// Write the [`Id`] preceded by the human-readable `banner` lines,
+    /// to the provided asynchronous `writer`, see [`Id::to_writer`].
+    ///
+    /// Banner lines are only to be sent by the server, and must neither start with `SSH-`
+    /// nor contain line breaks, since they are terminated by a `\r\n`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(id = %self))
+    )]
+    pub async fn to_writer_with_banner<W>(
+        &self,
+        writer: &mut W,
+        banner: &[&str],
+    ) -> Result<(), Error>
     where
         W: futures::io::AsyncWrite + Unpin,
     {
         use futures::io::AsyncWriteExt;
 
-        writer.write_all(self.to_string().as_bytes()).await?;
-        writer.write_all(b"\r\n").await?;
+        writer.write_all(&self.encode(banner)?).await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Read an [`Id`], discarding any _extra lines_ sent by the server
+    /// from the provided asynchronous `tokio` `reader`.
+    ///
+    /// Lines are bounded to the maximum size of the identification line.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, ret)
+    )]
+    pub async fn from_tokio_reader<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+        let mut line = Vec::with_capacity(MAX_SIZE + 1);
+
+        loop {
+            line.clear();
+
+            // Read one byte past the maximum size to detect oversized lines
+            (&mut *reader)
+                .take(MAX_SIZE as u64 + 1)
+                .read_until(b'\n', &mut line)
+                .await?;
+
+            match line.pop() {
+                Some(b'\n') => (),
+                _ if line.len() >= MAX_SIZE => {
+                    return Err(Error::BadIdentifer(
+                        String::from_utf8_lossy(&line).into_owned(),
+                    ))
+                }
+                _ => return Err(Error::UnexpectedEof),
+            }
+
+            // Skip extra lines the server can send before identifying
+            if line.starts_with(PREFIX.as_bytes()) {
+                break Self::from_line(line);
+            }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Write the [`Id`] to the provided asynchronous `tokio` `writer`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(id = %self))
+    )]
+    pub async fn to_tokio_writer<W>(&self, writer: &mut W) -> Result<(), Error>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        self.to_tokio_writer_with_banner(writer, &[]).await
+    }
+
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Write the [`Id`] preceded by the human-readable `banner` lines,
+    /// to the provided asynchronous `tokio` `writer`, see [`Id::to_writer_with_banner`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(id = %self))
+    )]
+    pub async fn to_tokio_writer_with_banner<W>(
+        &self,
+        writer: &mut W,
+        banner: &[&str],
+    ) -> Result<(), Error>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        writer.write_all(&self.encode(banner)?).await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Read an [`Id`], discarding any _extra lines_ sent by the server
+    /// from the provided blocking `embedded-io` `reader`.
+    ///
+    /// The `reader` is consumed byte by byte, so the data following the identification line is left unread,
+    /// and lines are bounded to the maximum size of the identification line.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, ret)
+    )]
+    pub fn from_embedded_reader<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: embedded_io::Read,
+    {
+        use std::io::Read;
+
+        let mut reader = crate::packet::Embedded(reader);
+        let mut line = Vec::with_capacity(MAX_SIZE);
+
+        loop {
+            line.clear();
+
+            loop {
+                let mut byte = 0;
+                reader.read_exact(std::slice::from_mut(&mut byte))?;
+
+                match byte {
+                    b'\n' => break,
+                    _ if line.len() >= MAX_SIZE => {
+                        return Err(Error::BadIdentifer(
+                            String::from_utf8_lossy(&line).into_owned(),
+                        ))
+                    }
+                    _ => line.push(byte),
+                }
+            }
+
+            // Skip extra lines the server can send before identifying
+            if line.starts_with(PREFIX.as_bytes()) {
+                break Self::from_line(line);
+            }
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Write the [`Id`] to the provided blocking `embedded-io` `writer`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(id = %self))
+    )]
+    pub fn to_embedded_writer<W>(&self, writer: &mut W) -> Result<(), Error>
+    where
+        W: embedded_io::Write,
+    {
+        self.to_embedded_writer_with_banner(writer, &[])
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Write the [`Id`] preceded by the human-readable `banner` lines,
+    /// to the provided blocking `embedded-io` `writer`, see [`Id::to_writer_with_banner`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(id = %self))
+    )]
+    pub fn to_embedded_writer_with_banner<W>(
+        &self,
+        writer: &mut W,
+        banner: &[&str],
+    ) -> Result<(), Error>
+    where
+        W: embedded_io::Write,
+    {
+        use std::io::Write;
+
+        crate::packet::Embedded(writer).write_all(&self.encode(banner)?)?;
 
         Ok(())
     }
@@ -90,11 +383,13 @@ impl std::str::FromStr for Id {
             ["SSH", protoversion, softwareversion]
                 if !protoversion.is_empty() && !softwareversion.is_empty() =>
             {
-                Ok(Self {
+                let id = Self {
                     protoversion: protoversion.to_string(),
                     softwareversion: softwareversion.to_string(),
                     comments: comments.map(str::to_string),
-                })
+                };
+
+                id.validate().map(|_| id)
             }
             _ => Err(Error::BadIdentifer(s.into())),
         }
@@ -134,17 +429,107 @@ mod tests {
     #[case("-2.0-billsSSH_3.6.3q3")]
     #[case("SSH--billsSSH_3.6.3q3")]
     #[case("SSH-2.0-")]
+    #[case("SSH-2.0-billsSSH-3.6.3q3")]
+    #[case("SSH-2.0-billsSSH_utf∞")]
+    #[case("SSH-2.0-billsSSH_3.6.3q3 with\0nul")]
+    #[case("SSH-2.0-billsSSH_3.6.3q3 with\x07bell")]
     fn it_rejects_invalid(#[case] text: &str) {
         Id::from_str(text).expect_err(text);
     }
 
     #[rstest]
     #[case(Id::v2("billsSSH_3.6.3q3", None::<String>))]
-    #[case(Id::v2("billsSSH_utf∞", None::<String>))]
     #[case(Id::v2("billsSSH_3.6.3q3", Some("with-comment")))]
     #[case(Id::v2("billsSSH_3.6.3q3", Some("utf∞-comment")))]
     #[case(Id::v2("billsSSH_3.6.3q3", Some("")))] // empty comment
     fn it_reparses_consistently(#[case] id: Id) {
         assert_eq!(id, id.to_string().parse().unwrap());
     }
+
+    #[rstest]
+    #[case("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.1", ("OpenSSH", Some("8.9p1")))]
+    #[case("SSH-2.0-billsSSH_3.6.3q3", ("billsSSH", Some("3.6.3q3")))]
+    #[case("SSH-2.0-dropbear", ("dropbear", None))]
+    fn it_parses_software(#[case] text: &str, #[case] software: (&str, Option<&str>)) {
+        assert_eq!(Id::from_str(text).unwrap().software(), software);
+    }
+
+    #[rstest]
+    #[case("SSH-2.0-OpenSSH_8.9p1", 8, 5, true)]
+    #[case("SSH-2.0-OpenSSH_8.5", 8, 5, true)]
+    #[case("SSH-2.0-OpenSSH_8", 8, 0, true)]
+    #[case("SSH-2.0-OpenSSH_7.4p1", 8, 5, false)]
+    #[case("SSH-2.0-OpenSSH_10.0", 8, 5, true)]
+    #[case("SSH-2.0-OpenSSH_unknown", 1, 0, false)]
+    #[case("SSH-2.0-billsSSH_9.9", 8, 5, false)]
+    fn it_compares_openssh_versions(
+        #[case] text: &str,
+        #[case] major: u32,
+        #[case] minor: u32,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(
+            Id::from_str(text)
+                .unwrap()
+                .is_openssh_at_least(major, minor),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(&[], Ok("SSH-2.0-billsSSH\r\n"))]
+    #[case(&["Welcome!", ""], Ok("Welcome!\r\n\r\nSSH-2.0-billsSSH\r\n"))]
+    #[case(&["SSH-2.0-impostor"], Err(()))]
+    #[case(&["two\r\nlines"], Err(()))]
+    fn it_encodes_banner_lines(#[case] banner: &[&str], #[case] expected: Result<&str, ()>) {
+        let encoded = Id::v2("billsSSH", None::<&str>).encode(banner);
+
+        assert_eq!(
+            encoded.as_deref().map_err(|_| ()),
+            expected.map(str::as_bytes)
+        );
+    }
+
+    #[cfg(any(feature = "futures", feature = "tokio", feature = "embedded-io"))]
+    #[async_std::test]
+    async fn it_reads_back_banner_lines() {
+        let id = Id::v2("billsSSH", None::<&str>);
+        let encoded = id.encode(&["SSHello", "SSH", "Welcome!"]).unwrap();
+
+        #[cfg(feature = "futures")]
+        assert_eq!(Id::from_reader(&mut &encoded[..]).await.unwrap(), id);
+        #[cfg(feature = "tokio")]
+        assert_eq!(Id::from_tokio_reader(&mut &encoded[..]).await.unwrap(), id);
+        #[cfg(feature = "embedded-io")]
+        assert_eq!(Id::from_embedded_reader(&mut &encoded[..]).unwrap(), id);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[async_std::test]
+    async fn it_bounds_tokio_lines() {
+        let oversized = format!("{}\r\nSSH-2.0-billsSSH\r\n", "a".repeat(MAX_SIZE));
+        assert_eq!(
+            Id::from_tokio_reader(&mut oversized.as_bytes()).await,
+            Err(Error::BadIdentifer(String::new()))
+        );
+
+        let bounded = format!("{}\r\nSSH-2.0-billsSSH\r\n", "a".repeat(MAX_SIZE - 1));
+        Id::from_tokio_reader(&mut bounded.as_bytes())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            Id::from_tokio_reader(&mut &b"SSH-2.0-billsSSH"[..]).await,
+            Err(Error::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn it_rejects_oversized() {
+        let text = format!("SSH-2.0-billsSSH_3.6.3q3 {}", "a".repeat(MAX_SIZE));
+
+        Id::from_str(&text).expect_err(&text);
+        Id::from_str(&text[..MAX_SIZE - 2]).expect(&text[..MAX_SIZE - 2]);
+        Id::from_str(&text[..MAX_SIZE - 1]).expect_err(&text[..MAX_SIZE - 1]);
+    }
 }
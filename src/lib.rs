@@ -13,21 +13,58 @@
 #![forbid(unsafe_code)]
 
 pub use ::binrw;
+pub use ::rand_core;
 
 mod error;
-pub use error::Error;
+pub use error::{DecodeError, Error, PaddingError, SizeError};
 
 mod packet;
 pub use packet::{
-    CipherCore, IntoPacket, Mac, OpeningCipher, Packet, SealingCipher, PACKET_MAX_SIZE,
-    PACKET_MIN_SIZE,
+    CipherCore, Compression, Decode, DecodeOptions, Direction, IntoPacket, Mac, MacMode,
+    OpeningCipher, Packet, PacketObserver, PacketReader, PacketWriter, SealingCipher, Seq,
+    TryIntoPacket, UnknownMessages, WireSize, PACKET_MAX_SIZE, PACKET_MIN_SIZE,
 };
 
+#[cfg(feature = "none")]
+#[cfg_attr(docsrs, doc(cfg(feature = "none")))]
+pub use packet::{NoneCipher, NoneMac};
+
+#[cfg(feature = "zlib")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zlib")))]
+pub use packet::{ZlibCompressor, ZlibDecompressor};
+
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub use packet::{Counters, Metrics};
+
+#[cfg(feature = "asynchronous-codec")]
+#[cfg_attr(docsrs, doc(cfg(feature = "asynchronous-codec")))]
+pub use packet::{PacketDecoder, PacketEncoder};
+
 mod id;
 pub use id::Id;
 
+mod message;
+pub use message::{HasMessageId, Message, MessageId};
+
+mod owned;
+pub use owned::IntoOwned;
+
 pub mod arch;
 pub mod connect;
 pub mod crypto;
+pub mod negotiate;
 pub mod trans;
 pub mod userauth;
+
+#[cfg(feature = "flow")]
+#[cfg_attr(docsrs, doc(cfg(feature = "flow")))]
+pub mod flow;
+
+#[cfg(feature = "proptest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "proptest")))]
+pub mod proptest;
+
+#[cfg(any(test, feature = "testing"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
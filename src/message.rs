@@ -0,0 +1,646 @@
+use binrw::{meta::WriteEndian, BinWrite};
+
+use crate::{connect, trans, userauth, DecodeError, DecodeOptions, IntoOwned, Packet};
+
+/// Any of the messages of the SSH protocol, decoded from a [`Packet`] by dispatching on it's message number.
+///
+/// Message numbers whose meaning depends on the negotiated methods are kept
+/// undecoded, to be decoded with [`Packet::to`] according to the context.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Message<'b> {
+    /// A message of the **transport** (`SSH-TRANS`) part of the protocol.
+    Trans(trans::Message<'b>),
+
+    /// A message of the **authentication** (`SSH-USERAUTH`) part of the protocol.
+    Userauth(userauth::Message<'b>),
+
+    /// A message of the **connect** (`SSH-CONNECT`) part of the protocol.
+    Connect(connect::Message<'b>),
+
+    /// Any other message, unknown to this crate, which may be replied with a `SSH_MSG_UNIMPLEMENTED`.
+    Unknown(Packet<'b>),
+}
+
+impl<'b> Message<'b> {
+    /// Decode the [`Message`] in the `packet` by dispatching on it's message number.
+    pub fn from_packet(packet: &'b Packet<'_>) -> Result<Self, DecodeError> {
+        Self::from_packet_with(packet, &DecodeOptions::default())
+    }
+
+    /// Decode the [`Message`] in the `packet` like [`Message::from_packet`], with the decoding `options`.
+    pub(crate) fn from_packet_with(
+        packet: &'b Packet<'_>,
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        if packet.payload.is_empty() {
+            return Err(unexpected(packet));
+        }
+
+        if let Some(message) = trans::Message::dispatch(packet, options) {
+            message.map(Self::Trans)
+        } else if let Some(message) = userauth::Message::dispatch(packet, options) {
+            message.map(Self::Userauth)
+        } else if let Some(message) = connect::Message::dispatch(packet, options) {
+            message.map(Self::Connect)
+        } else {
+            Ok(Self::Unknown(packet.as_borrow()))
+        }
+    }
+}
+
+impl BinWrite for Message<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> binrw::BinResult<()> {
+        match self {
+            Self::Trans(message) => message.write_options(writer, endian, args),
+            Self::Userauth(message) => message.write_options(writer, endian, args),
+            Self::Connect(message) => message.write_options(writer, endian, args),
+            Self::Unknown(packet) => Ok(writer.write_all(&packet.payload)?),
+        }
+    }
+}
+
+impl WriteEndian for Message<'_> {
+    const ENDIAN: binrw::meta::EndianKind = binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+}
+
+impl IntoOwned for Message<'_> {
+    type Owned = Message<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::Trans(message) => Message::Trans(message.into_owned()),
+            Self::Userauth(message) => Message::Userauth(message.into_owned()),
+            Self::Connect(message) => Message::Connect(message.into_owned()),
+            Self::Unknown(packet) => Message::Unknown(packet.into_owned()),
+        }
+    }
+}
+
+/// The message number of a [`Message`], as the first byte of a [`Packet`]'s payload.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4250#section-4.1>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct MessageId(pub u8);
+
+impl MessageId {
+    /// The `SSH_MSG_DISCONNECT` message number.
+    pub const DISCONNECT: Self = Self(1);
+
+    /// The `SSH_MSG_IGNORE` message number.
+    pub const IGNORE: Self = Self(2);
+
+    /// The `SSH_MSG_UNIMPLEMENTED` message number.
+    pub const UNIMPLEMENTED: Self = Self(3);
+
+    /// The `SSH_MSG_DEBUG` message number.
+    pub const DEBUG: Self = Self(4);
+
+    /// The `SSH_MSG_SERVICE_REQUEST` message number.
+    pub const SERVICE_REQUEST: Self = Self(5);
+
+    /// The `SSH_MSG_SERVICE_ACCEPT` message number.
+    pub const SERVICE_ACCEPT: Self = Self(6);
+
+    /// The `SSH_MSG_EXT_INFO` message number, see <https://datatracker.ietf.org/doc/html/rfc8308#section-2.3>.
+    pub const EXT_INFO: Self = Self(7);
+
+    /// The `SSH_MSG_NEWCOMPRESS` message number, see <https://datatracker.ietf.org/doc/html/rfc8308#section-3.2>.
+    pub const NEWCOMPRESS: Self = Self(8);
+
+    /// The `SSH_MSG_KEXINIT` message number.
+    pub const KEXINIT: Self = Self(20);
+
+    /// The `SSH_MSG_NEWKEYS` message number.
+    pub const NEWKEYS: Self = Self(21);
+
+    /// The `SSH_MSG_KEXDH_INIT` message number, shared with the other key-exchange method specific messages.
+    pub const KEXDH_INIT: Self = Self(30);
+
+    /// The `SSH_MSG_KEXDH_REPLY` message number, shared with the other key-exchange method specific messages.
+    pub const KEXDH_REPLY: Self = Self(31);
+
+    /// The `SSH_MSG_KEX_ECDH_INIT` message number, shared with the other key-exchange method specific messages.
+    pub const KEX_ECDH_INIT: Self = Self(30);
+
+    /// The `SSH_MSG_KEX_ECDH_REPLY` message number, shared with the other key-exchange method specific messages.
+    pub const KEX_ECDH_REPLY: Self = Self(31);
+
+    /// The `SSH_MSG_USERAUTH_REQUEST` message number.
+    pub const USERAUTH_REQUEST: Self = Self(50);
+
+    /// The `SSH_MSG_USERAUTH_FAILURE` message number.
+    pub const USERAUTH_FAILURE: Self = Self(51);
+
+    /// The `SSH_MSG_USERAUTH_SUCCESS` message number.
+    pub const USERAUTH_SUCCESS: Self = Self(52);
+
+    /// The `SSH_MSG_USERAUTH_BANNER` message number.
+    pub const USERAUTH_BANNER: Self = Self(53);
+
+    /// The `SSH_MSG_USERAUTH_PK_OK` message number, shared with the other authentication method specific messages.
+    pub const USERAUTH_PK_OK: Self = Self(60);
+
+    /// The `SSH_MSG_USERAUTH_PASSWD_CHANGEREQ` message number, shared with the other authentication method specific messages.
+    pub const USERAUTH_PASSWD_CHANGEREQ: Self = Self(60);
+
+    /// The `SSH_MSG_USERAUTH_INFO_REQUEST` message number, shared with the other authentication method specific messages.
+    pub const USERAUTH_INFO_REQUEST: Self = Self(60);
+
+    /// The `SSH_MSG_USERAUTH_INFO_RESPONSE` message number, shared with the other authentication method specific messages.
+    pub const USERAUTH_INFO_RESPONSE: Self = Self(61);
+
+    /// The `SSH_MSG_GLOBAL_REQUEST` message number.
+    pub const GLOBAL_REQUEST: Self = Self(80);
+
+    /// The `SSH_MSG_REQUEST_SUCCESS` message number.
+    pub const REQUEST_SUCCESS: Self = Self(81);
+
+    /// The `SSH_MSG_REQUEST_FAILURE` message number.
+    pub const REQUEST_FAILURE: Self = Self(82);
+
+    /// The `SSH_MSG_CHANNEL_OPEN` message number.
+    pub const CHANNEL_OPEN: Self = Self(90);
+
+    /// The `SSH_MSG_CHANNEL_OPEN_CONFIRMATION` message number.
+    pub const CHANNEL_OPEN_CONFIRMATION: Self = Self(91);
+
+    /// The `SSH_MSG_CHANNEL_OPEN_FAILURE` message number.
+    pub const CHANNEL_OPEN_FAILURE: Self = Self(92);
+
+    /// The `SSH_MSG_CHANNEL_WINDOW_ADJUST` message number.
+    pub const CHANNEL_WINDOW_ADJUST: Self = Self(93);
+
+    /// The `SSH_MSG_CHANNEL_DATA` message number.
+    pub const CHANNEL_DATA: Self = Self(94);
+
+    /// The `SSH_MSG_CHANNEL_EXTENDED_DATA` message number.
+    pub const CHANNEL_EXTENDED_DATA: Self = Self(95);
+
+    /// The `SSH_MSG_CHANNEL_EOF` message number.
+    pub const CHANNEL_EOF: Self = Self(96);
+
+    /// The `SSH_MSG_CHANNEL_CLOSE` message number.
+    pub const CHANNEL_CLOSE: Self = Self(97);
+
+    /// The `SSH_MSG_CHANNEL_REQUEST` message number.
+    pub const CHANNEL_REQUEST: Self = Self(98);
+
+    /// The `SSH_MSG_CHANNEL_SUCCESS` message number.
+    pub const CHANNEL_SUCCESS: Self = Self(99);
+
+    /// The `SSH_MSG_CHANNEL_FAILURE` message number.
+    pub const CHANNEL_FAILURE: Self = Self(100);
+
+    /// The `SSH2_MSG_PING` message number, the OpenSSH extension, see <https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL>.
+    pub const PING: Self = Self(192);
+
+    /// The `SSH2_MSG_PONG` message number, the OpenSSH extension, see <https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL>.
+    pub const PONG: Self = Self(193);
+
+    /// The name of the message number, or [`None`] if the number is unassigned
+    /// or specific to the negotiated key-exchange or authentication methods.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.0 {
+            1 => "SSH_MSG_DISCONNECT",
+            2 => "SSH_MSG_IGNORE",
+            3 => "SSH_MSG_UNIMPLEMENTED",
+            4 => "SSH_MSG_DEBUG",
+            5 => "SSH_MSG_SERVICE_REQUEST",
+            6 => "SSH_MSG_SERVICE_ACCEPT",
+            7 => "SSH_MSG_EXT_INFO",
+            8 => "SSH_MSG_NEWCOMPRESS",
+            20 => "SSH_MSG_KEXINIT",
+            21 => "SSH_MSG_NEWKEYS",
+            50 => "SSH_MSG_USERAUTH_REQUEST",
+            51 => "SSH_MSG_USERAUTH_FAILURE",
+            52 => "SSH_MSG_USERAUTH_SUCCESS",
+            53 => "SSH_MSG_USERAUTH_BANNER",
+            80 => "SSH_MSG_GLOBAL_REQUEST",
+            81 => "SSH_MSG_REQUEST_SUCCESS",
+            82 => "SSH_MSG_REQUEST_FAILURE",
+            90 => "SSH_MSG_CHANNEL_OPEN",
+            91 => "SSH_MSG_CHANNEL_OPEN_CONFIRMATION",
+            92 => "SSH_MSG_CHANNEL_OPEN_FAILURE",
+            93 => "SSH_MSG_CHANNEL_WINDOW_ADJUST",
+            94 => "SSH_MSG_CHANNEL_DATA",
+            95 => "SSH_MSG_CHANNEL_EXTENDED_DATA",
+            96 => "SSH_MSG_CHANNEL_EOF",
+            97 => "SSH_MSG_CHANNEL_CLOSE",
+            98 => "SSH_MSG_CHANNEL_REQUEST",
+            99 => "SSH_MSG_CHANNEL_SUCCESS",
+            100 => "SSH_MSG_CHANNEL_FAILURE",
+            192 => "SSH2_MSG_PING",
+            193 => "SSH2_MSG_PONG",
+            _ => return None,
+        })
+    }
+
+    /// Whether the message number is in the range of the **transport** (`SSH-TRANS`) part of the protocol.
+    pub fn is_trans(&self) -> bool {
+        matches!(self.0, 1..=49)
+    }
+
+    /// Whether the message number is in the range of the **authentication** (`SSH-USERAUTH`) part of the protocol.
+    pub fn is_userauth(&self) -> bool {
+        matches!(self.0, 50..=79)
+    }
+
+    /// Whether the message number is in the range of the **connect** (`SSH-CONNECT`) part of the protocol.
+    pub fn is_connect(&self) -> bool {
+        matches!(self.0, 80..=127)
+    }
+
+    /// Whether the message number is in the range reserved for local extensions.
+    pub fn is_local_extension(&self) -> bool {
+        matches!(self.0, 192..=255)
+    }
+}
+
+impl std::fmt::Display for MessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name} ({})", self.0),
+            None => write!(f, "SSH_MSG_{}", self.0),
+        }
+    }
+}
+
+impl From<u8> for MessageId {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
+impl From<MessageId> for u8 {
+    fn from(value: MessageId) -> Self {
+        value.0
+    }
+}
+
+/// The error of a `packet` whose message number is missing or unexpected in the decoding context.
+pub(crate) fn unexpected(packet: &Packet<'_>) -> DecodeError {
+    let err = match packet.payload.first() {
+        Some(id) => binrw::Error::BadMagic {
+            pos: 0x0,
+            found: Box::new(*id),
+        },
+        None => binrw::Error::AssertFail {
+            pos: 0x0,
+            message: "The packet's payload is empty, missing message number".into(),
+        },
+    };
+
+    err.into()
+}
+
+/// A message type with a fixed [`MessageId`], encoded as the first byte of it's payload.
+///
+/// This allows dispatch tables, `SSH_MSG_UNIMPLEMENTED` replies or logging to refer
+/// to the message number of a type without duplicating it.
+pub trait HasMessageId {
+    /// The message number of the type.
+    const MESSAGE_ID: MessageId;
+}
+
+macro_rules! message_id {
+    ($($ty:ty => $id:ident),* $(,)?) => {
+        $(
+            impl HasMessageId for $ty {
+                const MESSAGE_ID: MessageId = MessageId::$id;
+            }
+        )*
+    };
+}
+
+message_id!(
+    trans::Disconnect<'_> => DISCONNECT,
+    trans::Ignore<'_> => IGNORE,
+    trans::Unimplemented => UNIMPLEMENTED,
+    trans::Debug<'_> => DEBUG,
+    trans::ServiceRequest<'_> => SERVICE_REQUEST,
+    trans::ServiceAccept<'_> => SERVICE_ACCEPT,
+    trans::KexInit<'_> => KEXINIT,
+    trans::NewKeys => NEWKEYS,
+    trans::KexdhInit<'_> => KEXDH_INIT,
+    trans::KexdhReply<'_> => KEXDH_REPLY,
+    trans::KexEcdhInit<'_> => KEX_ECDH_INIT,
+    trans::KexEcdhReply<'_> => KEX_ECDH_REPLY,
+    userauth::Request<'_> => USERAUTH_REQUEST,
+    userauth::Failure<'_> => USERAUTH_FAILURE,
+    userauth::Success => USERAUTH_SUCCESS,
+    userauth::Banner<'_> => USERAUTH_BANNER,
+    userauth::PkOk<'_> => USERAUTH_PK_OK,
+    userauth::PasswdChangereq<'_> => USERAUTH_PASSWD_CHANGEREQ,
+    userauth::InfoRequest<'_> => USERAUTH_INFO_REQUEST,
+    userauth::InfoResponse => USERAUTH_INFO_RESPONSE,
+    connect::GlobalRequest<'_> => GLOBAL_REQUEST,
+    connect::RequestSuccess => REQUEST_SUCCESS,
+    connect::ForwardingSuccess => REQUEST_SUCCESS,
+    connect::RequestFailure => REQUEST_FAILURE,
+    connect::ChannelOpen<'_> => CHANNEL_OPEN,
+    connect::ChannelOpenConfirmation => CHANNEL_OPEN_CONFIRMATION,
+    connect::ChannelOpenFailure<'_> => CHANNEL_OPEN_FAILURE,
+    connect::ChannelWindowAdjust => CHANNEL_WINDOW_ADJUST,
+    connect::ChannelData<'_> => CHANNEL_DATA,
+    connect::ChannelExtendedData<'_> => CHANNEL_EXTENDED_DATA,
+    connect::ChannelEof => CHANNEL_EOF,
+    connect::ChannelClose => CHANNEL_CLOSE,
+    connect::ChannelRequest<'_> => CHANNEL_REQUEST,
+    connect::ChannelSuccess => CHANNEL_SUCCESS,
+    connect::ChannelFailure => CHANNEL_FAILURE,
+);
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{arch, TryIntoPacket};
+
+    #[rstest]
+    #[case(&[2, 0, 0, 0, 3, 1, 2, 3])]
+    #[case(&[21])]
+    #[case(&[31, 0, 0, 0, 0])]
+    #[case(&[52])]
+    #[case(&[60, 0xde, 0xad])]
+    #[case(&[81, 0, 0, 0x04, 0xd2])]
+    #[case(&[94, 0, 0, 0, 7, 0, 0, 0, 2, b'h', b'i'])]
+    #[case(&[192, 42])]
+    fn it_reencodes_consistently(#[case] payload: &[u8]) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        let message = packet.decode().unwrap();
+
+        assert_eq!((&message).try_into_packet().unwrap(), packet);
+    }
+
+    #[test]
+    fn it_dispatches_on_message_number() {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&[97, 0, 0, 0, 7]),
+        };
+
+        assert!(matches!(
+            packet.decode().unwrap(),
+            Message::Connect(connect::Message::ChannelClose(connect::ChannelClose {
+                recipient_channel: 7
+            }))
+        ));
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[97, 0, 0, 0, 7])]
+    #[case(&[192, 42])]
+    fn it_rejects_out_of_layer(#[case] payload: &[u8]) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        userauth::Message::from_packet(&packet).unwrap_err();
+    }
+
+    #[test]
+    fn it_decodes_within_layer() {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&[52]),
+        };
+
+        assert!(matches!(
+            userauth::Message::from_packet(&packet).unwrap(),
+            userauth::Message::Success(userauth::Success)
+        ));
+    }
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(&[5, 0, 0, 0, 0], Some(MessageId(5)))]
+    #[case(&[60], Some(MessageId(60)))]
+    #[case(&[94, 0xff], Some(MessageId(94)))]
+    fn it_peeks_message_id(#[case] payload: &[u8], #[case] id: Option<MessageId>) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        assert_eq!(packet.peek_message_id(), id);
+    }
+
+    #[rstest]
+    #[case(MessageId::DISCONNECT, Some("SSH_MSG_DISCONNECT"))]
+    #[case(MessageId::EXT_INFO, Some("SSH_MSG_EXT_INFO"))]
+    #[case(MessageId::KEX_ECDH_INIT, None)]
+    #[case(MessageId::USERAUTH_INFO_RESPONSE, None)]
+    #[case(MessageId::CHANNEL_FAILURE, Some("SSH_MSG_CHANNEL_FAILURE"))]
+    #[case(MessageId::PONG, Some("SSH2_MSG_PONG"))]
+    #[case(MessageId(150), None)]
+    fn it_names_message_id(#[case] id: MessageId, #[case] name: Option<&str>) {
+        assert_eq!(id.name(), name);
+    }
+
+    #[test]
+    fn it_rejects_trailing_bytes() {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&[97, 0, 0, 0, 7, 0xff]),
+        };
+
+        packet.decode().unwrap_err();
+        packet.to::<connect::ChannelClose>().unwrap_err();
+        packet.to_lenient::<connect::ChannelClose>().unwrap();
+    }
+
+    #[test]
+    fn it_rejects_empty() {
+        let packet = Packet {
+            payload: arch::Bytes::default(),
+        };
+
+        packet.decode().unwrap_err();
+    }
+
+    #[cfg(feature = "serde")]
+    #[rstest]
+    #[case(&[94, 0, 0, 0, 1, 0, 0, 0, 3, 0xde, 0xad, 0xff])]
+    #[case(&[2, 0, 0, 0, 3, 1, 2, 3])]
+    #[case(&[60, 0xde, 0xad])]
+    #[case(&[0xfe, 0x42])]
+    fn it_roundtrips_through_serde(#[case] payload: &[u8]) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+        let message = packet.decode().unwrap();
+
+        let json = serde_json::to_string(&message).unwrap();
+        let message: Message = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(&*(&message).try_into_packet().unwrap().payload, payload);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_bytes_as_base64() {
+        let data = crate::connect::ChannelData {
+            recipient_channel: 1,
+            data: arch::Bytes::borrowed(b"hello"),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&data).unwrap(),
+            r#"{"recipient_channel":1,"data":"aGVsbG8="}"#
+        );
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[rstest]
+    #[case(&[0; 64])]
+    #[case(&[0xff; 256])]
+    #[case(b"ssh-ed25519,curve25519-sha256\x00\x01\x02\x03 arbitrary input for structured fuzzing")]
+    fn it_roundtrips_arbitrary_messages(#[case] data: &[u8]) {
+        fn roundtrip<'d, T>(data: &'d [u8])
+        where
+            T: arbitrary::Arbitrary<'d>
+                + crate::Decode
+                + for<'a> BinWrite<Args<'a> = ()>
+                + WriteEndian,
+        {
+            let message: T = arbitrary::Unstructured::new(data).arbitrary().unwrap();
+            let packet = (&message).try_into_packet().unwrap();
+
+            let decoded: T = packet.to().unwrap();
+            assert_eq!((&decoded).try_into_packet().unwrap(), packet);
+        }
+
+        roundtrip::<trans::Disconnect>(data);
+        roundtrip::<trans::KexInit>(data);
+        roundtrip::<userauth::Request>(data);
+        roundtrip::<userauth::InfoRequest>(data);
+        roundtrip::<connect::GlobalRequest>(data);
+        roundtrip::<connect::ChannelOpen>(data);
+        roundtrip::<connect::ChannelRequest>(data);
+        roundtrip::<connect::ChannelData>(data);
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn it_encodes_the_message_id() {
+        fn check<'a, T>()
+        where
+            T: HasMessageId
+                + arbitrary::Arbitrary<'a>
+                + for<'w> BinWrite<Args<'w> = ()>
+                + WriteEndian,
+        {
+            let message: T = arbitrary::Unstructured::new(&[0; 256]).arbitrary().unwrap();
+            let packet = (&message).try_into_packet().unwrap();
+
+            assert_eq!(packet.peek_message_id(), Some(T::MESSAGE_ID));
+        }
+
+        check::<trans::Disconnect>();
+        check::<trans::Ignore>();
+        check::<trans::Unimplemented>();
+        check::<trans::Debug>();
+        check::<trans::ServiceRequest>();
+        check::<trans::ServiceAccept>();
+        check::<trans::KexInit>();
+        check::<trans::NewKeys>();
+        check::<trans::KexdhInit>();
+        check::<trans::KexdhReply>();
+        check::<trans::KexEcdhInit>();
+        check::<trans::KexEcdhReply>();
+        check::<userauth::Request>();
+        check::<userauth::Failure>();
+        check::<userauth::Success>();
+        check::<userauth::Banner>();
+        check::<userauth::PkOk>();
+        check::<userauth::PasswdChangereq>();
+        check::<userauth::InfoRequest>();
+        check::<userauth::InfoResponse>();
+        check::<connect::GlobalRequest>();
+        check::<connect::RequestSuccess>();
+        check::<connect::ForwardingSuccess>();
+        check::<connect::RequestFailure>();
+        check::<connect::ChannelOpen>();
+        check::<connect::ChannelOpenConfirmation>();
+        check::<connect::ChannelOpenFailure>();
+        check::<connect::ChannelWindowAdjust>();
+        check::<connect::ChannelData>();
+        check::<connect::ChannelExtendedData>();
+        check::<connect::ChannelEof>();
+        check::<connect::ChannelClose>();
+        check::<connect::ChannelRequest>();
+        check::<connect::ChannelSuccess>();
+        check::<connect::ChannelFailure>();
+    }
+
+    #[test]
+    fn it_compares_decoded_messages() {
+        let request = connect::ChannelRequest {
+            recipient_channel: 1,
+            want_reply: true.into(),
+            context: connect::ChannelRequestContext::Exec {
+                command: arch::Bytes::borrowed(b"uname -a"),
+            },
+        };
+
+        let packet = (&request).try_into_packet().unwrap();
+        assert_eq!(packet.to::<connect::ChannelRequest>().unwrap(), request);
+        assert_eq!(
+            packet.decode().unwrap(),
+            Message::Connect(connect::Message::ChannelRequest(request))
+        );
+    }
+
+    #[rstest]
+    #[case(&[2, 0, 0, 0, 3, 1, 2, 3])]
+    #[case(&[31, 0, 0, 0, 0])]
+    #[case(&[50, 0, 0, 0, 1, b'u', 0, 0, 0, 0, 0, 0, 0, 4, b'n', b'o', b'n', b'e'])]
+    #[case(&[94, 0, 0, 0, 7, 0, 0, 0, 2, b'h', b'i'])]
+    #[case(&[192, 42])]
+    fn it_detaches_messages_from_their_buffer(#[case] payload: &[u8]) {
+        let buf = payload.to_vec();
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&buf),
+        };
+
+        let message: Message<'static> = packet.decode().unwrap().into_owned();
+        drop(buf);
+
+        assert_eq!(&*(&message).try_into_packet().unwrap().payload, payload);
+    }
+
+    #[test]
+    fn it_owns_borrowed_fields() {
+        let command = String::from("uname -a");
+        let request = connect::ChannelRequest {
+            recipient_channel: 1,
+            want_reply: true.into(),
+            context: connect::ChannelRequestContext::Exec {
+                command: arch::Bytes::borrowed(command.as_bytes()),
+            },
+        };
+
+        let owned: connect::ChannelRequest<'static> = request.into_owned();
+        drop(command);
+
+        assert!(matches!(
+            owned.context,
+            connect::ChannelRequestContext::Exec { ref command } if &**command == b"uname -a"
+        ));
+    }
+}
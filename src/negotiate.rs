@@ -0,0 +1,655 @@
+//! Negotiation of the algorithms of a key exchange, from the local preferences
+//! and the `SSH_MSG_KEXINIT` messages exchanged with the peer.
+//!
+//! see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.1>.
+
+use crate::{
+    arch::{self, Ascii, NameList},
+    trans, CipherCore, IntoOwned, Mac, MacMode,
+};
+
+/// The side of the connection the local end is playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// The local end is the client.
+    Client,
+
+    /// The local end is the server.
+    Server,
+}
+
+/// The pseudo-algorithms indicating the support of `SSH_MSG_EXT_INFO`, by the client and server.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc8308#section-2.1>.
+const EXT_INFO: [Ascii<'static>; 2] = [arch::ascii!("ext-info-c"), arch::ascii!("ext-info-s")];
+
+/// The pseudo-algorithms indicating the support of the _strict key exchange_, by the client and server.
+///
+/// see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL>.
+const STRICT_KEX: [Ascii<'static>; 2] = [
+    arch::ascii!("kex-strict-c-v00@openssh.com"),
+    arch::ascii!("kex-strict-s-v00@openssh.com"),
+];
+
+/// Whether `name` is one of the [`EXT_INFO`] or [`STRICT_KEX`] pseudo-algorithms.
+fn is_pseudo_algorithm(name: &str) -> bool {
+    EXT_INFO
+        .iter()
+        .chain(&STRICT_KEX)
+        .any(|pseudo| **pseudo == *name)
+}
+
+/// The ciphers providing their own authentication, for which no MAC needs to be agreed upon.
+const AEAD_CIPHERS: &[&str] = &[
+    "chacha20-poly1305@openssh.com",
+    "aes128-gcm@openssh.com",
+    "aes256-gcm@openssh.com",
+];
+
+/// The local preferences of algorithms, from the most to the least preferred,
+/// applying to both directions.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Preferences<'b> {
+    /// Key-exchange algorithms, without the `ext-info` and `kex-strict` pseudo-algorithms.
+    pub kex: NameList<'b>,
+
+    /// Server host-key algorithms.
+    pub host_key: NameList<'b>,
+
+    /// Encryption algorithms.
+    pub cipher: NameList<'b>,
+
+    /// MAC algorithms.
+    pub mac: NameList<'b>,
+
+    /// Compression algorithms.
+    pub compression: NameList<'b>,
+
+    /// Whether to advertise the support of `SSH_MSG_EXT_INFO`.
+    pub ext_info: bool,
+
+    /// Whether to advertise the support of the _strict key exchange_.
+    pub strict_kex: bool,
+}
+
+impl Preferences<'_> {
+    /// Build the outgoing [`trans::KexInit`] with the `cookie`, advertising the preferences for `role`.
+    pub fn kexinit(&self, role: Role, cookie: [u8; 16]) -> trans::KexInit<'static> {
+        let kex_algorithms = self
+            .kex
+            .names()
+            .chain(self.ext_info.then(|| EXT_INFO[role as usize].clone()))
+            .chain(self.strict_kex.then(|| STRICT_KEX[role as usize].clone()))
+            .collect();
+
+        trans::KexInit {
+            cookie,
+            kex_algorithms,
+            server_host_key_algorithms: self.host_key.clone().into_owned(),
+            encryption_algorithms_client_to_server: self.cipher.clone().into_owned(),
+            encryption_algorithms_server_to_client: self.cipher.clone().into_owned(),
+            mac_algorithms_client_to_server: self.mac.clone().into_owned(),
+            mac_algorithms_server_to_client: self.mac.clone().into_owned(),
+            compression_algorithms_client_to_server: self.compression.clone().into_owned(),
+            compression_algorithms_server_to_client: self.compression.clone().into_owned(),
+            languages_client_to_server: Default::default(),
+            languages_server_to_client: Default::default(),
+            first_kex_packet_follows: arch::Bool(false),
+        }
+    }
+}
+
+/// The algorithms agreed upon for a single direction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Algorithms<'a> {
+    /// Encryption algorithm.
+    pub cipher: Ascii<'a>,
+
+    /// MAC algorithm, or [`None`] if the cipher is an AEAD and no MAC was in common.
+    pub mac: Option<Ascii<'a>>,
+
+    /// Compression algorithm.
+    pub compression: Ascii<'a>,
+}
+
+impl Algorithms<'_> {
+    /// Whether the `cipher` installed for this direction implements the agreed algorithms,
+    /// the MAC being ignored for _AEAD_ ciphers.
+    pub fn matches(&self, cipher: &impl CipherCore) -> bool {
+        let mac = cipher.mac();
+
+        *cipher.algorithm() == *self.cipher
+            && (mac.mode() == MacMode::Aead || self.mac.as_deref() == Some(&*mac.algorithm()))
+    }
+}
+
+/// The outcome of a successful negotiation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Negotiated<'a> {
+    /// Key-exchange algorithm.
+    pub kex: Ascii<'a>,
+
+    /// Server host-key algorithm.
+    pub host_key: Ascii<'a>,
+
+    /// Algorithms for the client -> server direction.
+    pub client_to_server: Algorithms<'a>,
+
+    /// Algorithms for the server -> client direction.
+    pub server_to_client: Algorithms<'a>,
+
+    /// Whether the peer advertised the support of `SSH_MSG_EXT_INFO`,
+    /// allowing it to be sent after the first `SSH_MSG_NEWKEYS`.
+    pub ext_info: bool,
+
+    /// Whether both peers advertised the support of the _strict key exchange_,
+    /// to be honored only during the initial key exchange.
+    pub strict_kex: bool,
+
+    /// Whether the peer guessed the algorithms wrong while sending it's first key exchange packet,
+    /// which is then to be ignored.
+    pub ignore_guess: bool,
+}
+
+impl Negotiated<'_> {
+    /// The algorithms of the packets sent by the local end playing `role`.
+    pub fn outbound(&self, role: Role) -> &Algorithms<'_> {
+        match role {
+            Role::Client => &self.client_to_server,
+            Role::Server => &self.server_to_client,
+        }
+    }
+
+    /// The algorithms of the packets received by the local end playing `role`.
+    pub fn inbound(&self, role: Role) -> &Algorithms<'_> {
+        match role {
+            Role::Client => &self.server_to_client,
+            Role::Server => &self.client_to_server,
+        }
+    }
+}
+
+/// No algorithm of a category was in common between the peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("No {0} algorithm in common with the peer")]
+pub struct NoCommonAlgorithm(pub &'static str);
+
+impl NoCommonAlgorithm {
+    /// Create a [`trans::Disconnect`] message to be sent to the peer for this error.
+    pub fn to_disconnect(&self) -> trans::Disconnect<'static> {
+        trans::Disconnect::new(trans::DisconnectReason::KeyExchangeFailed, self.to_string())
+    }
+}
+
+/// Negotiate the algorithms from the local end's `ours` [`trans::KexInit`] playing `role`,
+/// and the peer's `theirs`, picking the first algorithm of the client supported by the server.
+pub fn negotiate<'a>(
+    role: Role,
+    ours: &'a trans::KexInit<'_>,
+    theirs: &'a trans::KexInit<'_>,
+) -> Result<Negotiated<'a>, NoCommonAlgorithm> {
+    let (client, server) = match role {
+        Role::Client => (ours, theirs),
+        Role::Server => (theirs, ours),
+    };
+
+    let pick = |client: &'a NameList<'_>, server: &'a NameList<'_>, category| {
+        client
+            .preferred_in(server)
+            .ok_or(NoCommonAlgorithm(category))
+    };
+    let kex = client
+        .kex_algorithms
+        .names()
+        .filter(|name| !is_pseudo_algorithm(name))
+        .find(|name| server.kex_algorithms.contains(name))
+        .ok_or(NoCommonAlgorithm("key-exchange"))?;
+    let host_key = pick(
+        &client.server_host_key_algorithms,
+        &server.server_host_key_algorithms,
+        "host-key",
+    )?;
+
+    let algorithms = |ciphers: [&'a NameList<'_>; 2],
+                      macs: [&'a NameList<'_>; 2],
+                      compressions: [&'a NameList<'_>; 2],
+                      [cipher, mac, compression]: [&'static str; 3]| {
+        let cipher = pick(ciphers[0], ciphers[1], cipher)?;
+        let mac = match pick(macs[0], macs[1], mac) {
+            Err(_) if AEAD_CIPHERS.contains(&&*cipher) => None,
+            mac => Some(mac?),
+        };
+        let compression = pick(compressions[0], compressions[1], compression)?;
+
+        Ok(Algorithms {
+            cipher,
+            mac,
+            compression,
+        })
+    };
+    let client_to_server = algorithms(
+        [
+            &client.encryption_algorithms_client_to_server,
+            &server.encryption_algorithms_client_to_server,
+        ],
+        [
+            &client.mac_algorithms_client_to_server,
+            &server.mac_algorithms_client_to_server,
+        ],
+        [
+            &client.compression_algorithms_client_to_server,
+            &server.compression_algorithms_client_to_server,
+        ],
+        [
+            "client -> server encryption",
+            "client -> server MAC",
+            "client -> server compression",
+        ],
+    )?;
+    let server_to_client = algorithms(
+        [
+            &client.encryption_algorithms_server_to_client,
+            &server.encryption_algorithms_server_to_client,
+        ],
+        [
+            &client.mac_algorithms_server_to_client,
+            &server.mac_algorithms_server_to_client,
+        ],
+        [
+            &client.compression_algorithms_server_to_client,
+            &server.compression_algorithms_server_to_client,
+        ],
+        [
+            "server -> client encryption",
+            "server -> client MAC",
+            "server -> client compression",
+        ],
+    )?;
+
+    let peer = match role {
+        Role::Client => Role::Server,
+        Role::Server => Role::Client,
+    };
+    let ext_info = theirs.kex_algorithms.contains(&EXT_INFO[peer as usize]);
+    let strict_kex = client
+        .kex_algorithms
+        .contains(&STRICT_KEX[Role::Client as usize])
+        && server
+            .kex_algorithms
+            .contains(&STRICT_KEX[Role::Server as usize]);
+    let ignore_guess = *theirs.first_kex_packet_follows
+        && (theirs.kex_algorithms.names().next().as_deref() != Some(&*kex)
+            || theirs.server_host_key_algorithms.names().next().as_deref() != Some(&*host_key));
+
+    Ok(Negotiated {
+        kex,
+        host_key,
+        client_to_server,
+        server_to_client,
+        ext_info,
+        strict_kex,
+        ignore_guess,
+    })
+}
+
+/// The algorithms offered by both peers in a category, and their intersection.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CategoryDiff<'a> {
+    /// The name of the category, such as `client -> server encryption`.
+    pub category: &'static str,
+
+    /// The algorithms offered by the client, in it's order of preference.
+    pub client: Vec<Ascii<'a>>,
+
+    /// The algorithms offered by the server, in it's order of preference.
+    pub server: Vec<Ascii<'a>>,
+
+    /// The algorithms offered by both peers, in the client's order of preference.
+    pub common: Vec<Ascii<'a>>,
+
+    /// Whether the lack of common algorithms fails the negotiation, which is not the case
+    /// for the MAC algorithms when an AEAD cipher is in common.
+    pub mismatch: bool,
+}
+
+/// A diagnostic of the algorithms offered by both peers in each category of a key exchange,
+/// to report why a negotiation failed, see [`diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diff<'a> {
+    /// The categories, in the order of the [`trans::KexInit`] fields.
+    pub categories: Vec<CategoryDiff<'a>>,
+}
+
+impl<'a> Diff<'a> {
+    /// The categories failing the negotiation.
+    pub fn mismatches(&self) -> impl Iterator<Item = &CategoryDiff<'a>> {
+        self.categories.iter().filter(|category| category.mismatch)
+    }
+}
+
+impl std::fmt::Display for Diff<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, category) in self.mismatches().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+
+            write!(
+                f,
+                "no {} algorithm in common, client offered [{}] and server offered [{}]",
+                category.category,
+                NameList::from(category.client.clone()).0,
+                NameList::from(category.server.clone()).0,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare the algorithms of the local end's `ours` [`trans::KexInit`] playing `role` and the peer's `theirs`,
+/// to report per category what each side offered and which intersections are empty.
+///
+/// The `ext-info` and `kex-strict` pseudo-algorithms are left out of the key-exchange category.
+pub fn diff<'a>(
+    role: Role,
+    ours: &'a trans::KexInit<'_>,
+    theirs: &'a trans::KexInit<'_>,
+) -> Diff<'a> {
+    let (client, server) = match role {
+        Role::Client => (ours, theirs),
+        Role::Server => (theirs, ours),
+    };
+
+    let category = |category, client: &'a NameList<'_>, server: &'a NameList<'_>| {
+        let names = |list: &'a NameList<'_>| {
+            list.names()
+                .filter(|name| !is_pseudo_algorithm(name))
+                .collect::<Vec<_>>()
+        };
+        let (client, server) = (names(client), names(server));
+        let common = client
+            .iter()
+            .filter(|name| server.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        CategoryDiff {
+            category,
+            mismatch: common.is_empty(),
+            client,
+            server,
+            common,
+        }
+    };
+    let direction = |[cipher, mac, compression]: [&'static str; 3],
+                     ciphers: [&'a NameList<'_>; 2],
+                     macs: [&'a NameList<'_>; 2],
+                     compressions: [&'a NameList<'_>; 2]| {
+        let cipher = category(cipher, ciphers[0], ciphers[1]);
+        let mut mac = category(mac, macs[0], macs[1]);
+        if cipher
+            .common
+            .first()
+            .is_some_and(|cipher| AEAD_CIPHERS.contains(&&**cipher))
+        {
+            mac.mismatch = false;
+        }
+
+        [
+            cipher,
+            mac,
+            category(compression, compressions[0], compressions[1]),
+        ]
+    };
+
+    let [c2s_cipher, c2s_mac, c2s_compression] = direction(
+        [
+            "client -> server encryption",
+            "client -> server MAC",
+            "client -> server compression",
+        ],
+        [
+            &client.encryption_algorithms_client_to_server,
+            &server.encryption_algorithms_client_to_server,
+        ],
+        [
+            &client.mac_algorithms_client_to_server,
+            &server.mac_algorithms_client_to_server,
+        ],
+        [
+            &client.compression_algorithms_client_to_server,
+            &server.compression_algorithms_client_to_server,
+        ],
+    );
+    let [s2c_cipher, s2c_mac, s2c_compression] = direction(
+        [
+            "server -> client encryption",
+            "server -> client MAC",
+            "server -> client compression",
+        ],
+        [
+            &client.encryption_algorithms_server_to_client,
+            &server.encryption_algorithms_server_to_client,
+        ],
+        [
+            &client.mac_algorithms_server_to_client,
+            &server.mac_algorithms_server_to_client,
+        ],
+        [
+            &client.compression_algorithms_server_to_client,
+            &server.compression_algorithms_server_to_client,
+        ],
+    );
+
+    Diff {
+        categories: vec![
+            category(
+                "key-exchange",
+                &client.kex_algorithms,
+                &server.kex_algorithms,
+            ),
+            category(
+                "host-key",
+                &client.server_host_key_algorithms,
+                &server.server_host_key_algorithms,
+            ),
+            c2s_cipher,
+            s2c_cipher,
+            c2s_mac,
+            s2c_mac,
+            c2s_compression,
+            s2c_compression,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use super::*;
+
+    fn list(names: &str) -> NameList<'_> {
+        NameList(Ascii::borrowed(names).unwrap())
+    }
+
+    fn preferences<'b>(kex: &'b str, cipher: &'b str, mac: &'b str) -> Preferences<'b> {
+        Preferences {
+            kex: list(kex),
+            host_key: list("ssh-ed25519,rsa-sha2-256"),
+            cipher: list(cipher),
+            mac: list(mac),
+            compression: list("none"),
+            ext_info: true,
+            strict_kex: true,
+        }
+    }
+
+    #[test]
+    fn it_negotiates_the_client_preferences() {
+        let client = preferences(
+            "curve25519-sha256,diffie-hellman-group14-sha256",
+            "chacha20-poly1305@openssh.com,aes256-ctr",
+            "hmac-sha2-256",
+        )
+        .kexinit(Role::Client, [0; 16]);
+        let server = preferences(
+            "diffie-hellman-group14-sha256,curve25519-sha256",
+            "aes256-ctr,chacha20-poly1305@openssh.com",
+            "hmac-sha2-512",
+        )
+        .kexinit(Role::Server, [0; 16]);
+
+        assert!(client.kex_algorithms.contains("ext-info-c"));
+        assert!(server
+            .kex_algorithms
+            .contains("kex-strict-s-v00@openssh.com"));
+
+        let negotiated = negotiate(Role::Server, &server, &client).unwrap();
+        assert_eq!(
+            negotiated,
+            negotiate(Role::Client, &client, &server).unwrap()
+        );
+
+        assert_eq!(&*negotiated.kex, "curve25519-sha256");
+        assert_eq!(&*negotiated.host_key, "ssh-ed25519");
+        assert_eq!(
+            negotiated.outbound(Role::Client),
+            &Algorithms {
+                cipher: Ascii::borrowed("chacha20-poly1305@openssh.com").unwrap(),
+                mac: None,
+                compression: Ascii::borrowed("none").unwrap(),
+            }
+        );
+        assert!(negotiated.strict_kex);
+        assert!(!negotiated.ignore_guess);
+    }
+
+    #[rstest]
+    #[case("ext-info-c", "curve25519-sha256", "aes256-ctr", "key-exchange")]
+    #[case(
+        "curve25519-sha256",
+        "curve25519-sha256",
+        "aes128-ctr",
+        "client -> server encryption"
+    )]
+    fn it_fails_without_common_algorithms(
+        #[case] client_kex: &str,
+        #[case] server_kex: &str,
+        #[case] client_cipher: &str,
+        #[case] category: &str,
+    ) {
+        let client =
+            preferences(client_kex, client_cipher, "hmac-sha2-256").kexinit(Role::Client, [0; 16]);
+        let server =
+            preferences(server_kex, "aes256-ctr", "hmac-sha2-256").kexinit(Role::Server, [0; 16]);
+
+        let err = negotiate(Role::Client, &client, &server).unwrap_err();
+        assert_eq!(err.0, category);
+        assert_eq!(
+            err.to_disconnect().reason,
+            trans::DisconnectReason::KeyExchangeFailed
+        );
+    }
+
+    #[test]
+    fn it_reports_the_peer_extensions_and_guess() {
+        let ours = Preferences {
+            ext_info: false,
+            strict_kex: false,
+            ..preferences("curve25519-sha256", "aes256-ctr", "hmac-sha2-256")
+        }
+        .kexinit(Role::Server, [0; 16]);
+        let theirs = trans::KexInit {
+            first_kex_packet_follows: arch::Bool(true),
+            ..preferences(
+                "diffie-hellman-group14-sha256,curve25519-sha256",
+                "aes256-ctr",
+                "hmac-sha2-256",
+            )
+            .kexinit(Role::Client, [0; 16])
+        };
+
+        let negotiated = negotiate(Role::Server, &ours, &theirs).unwrap();
+        assert!(negotiated.ext_info);
+        assert!(!negotiated.strict_kex);
+        assert!(negotiated.ignore_guess);
+    }
+
+    #[test]
+    fn it_diffs_the_offered_algorithms() {
+        let client = preferences(
+            "curve25519-sha256",
+            "aes128-ctr,chacha20-poly1305@openssh.com",
+            "hmac-sha2-256",
+        )
+        .kexinit(Role::Client, [0; 16]);
+        let server = preferences(
+            "curve25519-sha256,diffie-hellman-group14-sha256",
+            "aes256-ctr",
+            "hmac-sha2-512",
+        )
+        .kexinit(Role::Server, [0; 16]);
+
+        let report = diff(Role::Server, &server, &client);
+        assert_eq!(report.categories.len(), 8);
+        assert_eq!(
+            report.categories[0],
+            CategoryDiff {
+                category: "key-exchange",
+                client: vec![arch::ascii!("curve25519-sha256")],
+                server: vec![
+                    arch::ascii!("curve25519-sha256"),
+                    arch::ascii!("diffie-hellman-group14-sha256")
+                ],
+                common: vec![arch::ascii!("curve25519-sha256")],
+                mismatch: false,
+            }
+        );
+        assert_eq!(
+            report
+                .mismatches()
+                .map(|category| category.category)
+                .collect::<Vec<_>>(),
+            [
+                "client -> server encryption",
+                "server -> client encryption",
+                "client -> server MAC",
+                "server -> client MAC"
+            ]
+        );
+        assert!(report.to_string().starts_with(
+            "no client -> server encryption algorithm in common, \
+            client offered [aes128-ctr,chacha20-poly1305@openssh.com] and server offered [aes256-ctr]; "
+        ));
+
+        // No MAC is needed with a common AEAD cipher
+        let server = preferences(
+            "curve25519-sha256",
+            "chacha20-poly1305@openssh.com",
+            "hmac-sha2-512",
+        )
+        .kexinit(Role::Server, [0; 16]);
+        assert_eq!(diff(Role::Client, &client, &server).mismatches().count(), 0);
+        negotiate(Role::Client, &client, &server).unwrap();
+    }
+
+    #[cfg(feature = "none")]
+    #[test]
+    fn it_matches_installed_ciphers() {
+        let mut algorithms = Algorithms {
+            cipher: arch::ascii!("none"),
+            mac: Some(arch::ascii!("none")),
+            compression: arch::ascii!("none"),
+        };
+        assert!(algorithms.matches(&crate::NoneCipher));
+
+        algorithms.mac = Some(arch::ascii!("hmac-sha2-256"));
+        assert!(!algorithms.matches(&crate::NoneCipher));
+    }
+}
@@ -0,0 +1,69 @@
+/// A conversion of a message or type borrowing from an input buffer
+/// into one owning it's data, detached from the input's lifetime.
+///
+/// This allows messages built from borrowed data to be stored or held across `.await` points,
+/// at the cost of copying the borrowed data, while owned data is moved as-is;
+/// decoded messages already own their data, see [`Packet::to`](crate::Packet::to).
+pub trait IntoOwned {
+    /// The owned counterpart of `Self`, usually `Self` with a `'static` lifetime.
+    type Owned: 'static;
+
+    /// Convert `self` into it's owned counterpart, copying any borrowed data.
+    fn into_owned(self) -> Self::Owned;
+}
+
+impl<T: IntoOwned> IntoOwned for Option<T> {
+    type Owned = Option<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        self.map(IntoOwned::into_owned)
+    }
+}
+
+impl<T: IntoOwned> IntoOwned for Box<T> {
+    type Owned = Box<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        Box::new((*self).into_owned())
+    }
+}
+
+impl<T: IntoOwned> IntoOwned for Vec<T> {
+    type Owned = Vec<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        self.into_iter().map(IntoOwned::into_owned).collect()
+    }
+}
+
+/// Implement [`IntoOwned`] for types without a lifetime as the identity,
+/// or for structures by converting the fields before the `;` and moving the ones after it.
+macro_rules! into_owned {
+    ($($ty:ident),* $(,)?) => {
+        $(
+            impl $crate::IntoOwned for $ty {
+                type Owned = Self;
+
+                fn into_owned(self) -> Self::Owned {
+                    self
+                }
+            }
+        )*
+    };
+    ($($ty:ident { $($owned:ident),* $(; $($kept:ident),*)? }),* $(,)?) => {
+        $(
+            impl $crate::IntoOwned for $ty<'_> {
+                type Owned = $ty<'static>;
+
+                fn into_owned(self) -> Self::Owned {
+                    $ty {
+                        $($owned: $crate::IntoOwned::into_owned(self.$owned),)*
+                        $($($kept: self.$kept,)*)?
+                    }
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use into_owned;
@@ -0,0 +1,144 @@
+use rand_core::CryptoRngCore;
+
+use super::{
+    arch, cipher, Decode, Direction, Error, OpeningCipher, Packet, PacketObserver, PacketReader,
+    PacketWriter, SealingCipher, Seq, TryIntoPacket,
+};
+
+impl Packet<'_> {
+    /// Read a [`Packet`], incrementing the `seq` sequence number, from the provided blocking `reader`.
+    pub fn from_blocking_reader<R, C>(
+        reader: &mut R,
+        cipher: &mut C,
+        seq: &mut Seq,
+    ) -> Result<Self, Error>
+    where
+        R: std::io::Read,
+        C: OpeningCipher,
+    {
+        let mut buf = Vec::new();
+        Packet::from_blocking_reader_into(reader, cipher, seq, &mut buf)?;
+
+        Ok(Self {
+            payload: buf.into(),
+        })
+    }
+
+    /// Read a [`Packet`], incrementing the `seq` sequence number, from the provided blocking `reader`,
+    /// reusing the provided `buf` to hold the payload instead of allocating a new buffer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(seq = seq.get()))
+    )]
+    pub fn from_blocking_reader_into<'a, R, C>(
+        reader: &mut R,
+        cipher: &mut C,
+        seq: &mut Seq,
+        buf: &'a mut Vec<u8>,
+    ) -> Result<Packet<'a>, Error>
+    where
+        R: std::io::Read,
+        C: OpeningCipher,
+    {
+        buf.clear();
+        buf.resize(cipher::header_size(cipher), 0);
+        reader.read_exact(&mut buf[..])?;
+
+        let len = Packet::open_header(buf, cipher, seq.get())?;
+
+        // Read the rest of the data and the MAC from the reader
+        buf.resize(
+            std::mem::size_of_val(&len) + len as usize + cipher.tag_size(),
+            0,
+        );
+        reader.read_exact(&mut buf[cipher::header_size(cipher)..])?;
+
+        Packet::open_payload(buf, cipher, seq.get())?;
+        seq.increment();
+
+        Ok(Packet {
+            payload: arch::Bytes::borrowed(buf),
+        })
+    }
+
+    /// Write the [`Packet`], padded with random bytes from `rng` and incrementing
+    /// the `seq` sequence number, to the provided blocking `writer`.
+    ///
+    /// The sealed packet and it's MAC are emitted with a single `write_all`,
+    /// so that a packet is not fragmented across writes.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(seq = seq.get()))
+    )]
+    pub fn to_blocking_writer<W, C>(
+        &self,
+        writer: &mut W,
+        cipher: &mut C,
+        seq: &mut Seq,
+        rng: &mut dyn CryptoRngCore,
+    ) -> Result<(), Error>
+    where
+        W: std::io::Write,
+        C: SealingCipher,
+    {
+        let buf = self.seal(cipher, seq.get(), rng)?;
+
+        writer.write_all(&buf)?;
+        seq.increment();
+
+        Ok(())
+    }
+}
+
+impl<R, C, O> PacketReader<R, C, O>
+where
+    R: std::io::Read,
+    C: OpeningCipher,
+    O: PacketObserver,
+{
+    /// Receive a [`Packet`] from the underlying blocking reader, borrowing the internal buffer.
+    pub fn blocking_recv_packet(&mut self) -> Result<Packet<'_>, Error> {
+        loop {
+            let seq = self.seq.get();
+            let read = match self.reader.read(self.state.unfilled(&self.cipher)) {
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                read => read?,
+            };
+
+            if self
+                .state
+                .advance(read, &mut self.cipher, &mut self.seq, &mut self.observer)?
+            {
+                let packet = self.state.packet();
+                self.observer.observe(Direction::Inbound, seq, &packet);
+
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Receive a [`Packet`] from the underlying blocking reader, and deserialize it into `T`.
+    pub fn blocking_recv<T: Decode>(&mut self) -> Result<T, Error> {
+        let options = self.options;
+
+        Ok(self.blocking_recv_packet()?.to_with(&options)?)
+    }
+}
+
+impl<W, C, G, O> PacketWriter<W, C, G, O>
+where
+    W: std::io::Write,
+    C: SealingCipher,
+    G: CryptoRngCore,
+    O: PacketObserver,
+{
+    /// Send the `message` as a [`Packet`] to the underlying blocking writer.
+    pub fn blocking_send<'b>(&mut self, message: impl TryIntoPacket<'b>) -> Result<(), Error> {
+        self.seal_message(message)?;
+
+        self.writer.write_all(&self.buf)?;
+        self.seq.increment();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use binrw::{io::NoSeek, meta::WriteEndian, BinWrite};
+use bytes::{Buf, BufMut};
+
+use super::Packet;
+use crate::{arch, Decode, DecodeError};
+
+impl Packet<'_> {
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    /// Serialize the `message` directly at the end of the provided `buf`,
+    /// without going through an intermediate buffer.
+    pub fn encode_buf<T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian>(
+        message: &T,
+        buf: &mut impl BufMut,
+    ) -> Result<(), binrw::Error> {
+        message.write(&mut NoSeek::new(buf.writer()))
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    /// Deserialize a `T` from the whole remaining content of the provided `buf`, as a [`Packet`]'s payload,
+    /// failing if it contains trailing bytes after the message.
+    ///
+    /// The `buf` is parsed in place when it is contiguous, and consumed entirely on success,
+    /// although the `string`s of the message are still copied out of it, see [`Packet::to`](crate::Packet::to).
+    pub fn decode_buf<T: Decode>(buf: &mut impl Buf) -> Result<T, DecodeError> {
+        let message = if buf.chunk().len() == buf.remaining() {
+            Packet {
+                payload: arch::Bytes::borrowed(buf.chunk()),
+            }
+            .to()?
+        } else {
+            Packet {
+                payload: arch::Bytes::borrowed(&buf.copy_to_bytes(buf.remaining())),
+            }
+            .to()?
+        };
+        buf.advance(buf.remaining());
+
+        Ok(message)
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    /// Create an owned [`Packet`] from the whole remaining content of the provided `buf`, consuming it.
+    pub fn from_buf(buf: &mut impl Buf) -> Packet<'static> {
+        let mut payload = Vec::with_capacity(buf.remaining());
+        payload.put(buf);
+
+        Packet {
+            payload: arch::Bytes::owned(payload),
+        }
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    /// Write the [`Packet`]'s payload at the end of the provided `buf`.
+    pub fn put_buf(&self, buf: &mut impl BufMut) {
+        buf.put_slice(&self.payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use super::*;
+    use crate::{connect, TryIntoPacket};
+
+    #[test]
+    fn it_encodes_and_decodes_over_buffers() {
+        let message = connect::ChannelData {
+            recipient_channel: 3,
+            data: arch::Bytes::borrowed(b"payload"),
+        };
+
+        let mut buf = bytes::BytesMut::new();
+        Packet::encode_buf(&message, &mut buf).unwrap();
+        assert_eq!(&buf[..], &*(&message).try_into_packet().unwrap().payload);
+
+        // Split the buffer in two non-contiguous chunks
+        let (head, tail) = buf.split_at(3);
+        let decoded: connect::ChannelData = Packet::decode_buf(&mut head.chain(tail)).unwrap();
+        assert_eq!(decoded.recipient_channel, 3);
+        assert_eq!(&*decoded.data, b"payload");
+
+        let mut frozen = buf.clone().freeze();
+        let packet = Packet::from_buf(&mut frozen);
+        assert!(!frozen.has_remaining());
+
+        let mut out = Vec::new();
+        packet.put_buf(&mut out);
+        assert_eq!(out, buf);
+
+        buf.put_u8(0);
+        assert!(Packet::decode_buf::<connect::ChannelData>(&mut buf).is_err());
+    }
+}
@@ -1,31 +1,83 @@
-use super::{Mac, PACKET_MIN_SIZE};
+use rand_core::CryptoRngCore;
+
+use super::{Compression, Mac, PACKET_MAX_SIZE, PACKET_MIN_SIZE};
+use crate::arch::Ascii;
+
+#[cfg(doc)]
+use super::MacMode;
 
 #[cfg(doc)]
 use super::Packet;
 
-const MIN_PAD_SIZE: usize = 4;
-const MIN_ALIGN: usize = 8;
+pub(super) const MIN_PAD_SIZE: usize = 4;
+pub(super) const MIN_ALIGN: usize = 8;
+
+/// The largest block size a padding of at most `255` bytes can always align packets to.
+pub(super) const MAX_BLOCK_SIZE: usize = 128;
+
+/// The size of the first read of a packet, holding it's length, which is the cipher's
+/// block size but at least `8` bytes for stream ciphers reporting smaller blocks.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6>.
+pub(super) fn header_size<C: CipherCore>(cipher: &C) -> usize {
+    cipher.block_size().max(MIN_ALIGN)
+}
 
 /// A trait with common methods and associated types involved
 /// in the manipulation of [`OpeningCipher`] and [`SealingCipher`].
 pub trait CipherCore {
-    /// The associated error type returned by the `open` method.
-    type Err: From<binrw::Error> + From<std::io::Error>;
+    /// The associated error type returned by the cipher's methods,
+    /// boxed in the crate's [`Error`](crate::Error) by the packet layer.
+    type Err: std::error::Error + Send + Sync + 'static;
 
     /// The _Message Authentication Code_ associated to the cipher.
     type Mac: Mac;
 
+    /// The name of the encryption algorithm, as negotiated in the `SSH_MSG_KEXINIT` messages,
+    /// such as `aes256-ctr`.
+    fn algorithm(&self) -> Ascii<'static>;
+
     /// Gets a reference to the _Message Authentication Code_ for this [`CipherCore`].
     fn mac(&self) -> &Self::Mac;
 
     /// The size of a [`CipherCore`]'s block.
     fn block_size(&self) -> usize;
 
+    /// The size of the authentication tag trailing each packet.
+    ///
+    /// The default implementation uses the [`Mac`] size, _AEAD_ ciphers
+    /// override it with the size of the tag they produce.
+    fn tag_size(&self) -> usize {
+        self.mac().size()
+    }
+
+    /// The maximum size of a packet in this direction, checked against the packet length field.
+    ///
+    /// The default implementation uses [`PACKET_MAX_SIZE`], which can be raised
+    /// to the size advertised by the peer, as with `SFTP` transfers.
+    fn max_packet_size(&self) -> usize {
+        PACKET_MAX_SIZE
+    }
+
+    /// The state of the compression for this direction, consulted by the
+    /// packet layer to run or skip the compression hooks.
+    ///
+    /// The default implementation always runs the compression hooks.
+    fn compression(&self) -> Compression {
+        Compression::Active
+    }
+
+    /// Hook called by the packet layer after the `SSH_MSG_USERAUTH_SUCCESS` message
+    /// went through this direction, to activate [`Compression::Delayed`] compression.
+    ///
+    /// The compression of the opposite direction is to be activated at the same time by the caller.
+    fn authenticated(&mut self) {}
+
     /// Calculate the necessary padding size for the provided payload `size`.
     fn padding(&self, payload: usize) -> u8 {
         let align = self.block_size().max(MIN_ALIGN);
 
-        let size = if self.mac().etm() {
+        let size = if self.mac().mode().is_over_ciphertext() {
             std::mem::size_of::<u8>() + payload
         } else {
             std::mem::size_of::<u32>() + std::mem::size_of::<u8>() + payload
@@ -47,29 +99,113 @@ pub trait CipherCore {
 }
 
 /// A cipher able to `open` a [`Packet`] and retrieve it's payload.
+///
+/// The opening of a [`Packet`] is split in explicit steps, so the standard modes map as follows:
+/// - _CBC_ and _CTR_ modes, where the length is part of the encrypted stream, only implement
+///   [`OpeningCipher::decrypt_payload`], called over the first block to retrieve the length,
+///   then over the rest of the packet, before [`OpeningCipher::open`] checks the _MAC_.
+/// - _Encrypt-then-MAC_ modes, reported by [`MacMode::EncryptThenMac`], keep the default plaintext
+///   [`OpeningCipher::decrypt_len`], and [`OpeningCipher::open`] checks the _MAC_ over the
+///   encrypted packet before [`OpeningCipher::decrypt_payload`] is called after the length.
+/// - _AEAD_ ciphers, reported by [`MacMode::Aead`], such as `chacha20-poly1305@openssh.com` decrypt the separately-keyed length
+///   in [`OpeningCipher::decrypt_len`], and verify the tag in [`OpeningCipher::open_in_place`].
 pub trait OpeningCipher: CipherCore {
-    /// Decrypt the received `buf` using the [`OpeningCipher`].
-    fn decrypt<B: AsMut<[u8]>>(&mut self, buf: B) -> Result<(), Self::Err>;
+    /// Decrypt the packet length from the received `len` bytes, when the [`Mac`] is applied over encrypted data.
+    ///
+    /// The default implementation considers the length to be transmitted in plaintext.
+    fn decrypt_len(&mut self, len: &[u8; 4], seq: u32) -> Result<u32, Self::Err> {
+        let _ = seq;
 
-    /// Compare the received `buf` against the received _Message Authentication Code_.
-    fn open<B: AsRef<[u8]>>(&mut self, buf: B, mac: Vec<u8>, seq: u32) -> Result<(), Self::Err>;
+        Ok(u32::from_be_bytes(*len))
+    }
+
+    /// Decrypt the received `buf` in place, being either the packet's first block,
+    /// the rest of the packet, or the whole packet after it's length.
+    fn decrypt_payload(&mut self, buf: &mut [u8], seq: u32) -> Result<(), Self::Err>;
+
+    /// Compare the received `buf` against the received _Message Authentication Code_ in `mac`.
+    fn open<B: AsRef<[u8]>>(&mut self, buf: B, mac: &[u8], seq: u32) -> Result<(), Self::Err>;
+
+    /// Compare the concatenation of the received `bufs` against the received _Message Authentication Code_ in `mac`,
+    /// for packets whose length, payload and padding aren't held contiguously.
+    ///
+    /// The default implementation concatenates the `bufs` before calling [`OpeningCipher::open`],
+    /// ciphers computing their _MAC_ incrementally override it to feed each buffer in turn.
+    fn open_vectored(&mut self, bufs: &[&[u8]], mac: &[u8], seq: u32) -> Result<(), Self::Err> {
+        self.open(bufs.concat(), mac, seq)
+    }
+
+    /// Authenticate and decrypt the received `buf` in place, when the [`Mac`] is applied over encrypted data.
+    ///
+    /// The default implementation calls [`OpeningCipher::open`] over the whole `buf`,
+    /// and [`OpeningCipher::decrypt_payload`] after the packet length; _AEAD_ ciphers like
+    /// `aes256-gcm@openssh.com` override it to decrypt the payload with the
+    /// plaintext length as additional authenticated data, and verify the tag in `mac`.
+    fn open_in_place(&mut self, buf: &mut [u8], mac: &[u8], seq: u32) -> Result<(), Self::Err> {
+        self.open(&*buf, mac, seq)?;
+        self.decrypt_payload(&mut buf[4..], seq)
+    }
 
-    /// Decompress the received `buf` using the [`OpeningCipher`].
-    fn decompress(&mut self, buf: Vec<u8>) -> Result<Vec<u8>, Self::Err>;
+    /// Decompress the received payload in `buf` in place using the [`OpeningCipher`],
+    /// allowing implementations to swap it with retained scratch space instead of allocating.
+    fn decompress(&mut self, buf: &mut Vec<u8>) -> Result<(), Self::Err>;
 }
 
 /// A cipher able to `seal` a payload to create a [`Packet`].
 pub trait SealingCipher: CipherCore {
-    /// Decompress the `buf` using the [`SealingCipher`].
-    fn compress<B: AsRef<[u8]>>(&mut self, buf: B) -> Result<Vec<u8>, Self::Err>;
+    /// Compress the `buf` using the [`SealingCipher`], appending the result to `output`.
+    fn compress(&mut self, buf: &[u8], output: &mut Vec<u8>) -> Result<(), Self::Err>;
 
-    /// Pad the `buf` to match [`SealingCipher`]'s block size with random data,
-    /// by increasing it by `padding` bytes and prefixing the `buf` it with it's len.
-    fn pad(&mut self, buf: Vec<u8>, padding: u8) -> Result<Vec<u8>, Self::Err>;
+    /// Fill the `padding` bytes of the packet, reserved in place by the packet layer
+    /// to align the packet to the [`SealingCipher`]'s block size.
+    ///
+    /// The default implementation fills the padding with bytes from the provided `rng`.
+    fn pad(&mut self, padding: &mut [u8], rng: &mut dyn CryptoRngCore) -> Result<(), Self::Err> {
+        rng.fill_bytes(padding);
 
-    /// Encrypt the `buf` using using the [`SealingCipher`].
-    fn encrypt<B: AsMut<[u8]>>(&mut self, buf: B) -> Result<(), Self::Err>;
+        Ok(())
+    }
+
+    /// Encrypt the packet length in `len`, when the [`Mac`] is applied over encrypted data.
+    ///
+    /// The default implementation leaves the length in plaintext.
+    fn encrypt_len(&mut self, len: &mut [u8; 4], seq: u32) -> Result<(), Self::Err> {
+        let _ = (len, seq);
+
+        Ok(())
+    }
 
-    /// Generate a seal from the HMAC algorithm to produce a _Message Authentication Code_.
-    fn seal<B: AsRef<[u8]>>(&mut self, buf: B, seq: u32) -> Result<Vec<u8>, Self::Err>;
+    /// Encrypt the `buf` in place, being either the whole packet,
+    /// or the whole packet after it's length.
+    fn encrypt_payload(&mut self, buf: &mut [u8], seq: u32) -> Result<(), Self::Err>;
+
+    /// Generate a seal from the HMAC algorithm to produce a _Message Authentication Code_,
+    /// written to `mac`, which is reserved by the packet layer with [`CipherCore::tag_size`] bytes.
+    fn seal<B: AsRef<[u8]>>(&mut self, buf: B, mac: &mut [u8], seq: u32) -> Result<(), Self::Err>;
+
+    /// Generate a seal like [`SealingCipher::seal`] over the concatenation of the `bufs`,
+    /// for packets whose length, payload and padding aren't held contiguously.
+    ///
+    /// The default implementation concatenates the `bufs` before calling [`SealingCipher::seal`],
+    /// ciphers computing their _MAC_ incrementally override it to feed each buffer in turn.
+    fn seal_vectored(&mut self, bufs: &[&[u8]], mac: &mut [u8], seq: u32) -> Result<(), Self::Err> {
+        self.seal(bufs.concat(), mac, seq)
+    }
+
+    /// Encrypt and authenticate the `buf` in place, when the [`Mac`] is applied over encrypted data.
+    ///
+    /// The default implementation calls [`SealingCipher::encrypt_len`], [`SealingCipher::encrypt_payload`]
+    /// after the packet length and [`SealingCipher::seal`] over the whole `buf`; _AEAD_ ciphers like
+    /// `aes256-gcm@openssh.com` override it to encrypt the payload with the
+    /// plaintext length as additional authenticated data, and produce the tag in `mac`.
+    fn seal_in_place(&mut self, buf: &mut [u8], mac: &mut [u8], seq: u32) -> Result<(), Self::Err> {
+        self.encrypt_len(
+            (&mut buf[..4])
+                .try_into()
+                .expect("The buffer of size 4 is not of size 4"),
+            seq,
+        )?;
+        self.encrypt_payload(&mut buf[4..], seq)?;
+        self.seal(&*buf, mac, seq)
+    }
 }
@@ -0,0 +1,180 @@
+use asynchronous_codec::{BytesMut, Decoder, Encoder};
+use bytes::Buf;
+use rand_core::CryptoRngCore;
+
+use super::{arch, stream::ReadState, Error, OpeningCipher, Packet, SealingCipher, Seq};
+
+/// An [`asynchronous_codec::Decoder`] of [`Packet`]s, owning the opening `cipher` state
+/// and the sequence number, to be used with [`asynchronous_codec::FramedRead`].
+///
+/// The bytes of a partially decoded packet are left in the source buffer until it is complete,
+/// so a stream ending in the middle of a packet is reported as an [`Error::UnexpectedEof`].
+#[derive(Debug)]
+pub struct PacketDecoder<C> {
+    cipher: C,
+    seq: Seq,
+    state: ReadState,
+    pending: usize,
+}
+
+impl<C: OpeningCipher> PacketDecoder<C> {
+    /// Create a new [`PacketDecoder`], opening packets with the `cipher`.
+    pub fn new(cipher: C) -> Self {
+        Self {
+            cipher,
+            seq: Seq::new(),
+            state: ReadState::default(),
+            pending: 0,
+        }
+    }
+
+    /// Access the opening cipher state.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Mutably access the opening cipher state, e.g. to install new keys.
+    pub fn cipher_mut(&mut self) -> &mut C {
+        &mut self.cipher
+    }
+
+    /// The sequence number of the next [`Packet`] to be decoded.
+    pub fn seq(&self) -> Seq {
+        self.seq
+    }
+
+    /// Reset the sequence number, after the `SSH_MSG_NEWKEYS` message in _strict key exchange_ mode.
+    pub fn reset_seq(&mut self) {
+        self.seq.reset();
+    }
+}
+
+impl<C: OpeningCipher> Decoder for PacketDecoder<C> {
+    type Item = Packet<'static>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        while src.len() > self.pending {
+            let unfilled = self.state.unfilled(&self.cipher);
+            let read = unfilled.len().min(src.len() - self.pending);
+            unfilled[..read].copy_from_slice(&src[self.pending..self.pending + read]);
+            self.pending += read;
+
+            let opened = self
+                .state
+                .advance(read, &mut self.cipher, &mut self.seq, &mut ());
+            if !matches!(opened, Ok(false)) {
+                src.advance(std::mem::take(&mut self.pending));
+            }
+
+            if opened? {
+                return Ok(Some(Packet {
+                    payload: arch::Bytes::owned(self.state.packet().payload.to_vec()),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// An [`asynchronous_codec::Encoder`] of [`Packet`]s, owning the sealing `cipher` state,
+/// the sequence number and the random number generator used for padding,
+/// to be used with [`asynchronous_codec::FramedWrite`].
+#[derive(Debug)]
+pub struct PacketEncoder<C, G> {
+    cipher: C,
+    seq: Seq,
+    rng: G,
+    buf: Vec<u8>,
+}
+
+impl<C: SealingCipher, G: CryptoRngCore> PacketEncoder<C, G> {
+    /// Create a new [`PacketEncoder`], sealing packets with the `cipher`
+    /// and padding them with random bytes from `rng`.
+    pub fn new(cipher: C, rng: G) -> Self {
+        Self {
+            cipher,
+            seq: Seq::new(),
+            rng,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Access the sealing cipher state.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Mutably access the sealing cipher state, e.g. to install new keys.
+    pub fn cipher_mut(&mut self) -> &mut C {
+        &mut self.cipher
+    }
+
+    /// The sequence number of the next [`Packet`] to be encoded.
+    pub fn seq(&self) -> Seq {
+        self.seq
+    }
+
+    /// Reset the sequence number, after the `SSH_MSG_NEWKEYS` message in _strict key exchange_ mode.
+    pub fn reset_seq(&mut self) {
+        self.seq.reset();
+    }
+}
+
+impl<C: SealingCipher, G: CryptoRngCore> Encoder for PacketEncoder<C, G> {
+    type Item<'a> = Packet<'a>;
+    type Error = Error;
+
+    fn encode(&mut self, item: Self::Item<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.seal_into(
+            &mut self.cipher,
+            self.seq.get(),
+            &mut self.rng,
+            &mut self.buf,
+        )?;
+
+        dst.extend_from_slice(&self.buf);
+        self.seq.increment();
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "none", feature = "futures"))]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use asynchronous_codec::{FramedRead, FramedWrite};
+    use futures::{SinkExt, TryStreamExt};
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::{connect, NoneCipher, TryIntoPacket};
+
+    #[async_std::test]
+    async fn it_frames_packets() {
+        let message = connect::ChannelClose {
+            recipient_channel: 7,
+        };
+
+        let mut wire = Vec::new();
+        let mut sink = FramedWrite::new(&mut wire, PacketEncoder::new(NoneCipher, OsRng));
+        for _ in 0..3 {
+            sink.send((&message).try_into_packet().unwrap())
+                .await
+                .unwrap();
+        }
+        assert_eq!(sink.encoder().seq().get(), 3);
+
+        // Truncate the last packet
+        wire.pop();
+
+        let mut stream = FramedRead::new(&wire[..], PacketDecoder::new(NoneCipher));
+        for _ in 0..2 {
+            let packet = stream.try_next().await.unwrap().unwrap();
+            let close: connect::ChannelClose = packet.to().unwrap();
+            assert_eq!(close.recipient_channel, 7);
+        }
+        assert!(matches!(stream.try_next().await, Err(Error::UnexpectedEof)));
+    }
+}
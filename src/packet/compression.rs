@@ -0,0 +1,87 @@
+use crate::arch;
+
+/// The `SSH_MSG_USERAUTH_SUCCESS` message number, activating delayed compression.
+const USERAUTH_SUCCESS: u8 = 52;
+
+/// The state of the compression for a direction of the transport,
+/// consulted by the packet layer to run or skip the compression hooks.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.2>.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression is applied, the compression hooks are skipped.
+    None,
+
+    /// Compression is in effect, the compression hooks are run.
+    #[default]
+    Active,
+
+    /// Delayed compression (`zlib@openssh.com`), the compression hooks
+    /// are skipped until the `SSH_MSG_USERAUTH_SUCCESS` message went through.
+    Delayed,
+}
+
+impl Compression {
+    /// The SSH `none` compression algorithm.
+    pub const NONE: arch::Ascii<'static> = arch::ascii!("none");
+
+    /// The SSH `zlib` compression algorithm.
+    pub const ZLIB: arch::Ascii<'static> = arch::ascii!("zlib");
+
+    /// The OpenSSH `zlib@openssh.com` delayed compression algorithm.
+    pub const ZLIB_OPENSSH: arch::Ascii<'static> = arch::ascii!("zlib@openssh.com");
+
+    /// Get the initial [`Compression`] state for the negotiated `algorithm`,
+    /// considering any unknown algorithm to be compressing from the start.
+    pub fn from_algorithm(algorithm: &str) -> Self {
+        if algorithm == &*Self::NONE {
+            Self::None
+        } else if algorithm == &*Self::ZLIB_OPENSSH {
+            Self::Delayed
+        } else {
+            Self::Active
+        }
+    }
+
+    /// Whether the compression hooks are to be run.
+    pub fn is_active(&self) -> bool {
+        matches!(self, Self::Active)
+    }
+
+    /// Activate the compression if it was [`Compression::Delayed`],
+    /// after the user has been successfully authenticated.
+    pub fn authenticated(&mut self) {
+        if let Self::Delayed = self {
+            *self = Self::Active;
+        }
+    }
+
+    /// Whether the `payload` is the `SSH_MSG_USERAUTH_SUCCESS` message activating delayed compression.
+    pub fn is_activation(payload: &[u8]) -> bool {
+        payload.first() == Some(&USERAUTH_SUCCESS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("none", Compression::None, Compression::None)]
+    #[case("zlib", Compression::Active, Compression::Active)]
+    #[case("zlib@openssh.com", Compression::Delayed, Compression::Active)]
+    fn it_activates_after_authentication(
+        #[case] algorithm: &str,
+        #[case] before: Compression,
+        #[case] after: Compression,
+    ) {
+        let mut compression = Compression::from_algorithm(algorithm);
+        assert_eq!(compression, before);
+
+        compression.authenticated();
+        assert_eq!(compression, after);
+    }
+}
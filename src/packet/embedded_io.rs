@@ -0,0 +1,178 @@
+use rand_core::CryptoRngCore;
+
+use super::{
+    Decode, Direction, Error, OpeningCipher, Packet, PacketObserver, PacketReader, PacketWriter,
+    SealingCipher, Seq, TryIntoPacket,
+};
+
+/// An adapter exposing an `embedded-io` reader or writer as a [`std::io`] one,
+/// to share the blocking implementations.
+pub(crate) struct Embedded<'a, T>(pub(crate) &'a mut T);
+
+/// Convert an `embedded-io` error to a [`std::io::Error`], preserving it's kind.
+fn error<E: embedded_io::Error>(err: E) -> std::io::Error {
+    std::io::Error::new(err.kind().into(), format!("{err:?}"))
+}
+
+impl<R: embedded_io::Read> std::io::Read for Embedded<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf).map_err(error)
+    }
+}
+
+impl<W: embedded_io::Write> std::io::Write for Embedded<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf).map_err(error)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush().map_err(error)
+    }
+}
+
+impl Packet<'_> {
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Read a [`Packet`], incrementing the `seq` sequence number, from the provided blocking `embedded-io` `reader`.
+    pub fn from_embedded_reader<R, C>(
+        reader: &mut R,
+        cipher: &mut C,
+        seq: &mut Seq,
+    ) -> Result<Self, Error>
+    where
+        R: embedded_io::Read,
+        C: OpeningCipher,
+    {
+        Self::from_blocking_reader(&mut Embedded(reader), cipher, seq)
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Read a [`Packet`], incrementing the `seq` sequence number, from the provided blocking `embedded-io` `reader`,
+    /// reusing the provided `buf` to hold the payload instead of allocating a new buffer.
+    pub fn from_embedded_reader_into<'a, R, C>(
+        reader: &mut R,
+        cipher: &mut C,
+        seq: &mut Seq,
+        buf: &'a mut Vec<u8>,
+    ) -> Result<Packet<'a>, Error>
+    where
+        R: embedded_io::Read,
+        C: OpeningCipher,
+    {
+        Packet::from_blocking_reader_into(&mut Embedded(reader), cipher, seq, buf)
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Write the [`Packet`], padded with random bytes from `rng` and incrementing
+    /// the `seq` sequence number, to the provided blocking `embedded-io` `writer`.
+    pub fn to_embedded_writer<W, C>(
+        &self,
+        writer: &mut W,
+        cipher: &mut C,
+        seq: &mut Seq,
+        rng: &mut dyn CryptoRngCore,
+    ) -> Result<(), Error>
+    where
+        W: embedded_io::Write,
+        C: SealingCipher,
+    {
+        self.to_blocking_writer(&mut Embedded(writer), cipher, seq, rng)
+    }
+}
+
+impl<R, C, O> PacketReader<R, C, O>
+where
+    R: embedded_io::Read,
+    C: OpeningCipher,
+    O: PacketObserver,
+{
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Receive a [`Packet`] from the underlying blocking `embedded-io` reader, borrowing the internal buffer.
+    pub fn embedded_recv_packet(&mut self) -> Result<Packet<'_>, Error> {
+        loop {
+            let seq = self.seq.get();
+            let read = match self.reader.read(self.state.unfilled(&self.cipher)) {
+                Err(err)
+                    if embedded_io::Error::kind(&err) == embedded_io::ErrorKind::Interrupted =>
+                {
+                    continue
+                }
+                read => read.map_err(error)?,
+            };
+
+            if self
+                .state
+                .advance(read, &mut self.cipher, &mut self.seq, &mut self.observer)?
+            {
+                let packet = self.state.packet();
+                self.observer.observe(Direction::Inbound, seq, &packet);
+
+                return Ok(packet);
+            }
+        }
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Receive a [`Packet`] from the underlying blocking `embedded-io` reader, and deserialize it into `T`.
+    pub fn embedded_recv<T: Decode>(&mut self) -> Result<T, Error> {
+        let options = self.options;
+
+        Ok(self.embedded_recv_packet()?.to_with(&options)?)
+    }
+}
+
+impl<W, C, G, O> PacketWriter<W, C, G, O>
+where
+    W: embedded_io::Write,
+    C: SealingCipher,
+    G: CryptoRngCore,
+    O: PacketObserver,
+{
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-io")))]
+    /// Send the `message` as a [`Packet`] to the underlying blocking `embedded-io` writer.
+    pub fn embedded_send<'b>(&mut self, message: impl TryIntoPacket<'b>) -> Result<(), Error> {
+        self.seal_message(message)?;
+
+        self.writer.write_all(&self.buf).map_err(error)?;
+        self.seq.increment();
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "none"))]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::{connect, Id, NoneCipher};
+
+    #[test]
+    fn it_exchanges_over_embedded_io() {
+        let mut wire = Vec::new();
+
+        let id = Id::v2("embedded", None::<&str>);
+        id.to_embedded_writer_with_banner(&mut wire, &["banner line"])
+            .unwrap();
+
+        let mut writer = PacketWriter::new(wire, NoneCipher, OsRng);
+        writer
+            .embedded_send(&connect::ChannelClose {
+                recipient_channel: 7,
+            })
+            .unwrap();
+        let (wire, _) = writer.into_inner();
+
+        let mut reader = &wire[..];
+        assert_eq!(Id::from_embedded_reader(&mut reader).unwrap(), id);
+
+        let mut reader = PacketReader::new(reader, NoneCipher);
+        let close = reader.embedded_recv::<connect::ChannelClose>().unwrap();
+        assert_eq!(close.recipient_channel, 7);
+
+        assert!(matches!(
+            reader.embedded_recv_packet(),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+}
@@ -1,8 +1,42 @@
+use crate::arch::Ascii;
+
 /// The algorithm parameters for the _Message Authentication Code_.
 pub trait Mac {
+    /// The name of the MAC algorithm, as negotiated in the `SSH_MSG_KEXINIT` messages,
+    /// such as `hmac-sha2-256-etm@openssh.com`.
+    fn algorithm(&self) -> Ascii<'static>;
+
     /// The size of the MAC at the end of the SSH packet.
     fn size(&self) -> usize;
 
-    /// Whether the MAC is applied over encrypted data.
-    fn etm(&self) -> bool;
+    /// The data authenticated by the MAC, which determines the layout of the packet
+    /// and the order in which the packet layer calls the cipher's methods.
+    fn mode(&self) -> MacMode;
+}
+
+/// The data authenticated by a [`Mac`], and how the packet length is protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MacMode {
+    /// _Encrypt-and-MAC_: the MAC is computed over the plaintext packet, including it's length,
+    /// which is encrypted along with the rest of the packet.
+    ///
+    /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.4>.
+    EncryptAndMac,
+
+    /// _Encrypt-then-MAC_: the MAC is computed over the encrypted packet,
+    /// with the length sent in plaintext, as with the `*-etm@openssh.com` MACs.
+    EncryptThenMac,
+
+    /// _Authenticated encryption_: the cipher produces the tag over the encrypted packet,
+    /// with the length as additional authenticated data, either sent in plaintext as with
+    /// `aes256-gcm@openssh.com`, or encrypted separately as with `chacha20-poly1305@openssh.com`.
+    Aead,
+}
+
+impl MacMode {
+    /// Whether the MAC is computed over encrypted data, so that the packet is authenticated
+    /// before being decrypted, and the length is excluded from the alignment of the packet.
+    pub fn is_over_ciphertext(self) -> bool {
+        !matches!(self, Self::EncryptAndMac)
+    }
 }
@@ -0,0 +1,80 @@
+use super::{Direction, Error, Packet, PacketObserver};
+
+/// The counters of a [`Metrics`] observer, for a single [`Direction`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counters {
+    /// The number of packets transferred.
+    pub packets: u64,
+
+    /// The number of bytes transferred on the wire.
+    pub bytes: u64,
+
+    /// The number of times the packet layer's buffer grew.
+    pub allocations: u64,
+
+    /// The number of bytes the packet layer's buffer grew by.
+    pub allocated: u64,
+
+    /// The number of packets which failed the integrity checks, see [`Error::Integrity`].
+    pub integrity_failures: u64,
+
+    /// The number of packets which failed to be processed, including integrity failures.
+    pub failures: u64,
+}
+
+/// A [`PacketObserver`] counting packets, bytes, allocations and failures
+/// of a [`PacketReader`](super::PacketReader) or a [`PacketWriter`](super::PacketWriter), per [`Direction`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Metrics {
+    inbound: Counters,
+    outbound: Counters,
+}
+
+impl Metrics {
+    /// The counters for the provided `direction`.
+    pub fn get(&self, direction: Direction) -> &Counters {
+        match direction {
+            Direction::Inbound => &self.inbound,
+            Direction::Outbound => &self.outbound,
+        }
+    }
+
+    fn get_mut(&mut self, direction: Direction) -> &mut Counters {
+        match direction {
+            Direction::Inbound => &mut self.inbound,
+            Direction::Outbound => &mut self.outbound,
+        }
+    }
+
+    /// Reset all the counters to zero.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl PacketObserver for Metrics {
+    fn observe(&mut self, _direction: Direction, _seq: u32, _packet: &Packet<'_>) {}
+
+    fn transferred(&mut self, direction: Direction, size: usize) {
+        let counters = self.get_mut(direction);
+
+        counters.packets += 1;
+        counters.bytes += size as u64;
+    }
+
+    fn allocated(&mut self, direction: Direction, size: usize) {
+        let counters = self.get_mut(direction);
+
+        counters.allocations += 1;
+        counters.allocated += size as u64;
+    }
+
+    fn failed(&mut self, direction: Direction, error: &Error) {
+        let counters = self.get_mut(direction);
+
+        if let Error::Integrity = error {
+            counters.integrity_failures += 1;
+        }
+        counters.failures += 1;
+    }
+}
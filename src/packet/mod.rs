@@ -1,16 +1,73 @@
-use binrw::{
-    meta::{ReadEndian, WriteEndian},
-    BinRead, BinWrite,
-};
+use binrw::{meta::WriteEndian, BinWrite};
+
+use rand_core::CryptoRngCore;
+
+use crate::{arch, DecodeError, Error, IntoOwned, Message, MessageId, PaddingError};
 
 mod cipher;
 pub use cipher::{CipherCore, OpeningCipher, SealingCipher};
 
 mod mac;
-pub use mac::Mac;
+pub use mac::{Mac, MacMode};
+
+mod compression;
+pub use compression::Compression;
+
+mod seq;
+pub use seq::Seq;
+
+mod options;
+pub use options::{Decode, DecodeOptions, UnknownMessages};
+
+#[cfg(feature = "none")]
+mod none;
+#[cfg(feature = "none")]
+pub use none::{NoneCipher, NoneMac};
+
+#[cfg(feature = "zlib")]
+mod zlib;
+#[cfg(feature = "zlib")]
+pub use zlib::{ZlibCompressor, ZlibDecompressor};
+
+mod observer;
+pub use observer::{Direction, PacketObserver};
+
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "metrics")]
+pub use metrics::{Counters, Metrics};
+
+mod wire_size;
+pub use wire_size::WireSize;
+
+mod stream;
+pub use stream::{PacketReader, PacketWriter};
+
+mod blocking;
+
+#[cfg(feature = "futures")]
+mod futures_io;
+
+#[cfg(feature = "tokio")]
+mod tokio_io;
 
-/// Maximum size for a SSH packet, coincidentally this is
+#[cfg(feature = "bytes")]
+mod buf;
+
+#[cfg(feature = "asynchronous-codec")]
+mod codec;
+#[cfg(feature = "asynchronous-codec")]
+pub use codec::{PacketDecoder, PacketEncoder};
+
+#[cfg(feature = "embedded-io")]
+mod embedded_io;
+#[cfg(feature = "embedded-io")]
+pub(crate) use embedded_io::Embedded;
+
+/// Default maximum size for a SSH packet, coincidentally this is
 /// the maximum size for a TCP packet.
+///
+/// This can be raised per-direction with [`CipherCore::max_packet_size`].
 pub const PACKET_MAX_SIZE: usize = u16::MAX as usize;
 
 /// Minimum size for a SSH packet, coincidentally this is
@@ -19,146 +76,1474 @@ pub const PACKET_MIN_SIZE: usize = 16;
 
 /// A SSH 2.0 binary packet representation.
 ///
+/// The payload can either be owned, or borrowed from a buffer to avoid copies,
+/// although the messages decoded from it always copy their `string`s out of it.
+///
+/// The type itself guarantees no invariant on it's payload: [`Packet::new`] checks that it
+/// holds a message number and fits in a packet of the default maximum size,
+/// while the [`From`] conversions are unchecked.
+///
+/// Messages are framed into a [`Packet`] with [`TryIntoPacket`], and are obtained back
+/// with [`Packet::to`] for a known message type, or [`Packet::decode`] into any [`Message`].
+///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6>.
-#[derive(Debug, Clone)]
-
-pub struct Packet {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "zeroize", derive(zeroize::Zeroize))]
+pub struct Packet<'b> {
     /// SSH packet's payload as binary.
-    pub payload: Vec<u8>,
+    pub payload: arch::Bytes<'b>,
 }
 
-impl Packet {
-    /// Try to deserialize the [`Packet`] into `T`.
-    pub fn to<T: for<'a> BinRead<Args<'a> = ()> + ReadEndian>(&self) -> Result<T, binrw::Error> {
-        T::read(&mut std::io::Cursor::new(&self.payload))
+impl IntoOwned for Packet<'_> {
+    type Owned = Packet<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        Packet {
+            payload: self.payload.into_owned(),
+        }
     }
+}
 
-    #[cfg(feature = "futures")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
-    /// Read a [`Packet`] from the provided asynchronous `reader`.
-    pub async fn from_reader<R, C>(reader: &mut R, cipher: &mut C, seq: u32) -> Result<Self, C::Err>
-    where
-        R: futures::io::AsyncRead + Unpin,
-        C: OpeningCipher,
-    {
-        use futures::io::AsyncReadExt;
-
-        let mut buf = vec![0; cipher.block_size()];
-        reader.read_exact(&mut buf[..]).await?;
-
-        if !cipher.mac().etm() {
-            cipher.decrypt(&mut buf[..])?;
-        }
-
-        let len = u32::from_be_bytes(
-            buf[..4]
-                .try_into()
-                .expect("The buffer of size 4 is not of size 4"),
-        );
+impl AsRef<[u8]> for Packet<'_> {
+    fn as_ref(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+/// The payload is left unchecked, see [`Packet::new`].
+impl From<Vec<u8>> for Packet<'_> {
+    fn from(value: Vec<u8>) -> Self {
+        Self {
+            payload: arch::Bytes::owned(value),
+        }
+    }
+}
+
+impl<'b> From<arch::Bytes<'b>> for Packet<'b> {
+    fn from(payload: arch::Bytes<'b>) -> Self {
+        Self { payload }
+    }
+}
+
+impl Packet<'_> {
+    /// The maximum size of an uncompressed payload fitting in a packet of [`PACKET_MAX_SIZE`],
+    /// along with it's padding length and minimal padding, for any cipher's alignment.
+    pub const PAYLOAD_MAX_SIZE: usize = PACKET_MAX_SIZE / cipher::MAX_BLOCK_SIZE
+        * cipher::MAX_BLOCK_SIZE
+        - std::mem::size_of::<u8>()
+        - cipher::MIN_PAD_SIZE;
+
+    /// Create a [`Packet`] from a raw `payload`, ensuring it holds at least a message number,
+    /// and that it is at most [`Packet::PAYLOAD_MAX_SIZE`] bytes long.
+    pub fn new(payload: Vec<u8>) -> Result<Packet<'static>, Error> {
+        if payload.is_empty() {
+            return Err(Error::PacketTooSmall { size: 0 });
+        }
+        if payload.len() > Self::PAYLOAD_MAX_SIZE {
+            return Err(Error::PacketTooLarge {
+                size: payload.len(),
+                max: Self::PAYLOAD_MAX_SIZE,
+            });
+        }
+
+        Ok(Packet::from(payload))
+    }
+}
 
-        if len as usize > PACKET_MAX_SIZE {
-            return Err(binrw::Error::Custom {
-                pos: 0x0,
-                err: Box::new(format!("Packet size too large, {len} > {PACKET_MAX_SIZE}")),
-            })?;
+impl<'b> Packet<'b> {
+    /// Obtain a [`Packet`] from a reference by borrowing the internal buffer.
+    pub fn as_borrow<'a: 'b>(&'a self) -> Packet<'a> {
+        Packet {
+            payload: self.payload.as_borrow(),
         }
+    }
+
+    /// Reclaim the payload's allocation as an empty buffer,
+    /// to be reused with the `from_*reader_into` methods or [`Packet::encode_into`].
+    pub fn recycle(self) -> Vec<u8> {
+        self.payload.recycle()
+    }
+
+    /// Access the [`Packet`]'s payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The size of the [`Packet`]'s payload, in bytes.
+    pub fn len(&self) -> usize {
+        self.payload.len()
+    }
 
-        // Read the rest of the data from the reader
-        buf.resize(std::mem::size_of_val(&len) + len as usize, 0);
-        reader.read_exact(&mut buf[cipher.block_size()..]).await?;
+    /// Whether the [`Packet`]'s payload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.payload.is_empty()
+    }
 
-        let mut mac = vec![0; cipher.mac().size()];
-        reader.read_exact(&mut mac[..]).await?;
+    /// Extract the [`Packet`]'s payload into a [`Vec`].
+    pub fn into_inner(self) -> Vec<u8> {
+        self.payload.into_vec()
+    }
+
+    /// Try to deserialize the [`Packet`] into `T`,
+    /// failing if the payload contains trailing bytes after the message.
+    ///
+    /// The `string`s of the message are copied out of the payload, so the decoded `T`
+    /// never borrows from the [`Packet`], and can be of any lifetime, in example `'static`.
+    pub fn to<T: Decode>(&self) -> Result<T, DecodeError> {
+        self.to_with(&DecodeOptions::default())
+    }
 
-        if cipher.mac().etm() {
-            cipher.open(&buf, mac, seq)?;
-            cipher.decrypt(&mut buf[4..])?;
+    /// Try to deserialize the [`Packet`] into `T`, ignoring any trailing bytes after the message.
+    pub fn to_lenient<T: Decode>(&self) -> Result<T, DecodeError> {
+        self.to_with(&DecodeOptions::permissive())
+    }
+
+    /// Try to deserialize the [`Packet`] into `T`, with the provided decoding `options`
+    /// passed down as the `binrw` arguments of `T`.
+    pub fn to_with<T: Decode>(&self, options: &DecodeOptions) -> Result<T, DecodeError> {
+        let mut reader = std::io::Cursor::new(&*self.payload);
+        let message = T::read_with(&mut reader, options)?;
+
+        let pos = reader.position();
+        if pos != self.payload.len() as u64 && options.strict {
+            return Err(DecodeError::from(binrw::Error::AssertFail {
+                pos,
+                message: format!(
+                    "The packet's payload has {} trailing bytes after the message",
+                    self.payload.len() as u64 - pos
+                ),
+            }));
+        }
+
+        Ok(message)
+    }
+
+    /// Peek at the [`MessageId`] of the [`Packet`], without decoding it's payload.
+    pub fn peek_message_id(&self) -> Option<MessageId> {
+        self.payload.first().copied().map(MessageId)
+    }
+
+    /// Serialize the `message` into the provided `buf`, reusing it's allocation,
+    /// and borrow it as a [`Packet`].
+    ///
+    /// Unlike [`TryIntoPacket`], the buffer isn't pre-sized with the [`WireSize::wire_size_hint`],
+    /// since a reused buffer seldom needs to grow, and the dry run would only serialize the `message` twice.
+    pub fn encode_into<T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian>(
+        message: &T,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<Self, binrw::Error> {
+        buf.clear();
+        message.write(&mut std::io::Cursor::new(&mut *buf))?;
+
+        Ok(Self {
+            payload: arch::Bytes::borrowed(buf),
+        })
+    }
+
+    /// Serialize the `message` into the provided fixed-size `buf`, such as a stack array,
+    /// and borrow the written prefix as a [`Packet`].
+    ///
+    /// This avoids any heap allocation for small messages, but fails if `buf` is too short.
+    pub fn encode_to_slice<T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian>(
+        message: &T,
+        buf: &'b mut [u8],
+    ) -> Result<Self, binrw::Error> {
+        let mut cursor = std::io::Cursor::new(&mut *buf);
+        message.write(&mut cursor)?;
+        let len = cursor.position() as usize;
+
+        Ok(Self {
+            payload: arch::Bytes::borrowed(&buf[..len]),
+        })
+    }
+
+    /// Decode the [`Packet`] into any of the [`Message`]s, by dispatching on it's message number.
+    pub fn decode(&self) -> Result<Message<'_>, DecodeError> {
+        Message::from_packet(self)
+    }
+
+    /// Decode the [`Packet`] into any of the [`Message`]s, with the provided decoding `options`.
+    pub fn decode_with(&self, options: &DecodeOptions) -> Result<Message<'_>, DecodeError> {
+        match Message::from_packet_with(self, options)? {
+            Message::Unknown(_) if options.unknown_messages == UnknownMessages::Reject => {
+                Err(crate::message::unexpected(self))
+            }
+            message => Ok(message),
+        }
+    }
+
+    /// Decrypt the first block of a [`Packet`] in `buf`, and retrieve the size of the remaining data to read.
+    ///
+    /// The length is checked to cover at least the first block and the minimum size of a packet,
+    /// so that the rest of the packet can be read after the first block.
+    fn open_header<C: OpeningCipher>(
+        buf: &mut [u8],
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<u32, Error> {
+        let header = cipher::header_size(cipher);
+        if header > cipher::MAX_BLOCK_SIZE {
+            return Err(Error::Cipher(
+                format!(
+                    "Unsupported block size of {} bytes, above {}",
+                    cipher.block_size(),
+                    cipher::MAX_BLOCK_SIZE
+                )
+                .into(),
+            ));
+        }
+
+        if !cipher.mac().mode().is_over_ciphertext() {
+            cipher
+                .decrypt_payload(&mut buf[..], seq)
+                .map_err(|err| Self::integrity(seq, err))?;
+        }
+
+        let len: [u8; 4] = buf[..4]
+            .try_into()
+            .expect("The buffer of size 4 is not of size 4");
+        let len = if cipher.mac().mode().is_over_ciphertext() {
+            // Keep the encrypted length in the buffer, since it is authenticated by the MAC
+            cipher
+                .decrypt_len(&len, seq)
+                .map_err(|err| Self::integrity(seq, err))?
         } else {
-            cipher.decrypt(&mut buf[cipher.block_size()..])?;
-            cipher.open(&buf, mac, seq)?;
+            u32::from_be_bytes(len)
+        };
+
+        let max = cipher.max_packet_size();
+        let min = (PACKET_MIN_SIZE - std::mem::size_of_val(&len))
+            .max(header - std::mem::size_of_val(&len));
+
+        let err = if len as usize > max {
+            Error::PacketTooLarge {
+                size: len as usize,
+                max,
+            }
+        } else if (len as usize) < min {
+            Error::PacketTooSmall { size: len as usize }
+        } else {
+            return Ok(len);
+        };
+
+        if cipher.mac().mode().is_over_ciphertext() {
+            Err(err)
+        } else {
+            // The length has been decrypted without being authenticated first,
+            // so it's bounds must not be distinguishable from any other integrity failure
+            Err(Self::integrity(seq, err))
         }
+    }
 
-        let (padlen, mut decrypted) =
-            buf[4..].split_first().ok_or_else(|| binrw::Error::Custom {
-                pos: 0x4,
-                err: Box::new(format!("Packet size too small ({len})")),
-            })?;
+    /// Ensure the `padding` of a packet of `len` bytes (as in the length field) respects the protocol's rules.
+    fn check_padding<C: CipherCore>(
+        cipher: &C,
+        len: usize,
+        padding: u8,
+    ) -> Result<(), PaddingError> {
+        let align = cipher.block_size().max(cipher::MIN_ALIGN);
+        let size = if cipher.mac().mode().is_over_ciphertext() {
+            len
+        } else {
+            4 + len
+        };
 
-        if *padlen as usize > len as usize - 1 {
-            return Err(binrw::Error::Custom {
-                pos: 0x4,
-                err: Box::new(format!("Padding size too large, {padlen} > {} - 1", len)),
-            })?;
+        if (padding as usize) < cipher::MIN_PAD_SIZE || padding as usize >= len || size % align != 0
+        {
+            Err(PaddingError {
+                padding,
+                size,
+                align,
+            })
+        } else {
+            Ok(())
         }
+    }
+
+    /// Collapse the failure of an integrity check of the inbound packet `seq` into [`Error::Integrity`],
+    /// reporting the actual cause through `tracing` only.
+    fn integrity(seq: u32, err: impl std::fmt::Display) -> Error {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(seq, %err, "Packet failed the integrity checks");
+        #[cfg(not(feature = "tracing"))]
+        let _ = (seq, err);
 
-        let mut payload = vec![0; len as usize - *padlen as usize - std::mem::size_of_val(padlen)];
-        std::io::Read::read_exact(&mut decrypted, &mut payload[..])?;
+        Error::Integrity
+    }
+
+    /// Authenticate and decrypt the whole [`Packet`] in `buf`, followed by it's MAC,
+    /// leaving only it's payload in the buffer.
+    fn open_payload<C: OpeningCipher>(
+        buf: &mut Vec<u8>,
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let size = buf.len();
+
+        let len = buf.len() - 4 - cipher.tag_size();
+        let (packet, mac) = buf.split_at_mut(4 + len);
+
+        if cipher.mac().mode().is_over_ciphertext() {
+            cipher
+                .open_in_place(packet, mac, seq)
+                .map_err(|err| Self::integrity(seq, err))?;
+        } else {
+            cipher
+                .decrypt_payload(&mut packet[cipher::header_size(cipher)..], seq)
+                .map_err(|err| Self::integrity(seq, err))?;
+            cipher
+                .open(&*packet, mac, seq)
+                .map_err(|err| Self::integrity(seq, err))?;
+        }
+
+        buf.truncate(4 + len);
+
+        let padlen = *buf.get(4).ok_or(Error::PacketTooSmall { size: len })?;
+
+        Self::check_padding(cipher, len, padlen).map_err(|err| Self::integrity(seq, err))?;
+
+        // Strip the padding, the length and padding length from the buffer
+        buf.truncate(buf.len() - padlen as usize);
+        buf.drain(..4 + std::mem::size_of_val(&padlen));
+
+        if cipher.compression().is_active() {
+            cipher
+                .decompress(buf)
+                .map_err(|err| Error::Compression(err.into()))?;
+        }
+
+        if Compression::is_activation(buf) {
+            cipher.authenticated();
+        }
 
-        let payload = cipher.decompress(payload)?;
+        #[cfg(feature = "tracing")]
+        Self::trace(Direction::Inbound, &cipher.algorithm(), seq, size, buf);
 
-        Ok(Self { payload })
+        Ok(())
     }
 
-    #[cfg(feature = "futures")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
-    /// Write the [`Packet`] to the provided asynchronous `writer`.
-    pub async fn to_writer<W, C>(
+    /// Compress, pad, encrypt and authenticate the [`Packet`] to produce it's binary representation, followed by it's MAC.
+    fn seal<C: SealingCipher>(
         &self,
-        writer: &mut W,
         cipher: &mut C,
         seq: u32,
-    ) -> Result<(), C::Err>
-    where
-        W: futures::io::AsyncWrite + Unpin,
-        C: SealingCipher,
-    {
-        use futures::AsyncWriteExt;
+        rng: &mut dyn CryptoRngCore,
+    ) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        self.seal_into(cipher, seq, rng, &mut buf)?;
 
-        let compressed = cipher.compress(&self.payload)?;
+        Ok(buf)
+    }
 
-        let padding = cipher.padding(compressed.len());
-        let buf = cipher.pad(compressed, padding)?;
-        let mut buf = [(buf.len() as u32).to_be_bytes().to_vec(), buf].concat();
+    /// Seal the [`Packet`] like [`Packet::seal`], reusing the allocation of the provided `buf`,
+    /// reporting any failure as an [`Error::Seal`] local to this side.
+    fn seal_into<C: SealingCipher>(
+        &self,
+        cipher: &mut C,
+        seq: u32,
+        rng: &mut dyn CryptoRngCore,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        self.seal_unchecked(cipher, seq, rng, buf)
+            .map_err(|err| Error::Seal(Box::new(err)))
+    }
 
-        let (buf, mac) = if cipher.mac().etm() {
-            cipher.encrypt(&mut buf[4..])?;
-            let mac = cipher.seal(&buf, seq)?;
+    fn seal_unchecked<C: SealingCipher>(
+        &self,
+        cipher: &mut C,
+        seq: u32,
+        rng: &mut dyn CryptoRngCore,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        // Lay the packet out in a single buffer, with room for the MAC, to be sealed in place,
+        // compressing the payload directly after the length and padding length fields
+        buf.clear();
+        buf.reserve(
+            5 + self.payload.len() + cipher.block_size().max(cipher::MIN_ALIGN) + cipher.tag_size(),
+        );
+        buf.extend_from_slice(&[0; 5]);
 
-            (buf, mac)
+        if cipher.compression().is_active() {
+            cipher
+                .compress(&self.payload, buf)
+                .map_err(|err| Error::Compression(err.into()))?;
         } else {
-            let mac = cipher.seal(&buf, seq)?;
-            cipher.encrypt(&mut buf[..])?;
+            buf.extend_from_slice(&self.payload);
+        }
 
-            (buf, mac)
-        };
+        let padding = cipher.padding(buf.len() - 5);
+        let len = buf.len() - 4 + padding as usize;
+
+        Self::check_padding(cipher, len, padding)?;
 
-        writer.write_all(&buf).await?;
-        writer.write_all(&mac).await?;
+        let max = cipher.max_packet_size();
+        if len > max {
+            return Err(Error::PacketTooLarge { size: len, max });
+        }
+
+        buf[..4].copy_from_slice(&(len as u32).to_be_bytes());
+        buf[4] = padding;
+        buf.resize(4 + len + cipher.tag_size(), 0);
+        let (packet, mac) = buf.split_at_mut(4 + len);
+
+        cipher
+            .pad(&mut packet[4 + len - padding as usize..], rng)
+            .map_err(|err| Error::Cipher(err.into()))?;
+
+        if cipher.mac().mode().is_over_ciphertext() {
+            cipher
+                .seal_in_place(packet, mac, seq)
+                .map_err(|err| Error::Cipher(err.into()))?;
+        } else {
+            cipher
+                .seal(&*packet, mac, seq)
+                .map_err(|err| Error::Cipher(err.into()))?;
+            cipher
+                .encrypt_payload(packet, seq)
+                .map_err(|err| Error::Cipher(err.into()))?;
+        }
+
+        if Compression::is_activation(&self.payload) {
+            cipher.authenticated();
+        }
+
+        #[cfg(feature = "tracing")]
+        Self::trace(
+            Direction::Outbound,
+            &cipher.algorithm(),
+            seq,
+            buf.len(),
+            &self.payload,
+        );
 
         Ok(())
     }
+
+    /// Emit a `tracing` event for a packet of `size` bytes on the wire, carrying the `payload`,
+    /// and processed by the `cipher` algorithm.
+    #[cfg(feature = "tracing")]
+    fn trace(direction: Direction, cipher: &str, seq: u32, size: usize, payload: &[u8]) {
+        tracing::trace!(
+            ?direction,
+            cipher,
+            seq,
+            size,
+            payload = payload.len(),
+            message_id = payload
+                .first()
+                .copied()
+                .map(MessageId)
+                .map(tracing::field::display),
+            "packet"
+        );
+    }
 }
 
-/// Allow types implementing [`BinWrite`] to be easily converted to a [`Packet`].
-pub trait IntoPacket {
+/// Allow types to be infallibly converted to a [`Packet`].
+///
+/// Messages are to be converted with [`TryIntoPacket`], since their serialization may fail.
+pub trait IntoPacket<'b> {
     /// Convert the current type to a [`Packet`].
-    fn into_packet(self) -> Packet;
+    fn into_packet(self) -> Packet<'b>;
 }
 
-impl IntoPacket for Packet {
-    fn into_packet(self) -> Packet {
+impl<'b> IntoPacket<'b> for Packet<'b> {
+    fn into_packet(self) -> Packet<'b> {
         self
     }
 }
 
-impl<T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian> IntoPacket for &T {
-    fn into_packet(self) -> Packet {
-        let mut buffer = std::io::Cursor::new(Vec::new());
-        self.write(&mut buffer)
-            .expect("Failed to convert `impl BinWrite` type to Packet");
+/// Allow types implementing [`BinWrite`] to be converted to a [`Packet`], failing if their serialization fails.
+///
+/// The freshly allocated payload is pre-sized with the [`WireSize::wire_size_hint`] of the message,
+/// so that it isn't reallocated while growing during the serialization.
+pub trait TryIntoPacket<'b> {
+    /// Try to convert the current type to a [`Packet`].
+    fn try_into_packet(self) -> Result<Packet<'b>, binrw::Error>;
+}
 
-        Packet {
-            payload: buffer.into_inner(),
+impl<'b> TryIntoPacket<'b> for Packet<'b> {
+    fn try_into_packet(self) -> Result<Packet<'b>, binrw::Error> {
+        Ok(self)
+    }
+}
+
+impl<'b, T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian> TryIntoPacket<'b> for &T {
+    fn try_into_packet(self) -> Result<Packet<'b>, binrw::Error> {
+        let mut buffer = std::io::Cursor::new(Vec::with_capacity(self.wire_size_hint()));
+        self.write(&mut buffer)?;
+
+        Ok(Packet {
+            payload: buffer.into_inner().into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rand_core::OsRng;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::arch::Ascii;
+
+    #[test]
+    fn it_exposes_the_payload() {
+        let packet = Packet::from(vec![97, 0, 0, 0, 7]);
+
+        assert_eq!(packet.len(), 5);
+        assert!(!packet.is_empty());
+        assert_eq!(packet.payload(), packet.as_ref());
+        assert_eq!(packet.peek_message_id(), Some(MessageId(97)));
+        assert_eq!(packet.into_inner(), [97, 0, 0, 0, 7]);
+
+        assert!(Packet::from(arch::Bytes::borrowed(&[])).is_empty());
+    }
+
+    #[rstest]
+    #[case(vec![], Some(0))]
+    #[case(vec![21], None)]
+    #[case(vec![94; Packet::PAYLOAD_MAX_SIZE], None)]
+    #[case(vec![94; PACKET_MAX_SIZE - 1 - 4], Some(PACKET_MAX_SIZE - 1 - 4))]
+    #[case(vec![94; Packet::PAYLOAD_MAX_SIZE + 1], Some(Packet::PAYLOAD_MAX_SIZE + 1))]
+    fn it_validates_new_payloads(#[case] payload: Vec<u8>, #[case] invalid: Option<usize>) {
+        match (Packet::new(payload.clone()), invalid) {
+            (Ok(packet), None) => assert_eq!(packet, Packet::from(payload)),
+            (Err(Error::PacketTooSmall { size }), Some(0)) => assert_eq!(size, 0),
+            (Err(Error::PacketTooLarge { size, .. }), Some(invalid)) => assert_eq!(size, invalid),
+            (result, _) => unimplemented!("{result:?}"),
+        }
+    }
+
+    /// A toy _AEAD_ cipher, encrypting the length separately and authenticating the ciphertext.
+    struct Aead;
+
+    impl Aead {
+        fn tag(buf: &[u8], seq: u32) -> Vec<u8> {
+            buf.iter()
+                .fold(seq, |acc, byte| acc.rotate_left(5) ^ u32::from(*byte))
+                .to_be_bytes()
+                .to_vec()
+        }
+    }
+
+    impl Mac for Aead {
+        fn algorithm(&self) -> Ascii<'static> {
+            arch::ascii!("toy-aead@example.com")
+        }
+
+        fn size(&self) -> usize {
+            4
+        }
+
+        fn mode(&self) -> MacMode {
+            MacMode::Aead
         }
     }
+
+    impl CipherCore for Aead {
+        type Err = binrw::Error;
+        type Mac = Self;
+
+        fn algorithm(&self) -> Ascii<'static> {
+            arch::ascii!("toy-aead@example.com")
+        }
+
+        fn mac(&self) -> &Self::Mac {
+            self
+        }
+
+        fn block_size(&self) -> usize {
+            8
+        }
+    }
+
+    impl OpeningCipher for Aead {
+        fn decrypt_len(&mut self, len: &[u8; 4], seq: u32) -> Result<u32, Self::Err> {
+            Ok(u32::from_be_bytes(*len) ^ seq ^ 0x5a5a5a5a)
+        }
+
+        fn decrypt_payload(&mut self, buf: &mut [u8], seq: u32) -> Result<(), Self::Err> {
+            buf.iter_mut().for_each(|byte| *byte ^= seq as u8);
+
+            Ok(())
+        }
+
+        fn open<B: AsRef<[u8]>>(&mut self, buf: B, mac: &[u8], seq: u32) -> Result<(), Self::Err> {
+            if Self::tag(buf.as_ref(), seq) == mac {
+                Ok(())
+            } else {
+                Err(binrw::Error::AssertFail {
+                    pos: 0,
+                    message: "tag mismatch".into(),
+                })
+            }
+        }
+
+        fn decompress(&mut self, _buf: &mut Vec<u8>) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl SealingCipher for Aead {
+        fn compress(&mut self, buf: &[u8], output: &mut Vec<u8>) -> Result<(), Self::Err> {
+            output.extend_from_slice(buf);
+
+            Ok(())
+        }
+
+        fn encrypt_len(&mut self, len: &mut [u8; 4], seq: u32) -> Result<(), Self::Err> {
+            *len = (u32::from_be_bytes(*len) ^ seq ^ 0x5a5a5a5a).to_be_bytes();
+
+            Ok(())
+        }
+
+        fn encrypt_payload(&mut self, buf: &mut [u8], seq: u32) -> Result<(), Self::Err> {
+            buf.iter_mut().for_each(|byte| *byte ^= seq as u8);
+
+            Ok(())
+        }
+
+        fn seal<B: AsRef<[u8]>>(
+            &mut self,
+            buf: B,
+            mac: &mut [u8],
+            seq: u32,
+        ) -> Result<(), Self::Err> {
+            mac.copy_from_slice(&Self::tag(buf.as_ref(), seq));
+
+            Ok(())
+        }
+    }
+
+    /// A toy _AEAD_ cipher, with the length in plaintext as additional authenticated data.
+    struct Gcm;
+
+    impl Gcm {
+        fn unused() -> binrw::Error {
+            binrw::Error::AssertFail {
+                pos: 0,
+                message: "only the in-place methods should be used".into(),
+            }
+        }
+    }
+
+    impl CipherCore for Gcm {
+        type Err = binrw::Error;
+        type Mac = Aead;
+
+        fn algorithm(&self) -> Ascii<'static> {
+            arch::ascii!("toy-gcm@example.com")
+        }
+
+        fn mac(&self) -> &Self::Mac {
+            &Aead
+        }
+
+        fn block_size(&self) -> usize {
+            16
+        }
+
+        fn tag_size(&self) -> usize {
+            4
+        }
+
+        fn max_packet_size(&self) -> usize {
+            256 * 1024
+        }
+    }
+
+    impl OpeningCipher for Gcm {
+        fn decrypt_payload(&mut self, _buf: &mut [u8], _seq: u32) -> Result<(), Self::Err> {
+            Err(Self::unused())
+        }
+
+        fn open<B: AsRef<[u8]>>(
+            &mut self,
+            _buf: B,
+            _mac: &[u8],
+            _seq: u32,
+        ) -> Result<(), Self::Err> {
+            Err(Self::unused())
+        }
+
+        fn open_in_place(&mut self, buf: &mut [u8], mac: &[u8], seq: u32) -> Result<(), Self::Err> {
+            Aead.open(&*buf, mac, seq)?;
+            Aead.decrypt_payload(&mut buf[4..], seq)
+        }
+
+        fn decompress(&mut self, _buf: &mut Vec<u8>) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl SealingCipher for Gcm {
+        fn compress(&mut self, buf: &[u8], output: &mut Vec<u8>) -> Result<(), Self::Err> {
+            output.extend_from_slice(buf);
+
+            Ok(())
+        }
+
+        fn encrypt_payload(&mut self, _buf: &mut [u8], _seq: u32) -> Result<(), Self::Err> {
+            Err(Self::unused())
+        }
+
+        fn seal<B: AsRef<[u8]>>(
+            &mut self,
+            _buf: B,
+            _mac: &mut [u8],
+            _seq: u32,
+        ) -> Result<(), Self::Err> {
+            Err(Self::unused())
+        }
+
+        fn seal_in_place(
+            &mut self,
+            buf: &mut [u8],
+            mac: &mut [u8],
+            seq: u32,
+        ) -> Result<(), Self::Err> {
+            Aead.encrypt_payload(&mut buf[4..], seq)?;
+            Aead.seal(&*buf, mac, seq)
+        }
+    }
+
+    /// A toy stream cipher with a block size of `1`, whose keystream spans packets,
+    /// authenticating the plaintext with the MAC (_encrypt-and-MAC_).
+    #[derive(Default)]
+    struct Stream(u8);
+
+    impl Stream {
+        fn apply(&mut self, buf: &mut [u8]) {
+            for byte in buf {
+                *byte ^= self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+        }
+    }
+
+    /// The _encrypt-and-MAC_ MAC of the [`Stream`] cipher.
+    struct EncryptAndMac;
+
+    impl Mac for EncryptAndMac {
+        fn algorithm(&self) -> Ascii<'static> {
+            arch::ascii!("toy-mac@example.com")
+        }
+
+        fn size(&self) -> usize {
+            4
+        }
+
+        fn mode(&self) -> MacMode {
+            MacMode::EncryptAndMac
+        }
+    }
+
+    impl CipherCore for Stream {
+        type Err = binrw::Error;
+        type Mac = EncryptAndMac;
+
+        fn algorithm(&self) -> Ascii<'static> {
+            arch::ascii!("toy-stream@example.com")
+        }
+
+        fn mac(&self) -> &Self::Mac {
+            &EncryptAndMac
+        }
+
+        fn block_size(&self) -> usize {
+            1
+        }
+    }
+
+    impl OpeningCipher for Stream {
+        fn decrypt_payload(&mut self, buf: &mut [u8], _seq: u32) -> Result<(), Self::Err> {
+            self.apply(buf);
+
+            Ok(())
+        }
+
+        fn open<B: AsRef<[u8]>>(&mut self, buf: B, mac: &[u8], seq: u32) -> Result<(), Self::Err> {
+            Aead.open(buf, mac, seq)
+        }
+
+        fn decompress(&mut self, _buf: &mut Vec<u8>) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl SealingCipher for Stream {
+        fn compress(&mut self, buf: &[u8], output: &mut Vec<u8>) -> Result<(), Self::Err> {
+            output.extend_from_slice(buf);
+
+            Ok(())
+        }
+
+        fn encrypt_payload(&mut self, buf: &mut [u8], _seq: u32) -> Result<(), Self::Err> {
+            self.apply(buf);
+
+            Ok(())
+        }
+
+        fn seal<B: AsRef<[u8]>>(
+            &mut self,
+            buf: B,
+            mac: &mut [u8],
+            seq: u32,
+        ) -> Result<(), Self::Err> {
+            Aead.seal(buf, mac, seq)
+        }
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[21])]
+    #[case(&[0x42; 1024])]
+    fn it_roundtrips_small_block_ciphers(#[case] payload: &[u8]) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        let mut writer = PacketWriter::new(Vec::new(), Stream::default(), OsRng);
+        writer.blocking_send(packet.as_borrow()).unwrap();
+        writer.blocking_send(packet.as_borrow()).unwrap();
+        let (wire, _) = writer.into_inner();
+
+        // The packets are still aligned on the minimum of 8 bytes, before their MAC
+        assert_eq!((wire.len() - 2 * 4) % 8, 0);
+
+        let (mut cipher, mut seq) = (Stream::default(), Seq::new());
+        let mut reader = &wire[..];
+        for _ in 0..2 {
+            let read = Packet::from_blocking_reader(&mut reader, &mut cipher, &mut seq).unwrap();
+            assert_eq!(read, packet);
+        }
+
+        let mut reader = PacketReader::new(&wire[..], Stream::default());
+        for _ in 0..2 {
+            assert_eq!(reader.blocking_recv_packet().unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn it_seals_the_largest_payloads() {
+        let packet = Packet::new(vec![94; Packet::PAYLOAD_MAX_SIZE]).unwrap();
+
+        let mut buf = Vec::new();
+        packet
+            .to_blocking_writer(
+                &mut buf,
+                &mut Stream::default(),
+                &mut Seq::new(),
+                &mut OsRng,
+            )
+            .unwrap();
+        let read =
+            Packet::from_blocking_reader(&mut &buf[..], &mut Stream::default(), &mut Seq::new())
+                .unwrap();
+        assert_eq!(read, packet);
+
+        buf.clear();
+        packet
+            .to_blocking_writer(&mut buf, &mut Aead, &mut Seq::new(), &mut OsRng)
+            .unwrap();
+        let read = Packet::from_blocking_reader(&mut &buf[..], &mut Aead, &mut Seq::new()).unwrap();
+        assert_eq!(read, packet);
+    }
+
+    #[cfg(feature = "none")]
+    #[rstest]
+    #[case(&[])]
+    #[case(&[21])]
+    #[case(&[0x42; 1024])]
+    fn it_roundtrips_blocking(#[case] payload: &[u8]) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        let mut buf = Vec::new();
+        packet
+            .to_blocking_writer(&mut buf, &mut NoneCipher, &mut Seq::new(), &mut OsRng)
+            .unwrap();
+
+        assert_eq!(buf.len() % 8, 0);
+
+        let read =
+            Packet::from_blocking_reader(&mut &buf[..], &mut NoneCipher, &mut Seq::new()).unwrap();
+
+        assert_eq!(read, packet);
+    }
+
+    #[cfg(feature = "none")]
+    #[test]
+    fn it_reads_into_reused_buffer() {
+        let mut wire = Vec::new();
+        for payload in [&b"first"[..], &b"second payload"[..]] {
+            Packet {
+                payload: arch::Bytes::borrowed(payload),
+            }
+            .to_blocking_writer(&mut wire, &mut NoneCipher, &mut Seq::new(), &mut OsRng)
+            .unwrap();
+        }
+
+        let mut reader = &wire[..];
+        let mut buf = Vec::new();
+
+        let packet = Packet::from_blocking_reader_into(
+            &mut reader,
+            &mut NoneCipher,
+            &mut Seq::new(),
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(&*packet.payload, b"first");
+
+        let packet = Packet::from_blocking_reader_into(
+            &mut reader,
+            &mut NoneCipher,
+            &mut Seq::new(),
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(&*packet.payload, b"second payload");
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[21])]
+    #[case(&[0x42; 1024])]
+    fn it_roundtrips_aead(#[case] payload: &[u8]) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        let mut buf = Vec::new();
+        packet
+            .to_blocking_writer(&mut buf, &mut Aead, &mut Seq::new(), &mut OsRng)
+            .unwrap();
+
+        let read = Packet::from_blocking_reader(&mut &buf[..], &mut Aead, &mut Seq::new()).unwrap();
+        assert_eq!(read, packet);
+
+        let mut mismatched = Seq::new();
+        mismatched.increment();
+        Packet::from_blocking_reader(&mut &buf[..], &mut Aead, &mut mismatched).unwrap_err();
+    }
+
+    #[test]
+    fn it_authenticates_vectored_buffers() {
+        let (len, payload) = (&[0, 0, 0, 12][..], &[4, 21, 0, 0, 0, 0][..]);
+
+        let mut mac = [0; 4];
+        Aead.seal_vectored(&[len, payload], &mut mac, 3).unwrap();
+        assert_eq!(mac[..], Aead::tag(&[len, payload].concat(), 3));
+
+        Aead.open_vectored(&[len, payload], &mac, 3).unwrap();
+        Aead.open_vectored(&[len, payload], &mac, 4).unwrap_err();
+    }
+
+    #[test]
+    fn it_streams_with_owned_state() {
+        let mut observed = Vec::new();
+        let mut writer = PacketWriter::new(Vec::new(), Aead, OsRng).with_observer(
+            |direction, seq, packet: &Packet<'_>| {
+                observed.push((direction, seq, packet.peek_message_id()))
+            },
+        );
+        writer
+            .blocking_send(&crate::trans::Ignore::default())
+            .unwrap();
+        writer
+            .blocking_send(&crate::connect::ChannelClose {
+                recipient_channel: 7,
+            })
+            .unwrap();
+        assert_eq!(writer.seq().get(), 2);
+
+        let (wire, _) = writer.into_inner();
+        assert_eq!(
+            observed,
+            [
+                (Direction::Outbound, 0, Some(MessageId::IGNORE)),
+                (Direction::Outbound, 1, Some(MessageId::CHANNEL_CLOSE))
+            ]
+        );
+        let mut reader = PacketReader::new(&wire[..], Aead);
+
+        reader.blocking_recv::<crate::trans::Ignore>().unwrap();
+        let close = reader
+            .blocking_recv::<crate::connect::ChannelClose>()
+            .unwrap();
+        assert_eq!(close.recipient_channel, 7);
+        assert_eq!(reader.seq().get(), 2);
+    }
+
+    #[test]
+    fn it_writes_packets_at_once() {
+        /// A writer recording the size of each write.
+        #[derive(Default)]
+        struct Writes(Vec<usize>);
+
+        impl std::io::Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut writer = PacketWriter::new(Writes::default(), Aead, OsRng);
+        writer.blocking_send(&crate::trans::NewKeys).unwrap();
+        writer
+            .blocking_send(&crate::connect::ChannelClose {
+                recipient_channel: 7,
+            })
+            .unwrap();
+
+        let (writes, _) = writer.into_inner();
+        assert_eq!(
+            writes.0.len(),
+            2,
+            "each packet must be emitted in one write"
+        );
+    }
+
+    #[test]
+    fn it_encodes_into_reused_buffer() {
+        let mut buf = Vec::new();
+
+        let packet = Packet::encode_into(&crate::trans::NewKeys, &mut buf).unwrap();
+        assert_eq!(&*packet.payload, &[21]);
+
+        let packet = Packet::encode_into(
+            &crate::connect::ChannelClose {
+                recipient_channel: 7,
+            },
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(&*packet.payload, &[97, 0, 0, 0, 7]);
+    }
+
+    #[test]
+    fn it_encodes_to_slice() {
+        let mut buf = [0u8; 16];
+
+        let packet = Packet::encode_to_slice(
+            &crate::connect::ChannelWindowAdjust {
+                recipient_channel: 1,
+                bytes_to_add: 2,
+            },
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(&*packet.payload, &[93, 0, 0, 0, 1, 0, 0, 0, 2]);
+
+        Packet::encode_to_slice(
+            &crate::connect::ChannelWindowAdjust {
+                recipient_channel: 1,
+                bytes_to_add: 2,
+            },
+            &mut buf[..4],
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn it_recycles_owned_payloads() {
+        let packet = Packet {
+            payload: Vec::with_capacity(64).into(),
+        };
+
+        let buf = packet.recycle();
+        assert!(buf.is_empty());
+        assert!(buf.capacity() >= 64);
+
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&[21]),
+        };
+        assert_eq!(packet.recycle().capacity(), 0);
+    }
+
+    #[test]
+    fn it_reports_encoding_failures() {
+        /// A message always failing to serialize.
+        struct Unencodable;
+
+        impl BinWrite for Unencodable {
+            type Args<'a> = ();
+
+            fn write_options<W: std::io::Write + std::io::Seek>(
+                &self,
+                writer: &mut W,
+                _endian: binrw::Endian,
+                _args: Self::Args<'_>,
+            ) -> binrw::BinResult<()> {
+                Err(binrw::Error::AssertFail {
+                    pos: writer.stream_position()?,
+                    message: "unencodable".into(),
+                })
+            }
+        }
+
+        impl WriteEndian for Unencodable {
+            const ENDIAN: binrw::meta::EndianKind =
+                binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+        }
+
+        (&Unencodable).try_into_packet().unwrap_err();
+
+        let mut writer = PacketWriter::new(Vec::new(), Aead, OsRng);
+        let err = writer.blocking_send(&Unencodable).unwrap_err();
+        assert!(matches!(err, Error::Encode(_)), "{err:?}");
+        assert_eq!(writer.seq().get(), 0);
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn it_resumes_cancelled_reads() {
+        use futures::FutureExt;
+
+        /// A reader yielding a single byte every other poll.
+        struct Trickle<'a>(&'a [u8], bool);
+
+        impl futures::io::AsyncRead for Trickle<'_> {
+            fn poll_read(
+                mut self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                buf: &mut [u8],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.1 = !self.1;
+                if self.1 {
+                    cx.waker().wake_by_ref();
+
+                    return std::task::Poll::Pending;
+                }
+
+                let read = buf.len().min(self.0.len()).min(1);
+                buf[..read].copy_from_slice(&self.0[..read]);
+                self.0 = &self.0[read..];
+
+                std::task::Poll::Ready(Ok(read))
+            }
+        }
+
+        let mut writer = PacketWriter::new(Vec::new(), Aead, OsRng);
+        for recipient_channel in 0..3 {
+            writer
+                .blocking_send(&crate::connect::ChannelClose { recipient_channel })
+                .unwrap();
+        }
+
+        let (wire, _) = writer.into_inner();
+        let mut reader = PacketReader::new(Trickle(&wire, false), Aead);
+
+        for recipient_channel in 0..3 {
+            // Drop the future each time it is pending, as a `select!` would
+            let close = loop {
+                if let Some(close) = reader.recv::<crate::connect::ChannelClose>().now_or_never() {
+                    break close.unwrap();
+                }
+            };
+
+            assert_eq!(close.recipient_channel, recipient_channel);
+        }
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[21])]
+    #[case(&[0x42; 1024])]
+    fn it_roundtrips_aead_with_plaintext_length(#[case] payload: &[u8]) {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(payload),
+        };
+
+        let mut buf = Vec::new();
+        packet
+            .to_blocking_writer(&mut buf, &mut Gcm, &mut Seq::new(), &mut OsRng)
+            .unwrap();
+
+        // The length is transmitted in plaintext, and the rest is aligned to the block size
+        assert_eq!(u32::from_be_bytes(buf[..4].try_into().unwrap()) % 16, 0);
+
+        let read = Packet::from_blocking_reader(&mut &buf[..], &mut Gcm, &mut Seq::new()).unwrap();
+        assert_eq!(read, packet);
+    }
+
+    #[cfg(feature = "none")]
+    #[rstest]
+    #[case(&[0, 0, 0, 12, 2, 21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])] // padding too small
+    #[case(&[0, 0, 0, 13, 4, 21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])] // misaligned
+    fn it_rejects_invalid_padding(#[case] wire: &[u8]) {
+        let err = Packet::from_blocking_reader(&mut &wire[..], &mut NoneCipher, &mut Seq::new())
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Integrity), "{err:?}");
+    }
+
+    #[cfg(feature = "none")]
+    #[rstest]
+    #[case(&[0, 0, 0, 0, 4, 0, 0, 0])]
+    #[case(&[0, 0, 0, 3, 2, 21, 0, 0])]
+    #[case(&[0, 0, 0, 11, 4, 21, 0, 0, 0, 0, 0, 0, 0, 0, 0])]
+    fn it_rejects_lengths_below_the_minimum(#[case] wire: &[u8]) {
+        // The length isn't authenticated in the encrypt-and-MAC mode, so it's reported as an integrity failure
+        let err = Packet::from_blocking_reader(&mut &wire[..], &mut NoneCipher, &mut Seq::new())
+            .unwrap_err();
+        assert!(matches!(err, Error::Integrity), "{err:?}");
+
+        let mut reader = PacketReader::new(wire, NoneCipher);
+        let err = reader.blocking_recv_packet().unwrap_err();
+        assert!(matches!(err, Error::Integrity), "{err:?}");
+    }
+
+    #[test]
+    fn it_rejects_lengths_below_the_first_block() {
+        // A plaintext length of 4 bytes, with it's MAC, shorter than the first block of 16 bytes
+        let wire = [0, 0, 0, 4, 4, 21, 0, 0, 1, 2, 3, 4, 0, 0, 0, 0];
+
+        let err =
+            Packet::from_blocking_reader(&mut &wire[..], &mut Gcm, &mut Seq::new()).unwrap_err();
+        assert!(matches!(err, Error::PacketTooSmall { size: 4 }), "{err:?}");
+
+        let mut reader = PacketReader::new(&wire[..], Gcm);
+        let err = reader.blocking_recv_packet().unwrap_err();
+        assert!(matches!(err, Error::PacketTooSmall { size: 4 }), "{err:?}");
+    }
+
+    #[cfg(feature = "none")]
+    #[test]
+    fn it_honors_max_packet_size() {
+        let packet = Packet {
+            payload: arch::Bytes::owned(vec![0x42; 128 * 1024]),
+        };
+
+        let mut buf = Vec::new();
+        let err = packet
+            .to_blocking_writer(&mut buf, &mut NoneCipher, &mut Seq::new(), &mut OsRng)
+            .unwrap_err();
+        let Error::Seal(ref inner) = err else {
+            unimplemented!("{err:?}")
+        };
+        assert!(matches!(**inner, Error::PacketTooLarge { .. }), "{err:?}");
+        assert_eq!(err.disconnect_reason(), None);
+
+        packet
+            .to_blocking_writer(&mut buf, &mut Gcm, &mut Seq::new(), &mut OsRng)
+            .unwrap();
+        let read = Packet::from_blocking_reader(&mut &buf[..], &mut Gcm, &mut Seq::new()).unwrap();
+        assert_eq!(read, packet);
+
+        let err = Packet::from_blocking_reader(&mut &buf[..], &mut NoneCipher, &mut Seq::new())
+            .unwrap_err();
+        assert!(matches!(err, Error::Integrity), "{err:?}");
+        assert_eq!(
+            err.disconnect_reason(),
+            Some(crate::trans::DisconnectReason::MacError)
+        );
+    }
+
+    #[test]
+    fn it_reports_integrity_failures() {
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&[21]),
+        };
+
+        let mut buf = Vec::new();
+        packet
+            .to_blocking_writer(&mut buf, &mut Aead, &mut Seq::new(), &mut OsRng)
+            .unwrap();
+
+        // Tamper with the last byte of the authentication tag
+        *buf.last_mut().unwrap() ^= 0xff;
+
+        let err =
+            Packet::from_blocking_reader(&mut &buf[..], &mut Aead, &mut Seq::new()).unwrap_err();
+        assert!(matches!(err, Error::Integrity), "{err:?}");
+    }
+
+    /// A toy cipher failing to decrypt anything, in the provided [`MacMode`].
+    struct Failing(MacMode);
+
+    impl Mac for Failing {
+        fn algorithm(&self) -> Ascii<'static> {
+            arch::ascii!("toy-failing@example.com")
+        }
+
+        fn size(&self) -> usize {
+            4
+        }
+
+        fn mode(&self) -> MacMode {
+            self.0
+        }
+    }
+
+    impl CipherCore for Failing {
+        type Err = binrw::Error;
+        type Mac = Self;
+
+        fn algorithm(&self) -> Ascii<'static> {
+            arch::ascii!("toy-failing@example.com")
+        }
+
+        fn mac(&self) -> &Self::Mac {
+            self
+        }
+
+        fn block_size(&self) -> usize {
+            8
+        }
+    }
+
+    impl OpeningCipher for Failing {
+        fn decrypt_len(&mut self, _len: &[u8; 4], _seq: u32) -> Result<u32, Self::Err> {
+            Err(Gcm::unused())
+        }
+
+        fn decrypt_payload(&mut self, _buf: &mut [u8], _seq: u32) -> Result<(), Self::Err> {
+            Err(Gcm::unused())
+        }
+
+        fn open<B: AsRef<[u8]>>(
+            &mut self,
+            _buf: B,
+            _mac: &[u8],
+            _seq: u32,
+        ) -> Result<(), Self::Err> {
+            Err(Gcm::unused())
+        }
+
+        fn decompress(&mut self, _buf: &mut Vec<u8>) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    #[case(MacMode::EncryptAndMac)]
+    #[case(MacMode::Aead)]
+    fn it_reports_header_decryption_failures(#[case] mode: MacMode) {
+        let wire = [0; 32];
+
+        let err = Packet::from_blocking_reader(&mut &wire[..], &mut Failing(mode), &mut Seq::new())
+            .unwrap_err();
+        assert!(matches!(err, Error::Integrity), "{err:?}");
+
+        let mut reader = PacketReader::new(&wire[..], Failing(mode));
+        let err = reader.blocking_recv_packet().unwrap_err();
+        assert!(matches!(err, Error::Integrity), "{err:?}");
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn it_collects_metrics() {
+        let mut writer =
+            PacketWriter::new(Vec::new(), Aead, OsRng).with_observer(Metrics::default());
+        writer.blocking_send(&crate::trans::NewKeys).unwrap();
+        writer.blocking_send(&crate::trans::NewKeys).unwrap();
+
+        let outbound = *writer.observer().get(Direction::Outbound);
+        let (mut wire, _) = writer.into_inner();
+        assert_eq!(outbound.packets, 2);
+        assert_eq!(outbound.bytes, wire.len() as u64);
+        assert_eq!(outbound.allocations, 1);
+
+        // Tamper with the last byte of the second packet's authentication tag
+        *wire.last_mut().unwrap() ^= 0xff;
+
+        let mut reader = PacketReader::new(&wire[..], Aead).with_observer(Metrics::default());
+        reader.blocking_recv_packet().unwrap();
+        reader.blocking_recv_packet().unwrap_err();
+
+        let inbound = reader.observer().get(Direction::Inbound);
+        assert_eq!(inbound.packets, 1);
+        assert_eq!(inbound.bytes, wire.len() as u64 / 2);
+        assert_eq!(inbound.integrity_failures, 1);
+        assert_eq!(inbound.failures, 1);
+        assert_eq!(
+            *reader.observer().get(Direction::Outbound),
+            Counters::default()
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn it_traces_packets() {
+        use std::sync::{Arc, Mutex};
+
+        /// A subscriber recording the fields of the events as `name=value` strings.
+        #[derive(Default, Clone)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl tracing::field::Visit for Recorder {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}={value:?}", field.name()));
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+                tracing::span::Id::from_u64(1)
+            }
+
+            fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+            fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+            fn event(&self, event: &tracing::Event<'_>) {
+                event.record(&mut self.clone());
+            }
+
+            fn enter(&self, _: &tracing::span::Id) {}
+
+            fn exit(&self, _: &tracing::span::Id) {}
+        }
+
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut buf = Vec::new();
+            crate::trans::NewKeys
+                .try_into_packet()
+                .unwrap()
+                .to_blocking_writer(&mut buf, &mut Aead, &mut Seq::new(), &mut OsRng)
+                .unwrap();
+            Packet::from_blocking_reader(&mut &buf[..], &mut Aead, &mut Seq::new()).unwrap();
+        });
+
+        let fields = recorder.0.lock().unwrap();
+        for direction in ["Outbound", "Inbound"] {
+            assert!(
+                fields.contains(&format!("direction={direction}")),
+                "{fields:?}"
+            );
+        }
+        assert_eq!(
+            fields
+                .iter()
+                .filter(|field| *field == "message_id=SSH_MSG_NEWKEYS (21)")
+                .count(),
+            2,
+            "{fields:?}"
+        );
+        assert!(
+            fields.contains(&"cipher=\"toy-aead@example.com\"".to_string()),
+            "{fields:?}"
+        );
+    }
 }
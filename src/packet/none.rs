@@ -0,0 +1,85 @@
+use super::{CipherCore, Compression, Mac, MacMode, OpeningCipher, SealingCipher};
+use crate::arch::{self, Ascii};
+
+/// The `none` _Message Authentication Code_, producing no authentication at all.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.4>.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoneMac;
+
+impl Mac for NoneMac {
+    fn algorithm(&self) -> Ascii<'static> {
+        arch::ascii!("none")
+    }
+
+    fn size(&self) -> usize {
+        0
+    }
+
+    fn mode(&self) -> MacMode {
+        MacMode::EncryptAndMac
+    }
+}
+
+/// The `none` cipher, leaving the packets unencrypted, uncompressed and unauthenticated
+/// as during the first key exchange, before the `SSH_MSG_NEWKEYS` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.3>.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoneCipher;
+
+impl CipherCore for NoneCipher {
+    type Err = binrw::Error;
+    type Mac = NoneMac;
+
+    fn algorithm(&self) -> Ascii<'static> {
+        arch::ascii!("none")
+    }
+
+    fn mac(&self) -> &Self::Mac {
+        &NoneMac
+    }
+
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn compression(&self) -> Compression {
+        Compression::None
+    }
+}
+
+impl OpeningCipher for NoneCipher {
+    fn decrypt_payload(&mut self, _buf: &mut [u8], _seq: u32) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn open<B: AsRef<[u8]>>(&mut self, _buf: B, _mac: &[u8], _seq: u32) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn decompress(&mut self, _buf: &mut Vec<u8>) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+impl SealingCipher for NoneCipher {
+    fn compress(&mut self, buf: &[u8], output: &mut Vec<u8>) -> Result<(), Self::Err> {
+        output.extend_from_slice(buf);
+
+        Ok(())
+    }
+
+    fn encrypt_payload(&mut self, _buf: &mut [u8], _seq: u32) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn seal<B: AsRef<[u8]>>(
+        &mut self,
+        _buf: B,
+        _mac: &mut [u8],
+        _seq: u32,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
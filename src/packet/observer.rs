@@ -0,0 +1,53 @@
+use super::{Error, Packet};
+
+/// The direction a [`Packet`] is travelling in, relative to the local side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// The [`Packet`] was received from the peer.
+    Inbound,
+
+    /// The [`Packet`] is being sent to the peer.
+    Outbound,
+}
+
+/// An observer of the [`Packet`]s going through a [`PacketReader`](super::PacketReader)
+/// or a [`PacketWriter`](super::PacketWriter), for debugging proxies, tracing or metrics.
+///
+/// It is implemented for `()`, observing nothing, and for closures taking the same arguments as [`PacketObserver::observe`].
+///
+/// The other hooks instrument the packet layer itself, and default to doing nothing.
+pub trait PacketObserver {
+    /// Observe the plaintext `packet` travelling in `direction`, with it's `seq` sequence number.
+    ///
+    /// The message number and the payload length can be obtained with
+    /// [`Packet::peek_message_id`] and the [`Packet::payload`]'s length.
+    fn observe(&mut self, direction: Direction, seq: u32, packet: &Packet<'_>);
+
+    /// Observe the `size` of a sealed packet on the wire travelling in `direction`,
+    /// including it's length field, padding and MAC.
+    fn transferred(&mut self, direction: Direction, size: usize) {
+        let _ = (direction, size);
+    }
+
+    /// Observe the growth by `size` bytes of the buffer used to process packets travelling in `direction`.
+    fn allocated(&mut self, direction: Direction, size: usize) {
+        let _ = (direction, size);
+    }
+
+    /// Observe the `error` which aborted the processing of a packet travelling in `direction`.
+    ///
+    /// Errors of the underlying reader or writer are returned to the caller without being observed.
+    fn failed(&mut self, direction: Direction, error: &Error) {
+        let _ = (direction, error);
+    }
+}
+
+impl PacketObserver for () {
+    fn observe(&mut self, _direction: Direction, _seq: u32, _packet: &Packet<'_>) {}
+}
+
+impl<F: FnMut(Direction, u32, &Packet<'_>)> PacketObserver for F {
+    fn observe(&mut self, direction: Direction, seq: u32, packet: &Packet<'_>) {
+        self(direction, seq, packet)
+    }
+}
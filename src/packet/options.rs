@@ -0,0 +1,196 @@
+use std::io::{Read, Seek};
+
+use binrw::{meta::ReadEndian, BinRead, BinResult};
+
+/// How messages with an unknown message number are handled by [`Packet::decode_with`](super::Packet::decode_with).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnknownMessages {
+    /// Capture the raw [`Packet`](super::Packet) as [`Message::Unknown`](crate::Message::Unknown),
+    /// to be replied with a `SSH_MSG_UNIMPLEMENTED`.
+    #[default]
+    Capture,
+
+    /// Reject the packet with a decoding error.
+    Reject,
+}
+
+/// The options of the decoding of [`Packet`](super::Packet)s into messages,
+/// to harden the parsing of untrusted input or to relax it for test tooling.
+///
+/// The [`Default`] options are the ones of [`Packet::to`](super::Packet::to) and [`Packet::decode`](super::Packet::decode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodeOptions {
+    /// The maximum size of any `string` in the message, in bytes.
+    pub max_string_size: usize,
+
+    /// The maximum number of names in any `name-list` in the message.
+    pub max_name_list_entries: usize,
+
+    /// Whether to reject payloads with trailing bytes after the message.
+    pub strict: bool,
+
+    /// How to handle messages with an unknown message number.
+    pub unknown_messages: UnknownMessages,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl DecodeOptions {
+    const DEFAULT: Self = Self {
+        max_string_size: usize::MAX,
+        max_name_list_entries: usize::MAX,
+        strict: true,
+        unknown_messages: UnknownMessages::Capture,
+    };
+
+    /// Options suited to servers facing untrusted peers: `string`s are bounded to the
+    /// `32768` bytes every implementation must support, `name-list`s to `64` names,
+    /// and trailing bytes are rejected.
+    ///
+    /// Unknown messages are still captured, since they must be replied with a `SSH_MSG_UNIMPLEMENTED`,
+    /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.1>
+    /// and <https://datatracker.ietf.org/doc/html/rfc4253#section-11.4>.
+    pub fn hardened() -> Self {
+        Self {
+            max_string_size: 32768,
+            max_name_list_entries: 64,
+            strict: true,
+            unknown_messages: UnknownMessages::Capture,
+        }
+    }
+
+    /// Options suited to test tooling: nothing is bounded,
+    /// trailing bytes are ignored and unknown messages are captured.
+    pub fn permissive() -> Self {
+        Self {
+            strict: false,
+            ..Self::DEFAULT
+        }
+    }
+}
+
+/// Allow types without any `string` or `name-list` to be decoded with [`DecodeOptions`],
+/// which are passed as the `binrw` arguments of the others.
+impl From<DecodeOptions> for () {
+    fn from(_: DecodeOptions) -> Self {}
+}
+
+/// A message decodable from a [`Packet`](super::Packet) with [`DecodeOptions`],
+/// which are passed down as it's `binrw` arguments to the `string`s and `name-list`s it holds.
+pub trait Decode: Sized {
+    /// Read `Self` from the `reader`, with the decoding `options`.
+    fn read_with<R: Read + Seek>(reader: &mut R, options: &DecodeOptions) -> BinResult<Self>;
+}
+
+impl<T> Decode for T
+where
+    T: BinRead + ReadEndian,
+    for<'a> T::Args<'a>: From<DecodeOptions>,
+{
+    fn read_with<R: Read + Seek>(reader: &mut R, options: &DecodeOptions) -> BinResult<Self> {
+        T::read_args(reader, (*options).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use super::*;
+    use crate::{arch, connect, userauth, Message, Packet};
+
+    fn packet(payload: &[u8]) -> Packet<'_> {
+        Packet {
+            payload: arch::Bytes::borrowed(payload),
+        }
+    }
+
+    #[test]
+    fn it_bounds_strings() {
+        let data = connect::ChannelData {
+            recipient_channel: 0,
+            data: vec![0; 32769].into(),
+        };
+        let packet = crate::TryIntoPacket::try_into_packet(&data).unwrap();
+
+        packet.decode_with(&DecodeOptions::default()).unwrap();
+        packet.decode_with(&DecodeOptions::hardened()).unwrap_err();
+        packet
+            .to_with::<connect::ChannelData>(&DecodeOptions::hardened())
+            .unwrap_err();
+
+        packet.to::<connect::ChannelData>().unwrap();
+    }
+
+    #[test]
+    fn it_bounds_nested_strings() {
+        let request = userauth::Request {
+            username: "user".into(),
+            service_name: arch::ascii!("ssh-connection"),
+            method: userauth::Method::Password {
+                password: "*".repeat(32769).into(),
+                new: None,
+            },
+        };
+        let packet = crate::TryIntoPacket::try_into_packet(&request).unwrap();
+
+        packet.to::<userauth::Request>().unwrap();
+        packet
+            .to_with::<userauth::Request>(&DecodeOptions::hardened())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn it_bounds_name_lists() {
+        let failure = userauth::Failure {
+            continue_with: (0..65).map(|i| format!("method{i}")).collect(),
+            ..Default::default()
+        };
+        let packet = crate::TryIntoPacket::try_into_packet(&failure).unwrap();
+
+        packet
+            .to_with::<userauth::Failure>(&DecodeOptions::default())
+            .unwrap();
+        packet
+            .to_with::<userauth::Failure>(&DecodeOptions::hardened())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn it_relaxes_trailing_bytes() {
+        let packet = packet(&[97, 0, 0, 0, 7, 0xff]);
+
+        packet.decode().unwrap_err();
+        assert!(matches!(
+            packet.decode_with(&DecodeOptions::permissive()).unwrap(),
+            Message::Connect(connect::Message::ChannelClose(connect::ChannelClose {
+                recipient_channel: 7
+            }))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_unknown_messages() {
+        let packet = packet(&[192, 42]);
+
+        assert!(matches!(
+            packet.decode_with(&DecodeOptions::default()).unwrap(),
+            Message::Unknown(_)
+        ));
+        // Unknown messages must still be replied to with a `SSH_MSG_UNIMPLEMENTED`
+        assert!(matches!(
+            packet.decode_with(&DecodeOptions::hardened()).unwrap(),
+            Message::Unknown(_)
+        ));
+        packet
+            .decode_with(&DecodeOptions {
+                unknown_messages: UnknownMessages::Reject,
+                ..DecodeOptions::hardened()
+            })
+            .unwrap_err();
+    }
+}
@@ -0,0 +1,77 @@
+/// A packet sequence number, incremented for each packet in a direction of the transport.
+///
+/// The sequence number wraps around after `2^32` packets, which
+/// mandates the peers to re-exchange keys before it happens.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.4>.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Seq {
+    value: u32,
+    wrapped: bool,
+}
+
+impl Seq {
+    /// Create a new [`Seq`], starting at `0`.
+    pub const fn new() -> Self {
+        Self {
+            value: 0,
+            wrapped: false,
+        }
+    }
+
+    /// Get the current value of the sequence number, for the next packet.
+    pub const fn get(&self) -> u32 {
+        self.value
+    }
+
+    /// Whether the sequence number wrapped around since the creation or the last reset,
+    /// meaning that the keys are to be re-exchanged.
+    pub const fn is_wrapped(&self) -> bool {
+        self.wrapped
+    }
+
+    /// Increment the sequence number after a packet went through, detecting a wrap around.
+    pub fn increment(&mut self) {
+        let (value, wrapped) = self.value.overflowing_add(1);
+
+        self.value = value;
+        self.wrapped |= wrapped;
+    }
+
+    /// Reset the sequence number to `0`, after the `SSH_MSG_NEWKEYS` message in _strict key exchange_ mode.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl From<Seq> for u32 {
+    fn from(value: Seq) -> Self {
+        value.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use super::*;
+
+    #[test]
+    fn it_detects_wrap_around() {
+        let mut seq = Seq {
+            value: u32::MAX - 1,
+            wrapped: false,
+        };
+
+        seq.increment();
+        assert_eq!((seq.get(), seq.is_wrapped()), (u32::MAX, false));
+
+        seq.increment();
+        assert_eq!((seq.get(), seq.is_wrapped()), (0, true));
+
+        seq.increment();
+        assert_eq!((seq.get(), seq.is_wrapped()), (1, true));
+
+        seq.reset();
+        assert_eq!((seq.get(), seq.is_wrapped()), (0, false));
+    }
+}
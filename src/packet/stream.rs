@@ -0,0 +1,346 @@
+use rand_core::CryptoRngCore;
+
+use super::{
+    arch, cipher, DecodeOptions, Direction, Error, OpeningCipher, Packet, PacketObserver,
+    SealingCipher, Seq, TryIntoPacket,
+};
+
+/// A reader of [`Packet`](super::Packet)s, owning the underlying `reader`, the opening `cipher` state
+/// and the sequence number, so they cannot be desynchronized between calls.
+///
+/// Packets are received with `recv`, `tokio_recv` or `blocking_recv` depending on the kind of the `reader`.
+///
+/// The partially read packet is retained between calls, so the asynchronous `recv` and `tokio_recv`
+/// are cancel-safe: dropping their future (e.g. in a `select!`) and calling them again resumes the read
+/// instead of desynchronizing the stream.
+#[derive(Debug)]
+pub struct PacketReader<R, C, O = ()> {
+    pub(super) reader: R,
+    pub(super) cipher: C,
+    pub(super) seq: Seq,
+    pub(super) state: ReadState,
+    pub(super) observer: O,
+    pub(super) options: DecodeOptions,
+}
+
+impl<R, C: OpeningCipher> PacketReader<R, C> {
+    /// Create a new [`PacketReader`] from the `reader`, opening packets with the `cipher`.
+    pub fn new(reader: R, cipher: C) -> Self {
+        Self {
+            reader,
+            cipher,
+            seq: Seq::new(),
+            state: ReadState::default(),
+            observer: (),
+            options: DecodeOptions::default(),
+        }
+    }
+}
+
+impl<R, C: OpeningCipher, O: PacketObserver> PacketReader<R, C, O> {
+    /// Set the `observer`, called with each received [`Packet`](super::Packet).
+    pub fn with_observer<P: PacketObserver>(self, observer: P) -> PacketReader<R, C, P> {
+        PacketReader {
+            reader: self.reader,
+            cipher: self.cipher,
+            seq: self.seq,
+            state: self.state,
+            observer,
+            options: self.options,
+        }
+    }
+
+    /// Set the decoding `options` of the messages received with `recv`, `tokio_recv` or `blocking_recv`.
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Access the decoding options.
+    pub fn decode_options(&self) -> &DecodeOptions {
+        &self.options
+    }
+
+    /// Access the observer.
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// Mutably access the observer.
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Access the opening cipher state.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Mutably access the opening cipher state, e.g. to install new keys.
+    pub fn cipher_mut(&mut self) -> &mut C {
+        &mut self.cipher
+    }
+
+    /// The sequence number of the next [`Packet`](super::Packet) to be received.
+    pub fn seq(&self) -> Seq {
+        self.seq
+    }
+
+    /// Reset the sequence number, after the `SSH_MSG_NEWKEYS` message in _strict key exchange_ mode.
+    pub fn reset_seq(&mut self) {
+        self.seq.reset();
+    }
+
+    /// Access the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Mutably access the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consume the [`PacketReader`], returning the underlying reader and the opening cipher state.
+    pub fn into_inner(self) -> (R, C) {
+        (self.reader, self.cipher)
+    }
+}
+
+/// The progress of reading a [`Packet`], retained across cancelled reads.
+#[derive(Debug, Default)]
+pub(super) struct ReadState {
+    buf: Vec<u8>,
+    capacity: usize,
+    filled: usize,
+    len: Option<u32>,
+    ready: bool,
+}
+
+impl ReadState {
+    /// The unfilled part of the buffer, where the next bytes from the reader are to be read.
+    pub(super) fn unfilled<C: OpeningCipher>(&mut self, cipher: &C) -> &mut [u8] {
+        if self.ready || (self.filled == 0 && self.len.is_none()) {
+            // Start reading the first block of a new packet
+            *self = Self {
+                buf: std::mem::take(&mut self.buf),
+                capacity: self.capacity,
+                ..Default::default()
+            };
+            self.buf.clear();
+            self.buf.resize(cipher::header_size(cipher), 0);
+        }
+
+        &mut self.buf[self.filled..]
+    }
+
+    /// Account for `read` bytes read from the reader, returning whether a whole packet has been opened.
+    pub(super) fn advance<C: OpeningCipher, O: PacketObserver>(
+        &mut self,
+        read: usize,
+        cipher: &mut C,
+        seq: &mut Seq,
+        observer: &mut O,
+    ) -> Result<bool, Error> {
+        let result = if read == 0 {
+            Err(Error::UnexpectedEof)
+        } else {
+            self.filled += read;
+            self.process(cipher, seq)
+        };
+
+        let capacity = self.buf.capacity();
+        if capacity > self.capacity {
+            observer.allocated(Direction::Inbound, capacity - self.capacity);
+            self.capacity = capacity;
+        }
+
+        match result {
+            Ok(true) => {
+                let len = self.len.unwrap_or_default() as usize;
+                observer.transferred(
+                    Direction::Inbound,
+                    std::mem::size_of::<u32>() + len + cipher.tag_size(),
+                );
+            }
+            Ok(false) => (),
+            Err(ref err) => {
+                observer.failed(Direction::Inbound, err);
+                *self = Self::default();
+            }
+        }
+
+        result
+    }
+
+    fn process<C: OpeningCipher>(&mut self, cipher: &mut C, seq: &mut Seq) -> Result<bool, Error> {
+        if self.filled < self.buf.len() {
+            return Ok(false);
+        }
+
+        if self.len.is_none() {
+            let len = Packet::open_header(&mut self.buf, cipher, seq.get())?;
+            let size = std::mem::size_of_val(&len) + len as usize + cipher.tag_size();
+
+            // Make room for the rest of the packet and it's MAC
+            self.buf.resize(size, 0);
+            self.len = Some(len);
+
+            if self.filled < self.buf.len() {
+                return Ok(false);
+            }
+        }
+
+        Packet::open_payload(&mut self.buf, cipher, seq.get())?;
+        seq.increment();
+        self.ready = true;
+
+        Ok(true)
+    }
+
+    /// The last opened [`Packet`].
+    pub(super) fn packet(&self) -> Packet<'_> {
+        Packet {
+            payload: arch::Bytes::borrowed(&self.buf),
+        }
+    }
+}
+
+/// A writer of [`Packet`](super::Packet)s, owning the underlying `writer`, the sealing `cipher` state,
+/// the sequence number and the random number generator used for padding,
+/// so they cannot be desynchronized between calls.
+///
+/// Packets are sent with `send`, `tokio_send` or `blocking_send` depending on the kind of the `writer`,
+/// sealing them in a buffer retained across calls.
+#[derive(Debug)]
+pub struct PacketWriter<W, C, G, O = ()> {
+    pub(super) writer: W,
+    pub(super) cipher: C,
+    pub(super) seq: Seq,
+    pub(super) rng: G,
+    pub(super) observer: O,
+    pub(super) buf: Vec<u8>,
+}
+
+impl<W, C: SealingCipher, G> PacketWriter<W, C, G> {
+    /// Create a new [`PacketWriter`] from the `writer`, sealing packets with the `cipher`
+    /// and padding them with random bytes from `rng`.
+    pub fn new(writer: W, cipher: C, rng: G) -> Self {
+        Self {
+            writer,
+            cipher,
+            seq: Seq::new(),
+            rng,
+            observer: (),
+            buf: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "rand")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+impl<W, C: SealingCipher> PacketWriter<W, C, rand_core::OsRng> {
+    /// Create a new [`PacketWriter`] from the `writer`, sealing packets with the `cipher`
+    /// and padding them with random bytes from the operating system.
+    pub fn with_os_rng(writer: W, cipher: C) -> Self {
+        Self::new(writer, cipher, rand_core::OsRng)
+    }
+}
+
+impl<W, C: SealingCipher, G, O: PacketObserver> PacketWriter<W, C, G, O> {
+    /// Set the `observer`, called with each [`Packet`](super::Packet) before it is sent.
+    pub fn with_observer<P: PacketObserver>(self, observer: P) -> PacketWriter<W, C, G, P> {
+        PacketWriter {
+            writer: self.writer,
+            cipher: self.cipher,
+            seq: self.seq,
+            rng: self.rng,
+            observer,
+            buf: self.buf,
+        }
+    }
+
+    /// Access the observer.
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// Mutably access the observer.
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Access the sealing cipher state.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Mutably access the sealing cipher state, e.g. to install new keys.
+    pub fn cipher_mut(&mut self) -> &mut C {
+        &mut self.cipher
+    }
+
+    /// The sequence number of the next [`Packet`](super::Packet) to be sent.
+    pub fn seq(&self) -> Seq {
+        self.seq
+    }
+
+    /// Reset the sequence number, after the `SSH_MSG_NEWKEYS` message in _strict key exchange_ mode.
+    pub fn reset_seq(&mut self) {
+        self.seq.reset();
+    }
+
+    /// Access the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Mutably access the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Consume the [`PacketWriter`], returning the underlying writer and the sealing cipher state.
+    pub fn into_inner(self) -> (W, C) {
+        (self.writer, self.cipher)
+    }
+}
+
+impl<W, C: SealingCipher, G: CryptoRngCore, O: PacketObserver> PacketWriter<W, C, G, O> {
+    /// Convert the `message` to a [`Packet`] and seal it in the retained buffer, to be written by the caller.
+    pub(super) fn seal_message<'b>(
+        &mut self,
+        message: impl TryIntoPacket<'b>,
+    ) -> Result<(), Error> {
+        let capacity = self.buf.capacity();
+
+        let result = message
+            .try_into_packet()
+            .map_err(Error::Encode)
+            .and_then(|packet| {
+                self.observer
+                    .observe(Direction::Outbound, self.seq.get(), &packet);
+
+                packet.seal_into(
+                    &mut self.cipher,
+                    self.seq.get(),
+                    &mut self.rng,
+                    &mut self.buf,
+                )
+            });
+
+        if self.buf.capacity() > capacity {
+            self.observer
+                .allocated(Direction::Outbound, self.buf.capacity() - capacity);
+        }
+
+        match result {
+            Ok(()) => self
+                .observer
+                .transferred(Direction::Outbound, self.buf.len()),
+            Err(ref err) => self.observer.failed(Direction::Outbound, err),
+        }
+
+        result
+    }
+}
@@ -0,0 +1,163 @@
+use rand_core::CryptoRngCore;
+
+use super::{
+    arch, cipher, Decode, Direction, Error, OpeningCipher, Packet, PacketObserver, PacketReader,
+    PacketWriter, SealingCipher, Seq, TryIntoPacket,
+};
+
+impl Packet<'_> {
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Read a [`Packet`], incrementing the `seq` sequence number, from the provided asynchronous `tokio` `reader`.
+    ///
+    /// Dropping the future mid-read loses the partially read bytes, see [`PacketReader`] for a cancel-safe alternative.
+    pub async fn from_tokio_reader<R, C>(
+        reader: &mut R,
+        cipher: &mut C,
+        seq: &mut Seq,
+    ) -> Result<Self, Error>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        C: OpeningCipher,
+    {
+        let mut buf = Vec::new();
+        Packet::from_tokio_reader_into(reader, cipher, seq, &mut buf).await?;
+
+        Ok(Self {
+            payload: buf.into(),
+        })
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Read a [`Packet`], incrementing the `seq` sequence number, from the provided asynchronous `tokio` `reader`,
+    /// reusing the provided `buf` to hold the payload instead of allocating a new buffer.
+    ///
+    /// Dropping the future mid-read loses the partially read bytes, see [`PacketReader`] for a cancel-safe alternative.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(seq = seq.get()))
+    )]
+    pub async fn from_tokio_reader_into<'a, R, C>(
+        reader: &mut R,
+        cipher: &mut C,
+        seq: &mut Seq,
+        buf: &'a mut Vec<u8>,
+    ) -> Result<Packet<'a>, Error>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        C: OpeningCipher,
+    {
+        use tokio::io::AsyncReadExt;
+
+        buf.clear();
+        buf.resize(cipher::header_size(cipher), 0);
+        reader.read_exact(&mut buf[..]).await?;
+
+        let len = Packet::open_header(buf, cipher, seq.get())?;
+
+        // Read the rest of the data and the MAC from the reader
+        buf.resize(
+            std::mem::size_of_val(&len) + len as usize + cipher.tag_size(),
+            0,
+        );
+        reader
+            .read_exact(&mut buf[cipher::header_size(cipher)..])
+            .await?;
+
+        Packet::open_payload(buf, cipher, seq.get())?;
+        seq.increment();
+
+        Ok(Packet {
+            payload: arch::Bytes::borrowed(buf),
+        })
+    }
+
+    /// Write the [`Packet`], padded with random bytes from `rng` and incrementing
+    /// the `seq` sequence number, to the provided asynchronous `tokio` `writer`.
+    ///
+    /// The sealed packet and it's MAC are emitted with a single `write_all`,
+    /// so that a packet is not fragmented across writes.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(seq = seq.get()))
+    )]
+    pub async fn to_tokio_writer<W, C>(
+        &self,
+        writer: &mut W,
+        cipher: &mut C,
+        seq: &mut Seq,
+        rng: &mut dyn CryptoRngCore,
+    ) -> Result<(), Error>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+        C: SealingCipher,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let buf = self.seal(cipher, seq.get(), rng)?;
+
+        writer.write_all(&buf).await?;
+        seq.increment();
+
+        Ok(())
+    }
+}
+
+impl<R, C, O> PacketReader<R, C, O>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    C: OpeningCipher,
+    O: PacketObserver,
+{
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Receive a [`Packet`] from the underlying asynchronous `tokio` reader, borrowing the internal buffer.
+    pub async fn tokio_recv_packet(&mut self) -> Result<Packet<'_>, Error> {
+        use tokio::io::AsyncReadExt;
+
+        loop {
+            let seq = self.seq.get();
+            let read = match self.reader.read(self.state.unfilled(&self.cipher)).await {
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                read => read?,
+            };
+
+            if self
+                .state
+                .advance(read, &mut self.cipher, &mut self.seq, &mut self.observer)?
+            {
+                let packet = self.state.packet();
+                self.observer.observe(Direction::Inbound, seq, &packet);
+
+                return Ok(packet);
+            }
+        }
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Receive a [`Packet`] from the underlying asynchronous `tokio` reader, and deserialize it into `T`.
+    pub async fn tokio_recv<T: Decode>(&mut self) -> Result<T, Error> {
+        let options = self.options;
+
+        Ok(self.tokio_recv_packet().await?.to_with(&options)?)
+    }
+}
+
+impl<W, C, G, O> PacketWriter<W, C, G, O>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+    C: SealingCipher,
+    G: CryptoRngCore,
+    O: PacketObserver,
+{
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Send the `message` as a [`Packet`] to the underlying asynchronous `tokio` writer.
+    pub async fn tokio_send<'b>(&mut self, message: impl TryIntoPacket<'b>) -> Result<(), Error> {
+        use tokio::io::AsyncWriteExt;
+
+        self.seal_message(message)?;
+
+        self.writer.write_all(&self.buf).await?;
+        self.seq.increment();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use binrw::{meta::WriteEndian, BinWrite};
+
+/// Allow types implementing [`BinWrite`] to compute their serialized size,
+/// to pre-allocate the exact buffer before serializing them.
+pub trait WireSize {
+    /// The size of the serialized representation, or `0` if the serialization fails.
+    fn wire_size_hint(&self) -> usize;
+}
+
+impl<T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian> WireSize for T {
+    fn wire_size_hint(&self) -> usize {
+        let mut counter = Counter::default();
+
+        match self.write(&mut counter) {
+            Ok(()) => counter.len as usize,
+            Err(_) => 0,
+        }
+    }
+}
+
+/// A writer discarding the data, only accounting for the size of what was written.
+#[derive(Debug, Default)]
+struct Counter {
+    pos: u64,
+    len: u64,
+}
+
+impl std::io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pos += buf.len() as u64;
+        self.len = self.len.max(self.pos);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::io::Seek for Counter {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            std::io::SeekFrom::Start(offset) => Some(offset),
+            std::io::SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            std::io::SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+
+        self.pos = pos.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{arch, connect, trans, TryIntoPacket};
+
+    #[rstest]
+    #[case(&trans::Ignore::default())]
+    #[case(&trans::ServiceRequest { service_name: arch::ascii!("ssh-userauth") })]
+    #[case(&connect::ChannelData { recipient_channel: 1, data: arch::Bytes::owned(vec![0x42; 1024]) })]
+    fn it_hints_exact_size<T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian>(#[case] message: &T) {
+        assert_eq!(
+            message.wire_size_hint(),
+            message.try_into_packet().unwrap().payload.len()
+        );
+    }
+}
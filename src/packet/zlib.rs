@@ -0,0 +1,219 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+
+use super::PACKET_MAX_SIZE;
+use crate::SizeError;
+
+/// The size of the output buffer extensions while (de)compressing.
+const CHUNK_SIZE: usize = 1024;
+
+/// A `zlib` compression stream, to be used in the [`SealingCipher::compress`](super::SealingCipher::compress) hook.
+///
+/// The stream state is kept across packets, and each packet is
+/// terminated with a partial flush, as required by the protocol.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.2>.
+#[derive(Debug)]
+pub struct ZlibCompressor {
+    inner: Compress,
+}
+
+impl Default for ZlibCompressor {
+    fn default() -> Self {
+        Self {
+            inner: Compress::new(Compression::default(), true),
+        }
+    }
+}
+
+impl ZlibCompressor {
+    /// Compress the `buf`, appending to `output` and flushing the stream at the end of the packet.
+    pub fn compress(&mut self, buf: &[u8], output: &mut Vec<u8>) -> std::io::Result<()> {
+        output.reserve(buf.len() + CHUNK_SIZE);
+        let start = self.inner.total_in();
+
+        loop {
+            let consumed = (self.inner.total_in() - start) as usize;
+
+            self.inner
+                .compress_vec(&buf[consumed..], output, FlushCompress::Partial)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+            // The flush is complete when all the input is consumed and the output has spare room
+            if (self.inner.total_in() - start) as usize == buf.len()
+                && output.len() < output.capacity()
+            {
+                break Ok(());
+            }
+
+            output.reserve(CHUNK_SIZE);
+        }
+    }
+}
+
+/// A `zlib` decompression stream, to be used in the [`OpeningCipher::decompress`](super::OpeningCipher::decompress) hook.
+///
+/// The stream state is kept across packets, and the decompressed
+/// size is bounded to prevent decompression bombs.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.2>.
+#[derive(Debug)]
+pub struct ZlibDecompressor {
+    inner: Decompress,
+    max_size: usize,
+    scratch: Vec<u8>,
+}
+
+impl Default for ZlibDecompressor {
+    fn default() -> Self {
+        Self::with_max_size(PACKET_MAX_SIZE)
+    }
+}
+
+impl ZlibDecompressor {
+    /// Create a [`ZlibDecompressor`] bounding the decompressed payloads to `max_size` bytes.
+    pub fn with_max_size(max_size: usize) -> Self {
+        Self {
+            inner: Decompress::new(true),
+            max_size,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Decompress the `buf` in place, swapping it with a scratch buffer retained across packets.
+    pub fn decompress_in_place(&mut self, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let mut output = std::mem::take(&mut self.scratch);
+        output.clear();
+
+        let result = self.decompress(buf, &mut output);
+        if result.is_ok() {
+            std::mem::swap(buf, &mut output);
+        }
+        self.scratch = output;
+
+        result
+    }
+
+    /// Decompress the `buf`, appending to `output`.
+    pub fn decompress(&mut self, buf: &[u8], output: &mut Vec<u8>) -> std::io::Result<()> {
+        output.reserve(buf.len() * 2 + CHUNK_SIZE);
+        let base = output.len();
+        let start = self.inner.total_in();
+
+        loop {
+            let consumed = (self.inner.total_in() - start) as usize;
+            let produced = output.len();
+
+            self.inner
+                .decompress_vec(&buf[consumed..], output, FlushDecompress::Sync)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+            if output.len() - base > self.max_size {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    SizeError {
+                        size: output.len() - base,
+                        max: self.max_size,
+                    },
+                ));
+            }
+
+            // Checked before stalls, as the previous call may have filled the output exactly
+            if (self.inner.total_in() - start) as usize == buf.len()
+                && output.len() < output.capacity()
+            {
+                break Ok(());
+            }
+
+            if (self.inner.total_in() - start) as usize == consumed
+                && output.len() == produced
+                && output.len() < output.capacity()
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Compressed stream stalled, trailing data after the end of stream",
+                ));
+            }
+
+            output.reserve(CHUNK_SIZE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(&[&b""[..]])]
+    #[case(&[&b"single packet"[..]])]
+    #[case(&[&b"first"[..], &b"second"[..], &[0x42; 16384][..], &b"first"[..]])]
+    fn it_roundtrips_across_packets(#[case] payloads: &[&[u8]]) {
+        let mut compressor = ZlibCompressor::default();
+        let mut decompressor = ZlibDecompressor::default();
+
+        let mut buf = Vec::new();
+        for payload in payloads {
+            buf.clear();
+            compressor.compress(payload, &mut buf).unwrap();
+            decompressor.decompress_in_place(&mut buf).unwrap();
+
+            assert_eq!(&buf, payload);
+        }
+    }
+
+    #[rstest]
+    #[case(1064)]
+    #[case(2132)]
+    fn it_decompresses_to_the_exact_capacity(#[case] size: usize) {
+        let mut compressor = ZlibCompressor::default();
+        let mut decompressor = ZlibDecompressor::default();
+
+        let mut compressed = Vec::new();
+        compressor
+            .compress(&vec![0; size], &mut compressed)
+            .unwrap();
+
+        let mut output = Vec::new();
+        decompressor.decompress(&compressed, &mut output).unwrap();
+        assert_eq!(output, vec![0; size]);
+    }
+
+    #[test]
+    fn it_rejects_oversized() {
+        let mut compressor = ZlibCompressor::default();
+        let mut decompressor = ZlibDecompressor::default();
+
+        let mut compressed = Vec::new();
+        compressor
+            .compress(&[0; PACKET_MAX_SIZE + 1], &mut compressed)
+            .unwrap();
+
+        let err = decompressor
+            .decompress(&compressed, &mut Vec::new())
+            .unwrap_err();
+        let err = err
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<SizeError>())
+            .unwrap();
+        assert_eq!(err.max, PACKET_MAX_SIZE);
+    }
+
+    #[test]
+    fn it_rejects_trailing_data() {
+        let mut compressor = Compress::new(Compression::default(), true);
+        let mut decompressor = ZlibDecompressor::default();
+
+        let mut compressed = Vec::with_capacity(CHUNK_SIZE);
+        compressor
+            .compress_vec(b"finished", &mut compressed, FlushCompress::Finish)
+            .unwrap();
+        compressed.extend_from_slice(b"trailing");
+
+        decompressor
+            .decompress(&compressed, &mut Vec::new())
+            .unwrap_err();
+    }
+}
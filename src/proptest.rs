@@ -0,0 +1,154 @@
+//! [`proptest`](https://docs.rs/proptest) strategies for the messages of the protocol,
+//! and helpers to property-test their encode/decode symmetry.
+
+use ::proptest::{
+    collection::vec,
+    prelude::{any, Strategy},
+    test_runner::{TestCaseError, TestRunner},
+};
+use binrw::{meta::WriteEndian, BinWrite};
+
+use crate::{
+    connect::{
+        ChannelClose, ChannelData, ChannelEof, ChannelExtendedData, ChannelFailure, ChannelOpen,
+        ChannelOpenConfirmation, ChannelOpenFailure, ChannelRequest, ChannelSuccess,
+        ChannelWindowAdjust, ForwardingSuccess, GlobalRequest, RequestFailure, RequestSuccess,
+    },
+    trans::{
+        Debug, Disconnect, Ignore, KexEcdhInit, KexEcdhReply, KexInit, KexdhInit, KexdhReply,
+        NewKeys, ServiceAccept, ServiceRequest, Unimplemented,
+    },
+    userauth::{
+        Banner, Failure, InfoRequest, InfoResponse, PasswdChangereq, PkOk, Request, Success,
+    },
+    Decode, IntoOwned, TryIntoPacket,
+};
+
+/// The maximum size of the random input each message is generated from.
+const INPUT_MAX_SIZE: usize = 1024;
+
+/// A message which can be generated from unstructured random input as an owned value.
+///
+/// Messages are generated with their [`arbitrary::Arbitrary`] implementation,
+/// and then converted with [`IntoOwned`] to be detached from the input's lifetime.
+pub trait Generate: Sized + std::fmt::Debug {
+    /// Generate a message from the unstructured input in `u`.
+    fn generate(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<Self>;
+}
+
+macro_rules! generate {
+    ($($ty:ident),* $(,)?; $($lt:ident),* $(,)?) => {
+        $(
+            impl Generate for $ty {
+                fn generate(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<Self> {
+                    u.arbitrary()
+                }
+            }
+        )*
+        $(
+            impl Generate for $lt<'static> {
+                fn generate(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<Self> {
+                    let message: $lt<'_> = u.arbitrary()?;
+
+                    Ok(message.into_owned())
+                }
+            }
+        )*
+    };
+}
+
+generate!(
+    Unimplemented, NewKeys, InfoResponse, Success, RequestSuccess, ForwardingSuccess,
+    RequestFailure, ChannelOpenConfirmation, ChannelWindowAdjust, ChannelEof, ChannelClose,
+    ChannelSuccess, ChannelFailure;
+    Disconnect, Ignore, Debug, ServiceRequest, ServiceAccept, KexInit, KexdhInit, KexdhReply,
+    KexEcdhInit, KexEcdhReply, Request, PkOk, PasswdChangereq, InfoRequest, Failure, Banner,
+    GlobalRequest, ChannelOpen, ChannelOpenFailure, ChannelData, ChannelExtendedData,
+    ChannelRequest,
+);
+
+/// A [`Strategy`] generating arbitrary messages of type `T`.
+pub fn strategy<T: Generate>() -> impl Strategy<Value = T> {
+    vec(any::<u8>(), 0..=INPUT_MAX_SIZE).prop_filter_map("unable to generate a message", |input| {
+        T::generate(&mut arbitrary::Unstructured::new(&input)).ok()
+    })
+}
+
+/// Property-test that arbitrary messages of type `T` are decoded
+/// from their encoded form into the same messages, with the same encoding.
+///
+/// # Panics
+///
+/// This function panics with the minimal failing message if the property doesn't hold.
+#[allow(clippy::panic)]
+pub fn assert_roundtrip<T>()
+where
+    T: Generate + Decode + for<'a> BinWrite<Args<'a> = ()> + WriteEndian + PartialEq,
+{
+    let result = TestRunner::default().run(&strategy::<T>(), |message| {
+        let packet = (&message)
+            .try_into_packet()
+            .map_err(|err| TestCaseError::fail(err.to_string()))?;
+        let decoded: T = packet
+            .to()
+            .map_err(|err| TestCaseError::fail(err.to_string()))?;
+        let reencoded = (&decoded)
+            .try_into_packet()
+            .map_err(|err| TestCaseError::fail(err.to_string()))?;
+
+        ::proptest::prop_assert_eq!(decoded, message);
+        ::proptest::prop_assert_eq!(reencoded, packet);
+
+        Ok(())
+    });
+
+    if let Err(err) = result {
+        panic!("{err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use super::*;
+
+    #[test]
+    fn it_roundtrips_messages() {
+        assert_roundtrip::<Unimplemented>();
+        assert_roundtrip::<NewKeys>();
+        assert_roundtrip::<InfoResponse>();
+        assert_roundtrip::<Success>();
+        assert_roundtrip::<RequestSuccess>();
+        assert_roundtrip::<ForwardingSuccess>();
+        assert_roundtrip::<RequestFailure>();
+        assert_roundtrip::<ChannelOpenConfirmation>();
+        assert_roundtrip::<ChannelWindowAdjust>();
+        assert_roundtrip::<ChannelEof>();
+        assert_roundtrip::<ChannelClose>();
+        assert_roundtrip::<ChannelSuccess>();
+        assert_roundtrip::<ChannelFailure>();
+        assert_roundtrip::<Disconnect>();
+        assert_roundtrip::<Ignore>();
+        assert_roundtrip::<Debug>();
+        assert_roundtrip::<ServiceRequest>();
+        assert_roundtrip::<ServiceAccept>();
+        assert_roundtrip::<KexInit>();
+        assert_roundtrip::<KexdhInit>();
+        assert_roundtrip::<KexdhReply>();
+        assert_roundtrip::<KexEcdhInit>();
+        assert_roundtrip::<KexEcdhReply>();
+        assert_roundtrip::<Request>();
+        assert_roundtrip::<PkOk>();
+        assert_roundtrip::<PasswdChangereq>();
+        assert_roundtrip::<InfoRequest>();
+        assert_roundtrip::<Failure>();
+        assert_roundtrip::<Banner>();
+        assert_roundtrip::<GlobalRequest>();
+        assert_roundtrip::<ChannelOpen>();
+        assert_roundtrip::<ChannelOpenFailure>();
+        assert_roundtrip::<ChannelData>();
+        assert_roundtrip::<ChannelExtendedData>();
+        assert_roundtrip::<ChannelRequest>();
+    }
+}
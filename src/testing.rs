@@ -0,0 +1,140 @@
+//! Byte-exact round-trip assertions of the messages against their expected wire encoding,
+//! to cheaply write regression tests for the messages of this crate or user-defined ones.
+
+use binrw::{meta::WriteEndian, BinWrite};
+
+use crate::{Decode, TryIntoPacket};
+
+/// Assert that the `message` is encoded as the `expected` hexadecimal payload,
+/// and that this payload decodes back into the same `message`.
+///
+/// The `expected` payload may contain whitespace to group bytes,
+/// and includes the message number; see [`assert_wire_roundtrip!`](crate::assert_wire_roundtrip).
+///
+/// # Panics
+///
+/// This function panics if the `expected` payload isn't valid hexadecimal,
+/// or if the `message` fails to roundtrip to and from it.
+#[track_caller]
+#[allow(clippy::panic)]
+pub fn assert_wire_roundtrip<T>(message: &T, expected: &str)
+where
+    T: Decode + for<'a> BinWrite<Args<'a> = ()> + WriteEndian + PartialEq + std::fmt::Debug,
+{
+    let expected = match decode_hex(expected) {
+        Some(expected) => expected,
+        None => panic!("the expected payload `{expected}` isn't valid hexadecimal"),
+    };
+
+    let packet = match message.try_into_packet() {
+        Ok(packet) => packet,
+        Err(err) => panic!("unable to encode {message:?}: {err}"),
+    };
+    if *packet.payload != *expected {
+        panic!(
+            "{message:?} was encoded as\n  {}\ninstead of\n  {}",
+            encode_hex(&packet.payload),
+            encode_hex(&expected)
+        );
+    }
+
+    match packet.to::<T>() {
+        Ok(decoded) if decoded == *message => (),
+        Ok(decoded) => panic!("{message:?} was decoded back as {decoded:?}"),
+        Err(err) => panic!("unable to decode {message:?} back: {err}"),
+    }
+}
+
+/// Assert that a message is encoded as the expected hexadecimal payload,
+/// and that this payload decodes back into the same message.
+///
+/// ```
+/// use ssh_packet::{assert_wire_roundtrip, connect};
+///
+/// assert_wire_roundtrip!(
+///     connect::ChannelClose { recipient_channel: 7 },
+///     "61 00000007"
+/// );
+/// ```
+///
+/// see [`testing::assert_wire_roundtrip`](crate::testing::assert_wire_roundtrip).
+#[macro_export]
+macro_rules! assert_wire_roundtrip {
+    ($message:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_wire_roundtrip(&$message, $expected)
+    };
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let digits = hex
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<_>>>()?;
+
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+
+    Some(
+        digits
+            .chunks_exact(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect(),
+    )
+}
+
+fn encode_hex(buf: &[u8]) -> String {
+    use std::fmt::Write;
+
+    buf.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use rstest::rstest;
+
+    use crate::{arch, connect, trans, userauth};
+
+    #[test]
+    fn it_roundtrips_on_the_wire() {
+        assert_wire_roundtrip!(trans::NewKeys, "15");
+        assert_wire_roundtrip!(
+            trans::Ignore {
+                data: arch::Bytes::borrowed(&[0xde, 0xad])
+            },
+            "02 00000002 dead"
+        );
+        assert_wire_roundtrip!(userauth::Banner::new("hi"), "35 00000002 6869 00000000");
+        assert_wire_roundtrip!(
+            connect::ChannelRequest::exec(1, "ls").want_reply(true),
+            "62 00000001 00000004 65786563 01 00000002 6c73"
+        );
+        assert_wire_roundtrip!(
+            connect::ChannelWindowAdjust {
+                recipient_channel: 0,
+                bytes_to_add: 0x0020_0000,
+            },
+            "5D 00000000 00200000",
+        );
+    }
+
+    #[rstest]
+    #[case("62 00000001")]
+    #[case("61 0000007")]
+    #[case("61 0000000g")]
+    #[should_panic]
+    fn it_panics_on_mismatches(#[case] expected: &str) {
+        assert_wire_roundtrip!(
+            connect::ChannelClose {
+                recipient_channel: 7
+            },
+            expected
+        );
+    }
+}
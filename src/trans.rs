@@ -2,30 +2,61 @@
 //! as defined in the [RFC 4253](https://datatracker.ietf.org/doc/html/rfc4253)
 //! and [RFC 5656](https://datatracker.ietf.org/doc/html/rfc5656).
 
-use binrw::binrw;
+use binrw::{binrw, meta::WriteEndian, BinWrite};
 
-use crate::arch;
+use crate::{arch, message, owned, DecodeError, IntoOwned, Packet};
 
 /// The `SSH_MSG_DISCONNECT` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.1>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 1_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct Disconnect<'b> {
     /// Reason for disconnection.
     pub reason: DisconnectReason,
 
     /// Description of the reason for disconnection.
+    #[br(args_raw(options))]
     pub description: arch::Utf8<'b>,
 
     /// Language tag.
+    #[br(args_raw(options))]
     pub language: arch::Ascii<'b>,
 }
 
+impl<'b> Disconnect<'b> {
+    /// Create a [`Disconnect`] message for the `reason`, with a human-readable `description`
+    /// and an empty language tag.
+    pub fn new(reason: DisconnectReason, description: impl Into<arch::Utf8<'b>>) -> Self {
+        Self {
+            reason,
+            description: description.into(),
+            language: Default::default(),
+        }
+    }
+
+    /// Create a [`Disconnect`] message for [`DisconnectReason::ByApplication`], with the `description`.
+    pub fn by_application(description: impl Into<arch::Utf8<'b>>) -> Self {
+        Self::new(DisconnectReason::ByApplication, description)
+    }
+
+    /// Create a [`Disconnect`] message for [`DisconnectReason::ProtocolError`], with the `description`.
+    pub fn protocol_error(description: impl Into<arch::Utf8<'b>>) -> Self {
+        Self::new(DisconnectReason::ProtocolError, description)
+    }
+}
+
 /// The `reason` for disconnect in the `SSH_MSG_DISCONNECT` message.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big)]
 pub enum DisconnectReason {
     /// `SSH_DISCONNECT_HOST_NOT_ALLOWED_TO_CONNECT`.
@@ -95,22 +126,179 @@ pub enum DisconnectReason {
     Other(u32),
 }
 
+impl DisconnectReason {
+    /// The numeric code of the reason, as encoded in the message.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::HostNotAllowedToConnect => 1,
+            Self::ProtocolError => 2,
+            Self::KeyExchangeFailed => 3,
+            Self::Reserved => 4,
+            Self::MacError => 5,
+            Self::CompressionError => 6,
+            Self::ServiceNotAvailable => 7,
+            Self::ProtocolVersionNotSupported => 8,
+            Self::HostKeyNotVerifiable => 9,
+            Self::ConnectionLost => 10,
+            Self::ByApplication => 11,
+            Self::TooManyConnections => 12,
+            Self::AuthCancelledByUser => 13,
+            Self::NoMoreAuthMethodsAvailable => 14,
+            Self::IllegalUserName => 15,
+            Self::Other(code) => *code,
+        }
+    }
+
+    /// The name of the reason's constant, or [`None`] for [`DisconnectReason::Other`].
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::HostNotAllowedToConnect => "SSH_DISCONNECT_HOST_NOT_ALLOWED_TO_CONNECT",
+            Self::ProtocolError => "SSH_DISCONNECT_PROTOCOL_ERROR",
+            Self::KeyExchangeFailed => "SSH_DISCONNECT_KEY_EXCHANGE_FAILED",
+            Self::Reserved => "SSH_DISCONNECT_RESERVED",
+            Self::MacError => "SSH_DISCONNECT_MAC_ERROR",
+            Self::CompressionError => "SSH_DISCONNECT_COMPRESSION_ERROR",
+            Self::ServiceNotAvailable => "SSH_DISCONNECT_SERVICE_NOT_AVAILABLE",
+            Self::ProtocolVersionNotSupported => "SSH_DISCONNECT_PROTOCOL_VERSION_NOT_SUPPORTED",
+            Self::HostKeyNotVerifiable => "SSH_DISCONNECT_HOST_KEY_NOT_VERIFIABLE",
+            Self::ConnectionLost => "SSH_DISCONNECT_CONNECTION_LOST",
+            Self::ByApplication => "SSH_DISCONNECT_BY_APPLICATION",
+            Self::TooManyConnections => "SSH_DISCONNECT_TOO_MANY_CONNECTIONS",
+            Self::AuthCancelledByUser => "SSH_DISCONNECT_AUTH_CANCELLED_BY_USER",
+            Self::NoMoreAuthMethodsAvailable => "SSH_DISCONNECT_NO_MORE_AUTH_METHODS_AVAILABLE",
+            Self::IllegalUserName => "SSH_DISCONNECT_ILLEGAL_USER_NAME",
+            Self::Other(_) => return None,
+        })
+    }
+
+    /// Whether the reason is in the `0xFE000000` through `0xFFFFFFFF` range reserved for PRIVATE USE.
+    pub fn is_private_use(&self) -> bool {
+        self.code() >= 0xFE000000
+    }
+}
+
+impl std::fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name} ({})", self.code()),
+            None if self.is_private_use() => write!(f, "private use reason {:#010x}", self.code()),
+            None => write!(f, "unknown reason {}", self.code()),
+        }
+    }
+}
+
 /// The `SSH_MSG_IGNORE` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.2>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 2_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct Ignore<'b> {
     /// A random blob of data to ignore.
+    #[br(args_raw(options))]
     pub data: arch::Bytes<'b>,
 }
 
+impl Ignore<'_> {
+    #[cfg(feature = "rand")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+    /// Create an [`Ignore`] message carrying `size` random bytes from the operating system.
+    pub fn random(size: usize) -> Ignore<'static> {
+        Ignore::random_with_rng(size, &mut rand_core::OsRng)
+    }
+
+    /// Create an [`Ignore`] message carrying `size` random bytes from `rng`.
+    pub fn random_with_rng(
+        size: usize,
+        rng: &mut impl rand_core::CryptoRngCore,
+    ) -> Ignore<'static> {
+        let mut data = vec![0; size];
+        rng.fill_bytes(&mut data);
+
+        Ignore {
+            data: arch::Bytes::owned(data),
+        }
+    }
+}
+
+/// A generator of [`Ignore`] messages of random sizes drawn from the `sizes` distribution,
+/// to obfuscate the timing of keystrokes or pad the traffic, in interactive sessions.
+///
+/// The generator is an endless [`Iterator`], and the payloads are random bytes from the `rng`.
+#[derive(Debug)]
+pub struct IgnoreGenerator<G, D> {
+    rng: G,
+    sizes: D,
+}
+
+impl<G, D> IgnoreGenerator<G, D>
+where
+    G: rand_core::CryptoRngCore,
+    D: FnMut(&mut G) -> usize,
+{
+    /// Create an [`IgnoreGenerator`] drawing the sizes of the payloads from `sizes` with the `rng`.
+    pub fn new(rng: G, sizes: D) -> Self {
+        Self { rng, sizes }
+    }
+
+    /// Generate an [`Ignore`] message of a random size.
+    pub fn generate(&mut self) -> Ignore<'static> {
+        let size = (self.sizes)(&mut self.rng);
+
+        Ignore::random_with_rng(size, &mut self.rng)
+    }
+}
+
+impl<G: rand_core::CryptoRngCore> IgnoreGenerator<G, fn(&mut G) -> usize> {
+    /// Create an [`IgnoreGenerator`] drawing the sizes of the payloads uniformly in `sizes` with the `rng`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `sizes` range is empty.
+    pub fn uniform(
+        rng: G,
+        sizes: std::ops::RangeInclusive<usize>,
+    ) -> IgnoreGenerator<G, impl FnMut(&mut G) -> usize> {
+        let (start, end) = sizes.into_inner();
+        let span = end
+            .checked_sub(start)
+            .map(|span| u128::from(span as u64) + 1)
+            .expect("The range of sizes is empty");
+
+        IgnoreGenerator::new(rng, move |rng: &mut G| {
+            // Map the random value onto the span by multiplication rather than
+            // modulo, which keeps the bias negligible for any reasonable span.
+            let offset = (u128::from(rng.next_u64()) * span) >> 64;
+
+            start + offset as usize
+        })
+    }
+}
+
+impl<G, D> Iterator for IgnoreGenerator<G, D>
+where
+    G: rand_core::CryptoRngCore,
+    D: FnMut(&mut G) -> usize,
+{
+    type Item = Ignore<'static>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.generate())
+    }
+}
+
 /// The `SSH_MSG_UNIMPLEMENTED` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 3_u8)]
 pub struct Unimplemented {
     /// Packet sequence number of rejected message.
@@ -121,27 +309,58 @@ pub struct Unimplemented {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.3>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 4_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct Debug<'b> {
     /// Whether the debug data should be forcefully displayed.
     pub always_display: arch::Bool,
 
     /// The debug message.
+    #[br(args_raw(options))]
     pub message: arch::Utf8<'b>,
 
     /// Language tag.
+    #[br(args_raw(options))]
     pub language: arch::Ascii<'b>,
 }
 
+impl<'b> Debug<'b> {
+    /// Create a [`Debug`](struct@Debug) message to be forcefully displayed, with an empty language tag.
+    pub fn display(message: impl Into<arch::Utf8<'b>>) -> Self {
+        Self {
+            always_display: true.into(),
+            message: message.into(),
+            language: Default::default(),
+        }
+    }
+
+    /// Create a [`Debug`](struct@Debug) message to be displayed only on demand, with an empty language tag.
+    pub fn hidden(message: impl Into<arch::Utf8<'b>>) -> Self {
+        Self {
+            always_display: false.into(),
+            message: message.into(),
+            language: Default::default(),
+        }
+    }
+}
+
 /// The `SSH_MSG_SERVICE_REQUEST` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-10>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 5_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct ServiceRequest<'b> {
     /// The service name to request.
+    #[br(args_raw(options))]
     pub service_name: arch::Ascii<'b>,
 }
 
@@ -149,10 +368,15 @@ pub struct ServiceRequest<'b> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-10>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 6_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct ServiceAccept<'b> {
     /// Service name accepted to be requested.
+    #[br(args_raw(options))]
     pub service_name: arch::Ascii<'b>,
 }
 
@@ -160,40 +384,54 @@ pub struct ServiceAccept<'b> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.1>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 20_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct KexInit<'b> {
     /// The kex-init cookie.
     pub cookie: [u8; 16],
 
     /// Kex algorithms.
+    #[br(args_raw(options))]
     pub kex_algorithms: arch::NameList<'b>,
 
     /// Server host-key algorithms.
+    #[br(args_raw(options))]
     pub server_host_key_algorithms: arch::NameList<'b>,
 
     /// Client -> server encryption algorithms.
+    #[br(args_raw(options))]
     pub encryption_algorithms_client_to_server: arch::NameList<'b>,
 
     /// Server -> client encryption algorithms.
+    #[br(args_raw(options))]
     pub encryption_algorithms_server_to_client: arch::NameList<'b>,
 
     /// Client -> server MAC algorithms.
+    #[br(args_raw(options))]
     pub mac_algorithms_client_to_server: arch::NameList<'b>,
 
     /// Server -> client MAC algorithms.
+    #[br(args_raw(options))]
     pub mac_algorithms_server_to_client: arch::NameList<'b>,
 
     /// Client -> server compression algorithms.
+    #[br(args_raw(options))]
     pub compression_algorithms_client_to_server: arch::NameList<'b>,
 
     /// Server -> client compression algorithms.
+    #[br(args_raw(options))]
     pub compression_algorithms_server_to_client: arch::NameList<'b>,
 
     /// Client -> server languages.
+    #[br(args_raw(options))]
     pub languages_client_to_server: arch::NameList<'b>,
 
     /// Server -> client languages.
+    #[br(args_raw(options))]
     pub languages_server_to_client: arch::NameList<'b>,
 
     /// Whether the first kex packet follows.
@@ -203,11 +441,31 @@ pub struct KexInit<'b> {
     _reserved: u32,
 }
 
+impl KexInit<'_> {
+    #[cfg(feature = "rand")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+    /// Generate a random [`KexInit::cookie`] from the operating system.
+    pub fn random_cookie() -> [u8; 16] {
+        Self::random_cookie_with_rng(&mut rand_core::OsRng)
+    }
+
+    /// Generate a random [`KexInit::cookie`] from `rng`.
+    pub fn random_cookie_with_rng(rng: &mut impl rand_core::CryptoRngCore) -> [u8; 16] {
+        let mut cookie = [0; 16];
+        rng.fill_bytes(&mut cookie);
+
+        cookie
+    }
+}
+
 /// The `SSH_MSG_NEWKEYS` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.3>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 21_u8)]
 pub struct NewKeys;
 
@@ -215,10 +473,15 @@ pub struct NewKeys;
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-8>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 30_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct KexdhInit<'b> {
     /// Exchange value sent by the client.
+    #[br(args_raw(options))]
     pub e: arch::MpInt<'b>,
 }
 
@@ -226,27 +489,63 @@ pub struct KexdhInit<'b> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-8>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 31_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct KexdhReply<'b> {
     /// Server's public host key.
+    #[br(args_raw(options))]
     pub k_s: arch::Bytes<'b>,
 
     /// Exchange value sent by the server.
+    #[br(args_raw(options))]
     pub f: arch::MpInt<'b>,
 
     /// Signature of the exchange hash.
+    #[br(args_raw(options))]
     pub signature: arch::Bytes<'b>,
 }
 
+/// The signature is redacted.
+impl std::fmt::Debug for KexdhReply<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KexdhReply")
+            .field("k_s", &self.k_s)
+            .field("f", &self.f)
+            .field("signature", &arch::Redacted(&self.signature))
+            .finish()
+    }
+}
+
+/// The signature is redacted.
+#[cfg(feature = "defmt")]
+impl defmt::Format for KexdhReply<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "KexdhReply {{ k_s: {}, f: {}, signature: {} }}",
+            self.k_s,
+            self.f,
+            arch::Redacted(&self.signature),
+        )
+    }
+}
+
 /// The `SSH_MSG_KEX_ECDH_INIT` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc5656#section-4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 30_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct KexEcdhInit<'b> {
     /// Client's ephemeral public key octet string.
+    #[br(args_raw(options))]
     pub q_c: arch::Bytes<'b>,
 }
 
@@ -254,15 +553,547 @@ pub struct KexEcdhInit<'b> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc5656#section-4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 31_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct KexEcdhReply<'b> {
     /// Server's public host key.
+    #[br(args_raw(options))]
     pub k_s: arch::Bytes<'b>,
 
     /// Server's ephemeral public key octet string.
+    #[br(args_raw(options))]
     pub q_s: arch::Bytes<'b>,
 
     /// Signature of the exchange hash.
+    #[br(args_raw(options))]
     pub signature: arch::Bytes<'b>,
 }
+
+/// The signature is redacted.
+impl std::fmt::Debug for KexEcdhReply<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KexEcdhReply")
+            .field("k_s", &self.k_s)
+            .field("q_s", &self.q_s)
+            .field("signature", &arch::Redacted(&self.signature))
+            .finish()
+    }
+}
+
+/// The signature is redacted.
+#[cfg(feature = "defmt")]
+impl defmt::Format for KexEcdhReply<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "KexEcdhReply {{ k_s: {}, q_s: {}, signature: {} }}",
+            self.k_s,
+            self.q_s,
+            arch::Redacted(&self.signature),
+        )
+    }
+}
+
+impl KexEcdhReply<'_> {
+    /// Verify the reply's signature of the exchange hash with the server's host `key`,
+    /// recomputing the hash of the `exchange` with the `D` digest algorithm.
+    ///
+    /// The `exchange` is first checked to carry the host key and ephemeral public key of this reply,
+    /// and the signature is decoded from it's `string` algorithm name and `string` blob encoding,
+    /// whose algorithm name must be the expected signature `algorithm` of the negotiated host key algorithm,
+    /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.6> and
+    /// <https://datatracker.ietf.org/doc/html/rfc8332#section-3>.
+    ///
+    /// On success, the verified exchange hash is returned, which is also the _session identifier_
+    /// for the first key exchange.
+    #[cfg(all(feature = "digest", feature = "signature"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "digest", feature = "signature"))))]
+    pub fn verify_host_signature<D, S, K>(
+        &self,
+        exchange: &crate::crypto::exchange::Ecdh<'_>,
+        algorithm: &str,
+        key: &K,
+    ) -> signature::Result<digest::Output<D>>
+    where
+        D: digest::Digest,
+        S: for<'s> TryFrom<&'s [u8], Error = signature::Error>,
+        K: signature::Verifier<S>,
+    {
+        if *exchange.k_s != *self.k_s || *exchange.q_s != *self.q_s {
+            return Err(signature::Error::new());
+        }
+
+        let blob = crate::crypto::signature::SignatureBlob::decode(&self.signature)?;
+        if *blob.algorithm != *algorithm {
+            return Err(signature::Error::new());
+        }
+
+        let hash = exchange.hash::<D>();
+        blob.verify(&hash, key)?;
+
+        Ok(hash)
+    }
+}
+
+/// The family of a key-exchange method, defining the messages it exchanges
+/// under the `30` and `31` message numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum KexFamily {
+    /// The _Diffie-Hellman_ methods on fixed groups, exchanging [`KexdhInit`] and [`KexdhReply`].
+    Dh,
+
+    /// The _Elliptic Curve Diffie-Hellman_ methods and their hybrid post-quantum variants,
+    /// exchanging [`KexEcdhInit`] and [`KexEcdhReply`].
+    Ecdh,
+}
+
+impl KexFamily {
+    /// The family of the negotiated key-exchange `algorithm`, or [`None`] if it is unknown
+    /// or uses other messages, such as the `diffie-hellman-group-exchange-*` methods.
+    pub fn from_algorithm(algorithm: &str) -> Option<Self> {
+        if algorithm.starts_with("diffie-hellman-group-exchange-") {
+            None
+        } else if algorithm.starts_with("diffie-hellman-group") {
+            Some(Self::Dh)
+        } else if algorithm.starts_with("ecdh-sha2-")
+            || algorithm.starts_with("curve25519-")
+            || algorithm.starts_with("curve448-")
+            || algorithm.starts_with("sntrup761x25519-")
+            || algorithm.starts_with("mlkem768x25519-")
+        {
+            Some(Self::Ecdh)
+        } else {
+            None
+        }
+    }
+}
+
+/// The key-exchange method specific messages sharing the `30` and `31` message numbers,
+/// decoded according to the negotiated [`KexFamily`].
+///
+/// Since both families encode their exchange values as length-prefixed fields,
+/// decoding one's messages as the other's would succeed while misinterpreting them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum KexMessage<'b> {
+    /// The `SSH_MSG_KEXDH_INIT` message.
+    KexdhInit(KexdhInit<'b>),
+
+    /// The `SSH_MSG_KEXDH_REPLY` message.
+    KexdhReply(KexdhReply<'b>),
+
+    /// The `SSH_MSG_KEX_ECDH_INIT` message.
+    KexEcdhInit(KexEcdhInit<'b>),
+
+    /// The `SSH_MSG_KEX_ECDH_REPLY` message.
+    KexEcdhReply(KexEcdhReply<'b>),
+}
+
+impl KexMessage<'_> {
+    /// Decode the [`KexMessage`] in the `packet` by dispatching on it's message number
+    /// and the negotiated key-exchange `family`.
+    pub fn from_packet(packet: &Packet<'_>, family: KexFamily) -> Result<Self, DecodeError> {
+        match (family, packet.payload.first()) {
+            (KexFamily::Dh, Some(30)) => packet.to().map(Self::KexdhInit),
+            (KexFamily::Dh, Some(31)) => packet.to().map(Self::KexdhReply),
+            (KexFamily::Ecdh, Some(30)) => packet.to().map(Self::KexEcdhInit),
+            (KexFamily::Ecdh, Some(31)) => packet.to().map(Self::KexEcdhReply),
+            _ => Err(message::unexpected(packet)),
+        }
+    }
+}
+
+impl BinWrite for KexMessage<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> binrw::BinResult<()> {
+        match self {
+            Self::KexdhInit(message) => message.write_options(writer, endian, args),
+            Self::KexdhReply(message) => message.write_options(writer, endian, args),
+            Self::KexEcdhInit(message) => message.write_options(writer, endian, args),
+            Self::KexEcdhReply(message) => message.write_options(writer, endian, args),
+        }
+    }
+}
+
+impl WriteEndian for KexMessage<'_> {
+    const ENDIAN: binrw::meta::EndianKind = binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+}
+
+impl IntoOwned for KexMessage<'_> {
+    type Owned = KexMessage<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::KexdhInit(message) => KexMessage::KexdhInit(message.into_owned()),
+            Self::KexdhReply(message) => KexMessage::KexdhReply(message.into_owned()),
+            Self::KexEcdhInit(message) => KexMessage::KexEcdhInit(message.into_owned()),
+            Self::KexEcdhReply(message) => KexMessage::KexEcdhReply(message.into_owned()),
+        }
+    }
+}
+
+/// Any of the messages of the **transport** (`SSH-TRANS`) part of the protocol,
+/// decoded from a [`Packet`] by dispatching on it's message number.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Message<'b> {
+    /// The `SSH_MSG_DISCONNECT` message.
+    Disconnect(Disconnect<'b>),
+
+    /// The `SSH_MSG_IGNORE` message.
+    Ignore(Ignore<'b>),
+
+    /// The `SSH_MSG_UNIMPLEMENTED` message.
+    Unimplemented(Unimplemented),
+
+    /// The `SSH_MSG_DEBUG` message.
+    Debug(Debug<'b>),
+
+    /// The `SSH_MSG_SERVICE_REQUEST` message.
+    ServiceRequest(ServiceRequest<'b>),
+
+    /// The `SSH_MSG_SERVICE_ACCEPT` message.
+    ServiceAccept(ServiceAccept<'b>),
+
+    /// The `SSH_MSG_KEXINIT` message.
+    KexInit(Box<KexInit<'b>>),
+
+    /// The `SSH_MSG_NEWKEYS` message.
+    NewKeys(NewKeys),
+
+    /// A key-exchange method specific message (`30` to `49`), such as
+    /// [`KexdhInit`] or [`KexEcdhInit`], depending on the negotiated method,
+    /// see [`KexMessage::from_packet`].
+    KexSpecific(Packet<'b>),
+}
+
+impl<'b> Message<'b> {
+    /// Decode the [`Message`] in the `packet` by dispatching on it's message number,
+    /// failing if it is not a known message of this part of the protocol.
+    pub fn from_packet(packet: &'b Packet<'_>) -> Result<Self, DecodeError> {
+        Self::dispatch(packet, &Default::default())
+            .unwrap_or_else(|| Err(message::unexpected(packet)))
+    }
+
+    pub(crate) fn dispatch(
+        packet: &'b Packet<'_>,
+        options: &crate::DecodeOptions,
+    ) -> Option<Result<Self, DecodeError>> {
+        Some(match packet.payload.first()? {
+            1 => packet.to_with(options).map(Self::Disconnect),
+            2 => packet.to_with(options).map(Self::Ignore),
+            3 => packet.to_with(options).map(Self::Unimplemented),
+            4 => packet.to_with(options).map(Self::Debug),
+            5 => packet.to_with(options).map(Self::ServiceRequest),
+            6 => packet.to_with(options).map(Self::ServiceAccept),
+            20 => packet.to_with(options).map(Box::new).map(Self::KexInit),
+            21 => packet.to_with(options).map(Self::NewKeys),
+            30..=49 => Ok(Self::KexSpecific(packet.as_borrow())),
+            _ => return None,
+        })
+    }
+}
+
+impl BinWrite for Message<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> binrw::BinResult<()> {
+        match self {
+            Self::Disconnect(message) => message.write_options(writer, endian, args),
+            Self::Ignore(message) => message.write_options(writer, endian, args),
+            Self::Unimplemented(message) => message.write_options(writer, endian, args),
+            Self::Debug(message) => message.write_options(writer, endian, args),
+            Self::ServiceRequest(message) => message.write_options(writer, endian, args),
+            Self::ServiceAccept(message) => message.write_options(writer, endian, args),
+            Self::KexInit(message) => message.as_ref().write_options(writer, endian, args),
+            Self::NewKeys(message) => message.write_options(writer, endian, args),
+            Self::KexSpecific(packet) => Ok(writer.write_all(&packet.payload)?),
+        }
+    }
+}
+
+impl WriteEndian for Message<'_> {
+    const ENDIAN: binrw::meta::EndianKind = binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+}
+
+owned::into_owned!(Unimplemented, NewKeys);
+owned::into_owned!(
+    Disconnect { description, language; reason },
+    Ignore { data },
+    Debug { message, language; always_display },
+    ServiceRequest { service_name },
+    ServiceAccept { service_name },
+    KexInit {
+        kex_algorithms,
+        server_host_key_algorithms,
+        encryption_algorithms_client_to_server,
+        encryption_algorithms_server_to_client,
+        mac_algorithms_client_to_server,
+        mac_algorithms_server_to_client,
+        compression_algorithms_client_to_server,
+        compression_algorithms_server_to_client,
+        languages_client_to_server,
+        languages_server_to_client;
+        cookie,
+        first_kex_packet_follows
+    },
+    KexdhInit { e },
+    KexdhReply { k_s, f, signature },
+    KexEcdhInit { q_c },
+    KexEcdhReply { k_s, q_s, signature },
+);
+
+impl IntoOwned for Message<'_> {
+    type Owned = Message<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::Disconnect(message) => Message::Disconnect(message.into_owned()),
+            Self::Ignore(message) => Message::Ignore(message.into_owned()),
+            Self::Unimplemented(message) => Message::Unimplemented(message),
+            Self::Debug(message) => Message::Debug(message.into_owned()),
+            Self::ServiceRequest(message) => Message::ServiceRequest(message.into_owned()),
+            Self::ServiceAccept(message) => Message::ServiceAccept(message.into_owned()),
+            Self::KexInit(message) => Message::KexInit(message.into_owned()),
+            Self::NewKeys(message) => Message::NewKeys(message),
+            Self::KexSpecific(packet) => Message::KexSpecific(packet.into_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+
+    /// A toy signature, valid when it equals the signed message.
+    #[cfg(all(feature = "digest", feature = "signature"))]
+    struct Echo(Vec<u8>);
+
+    #[cfg(all(feature = "digest", feature = "signature"))]
+    impl TryFrom<&[u8]> for Echo {
+        type Error = signature::Error;
+
+        fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+            Ok(Self(value.to_vec()))
+        }
+    }
+
+    #[cfg(all(feature = "digest", feature = "signature"))]
+    struct EchoKey;
+
+    #[cfg(all(feature = "digest", feature = "signature"))]
+    impl signature::Verifier<Echo> for EchoKey {
+        fn verify(&self, msg: &[u8], signature: &Echo) -> Result<(), signature::Error> {
+            if msg == signature.0 {
+                Ok(())
+            } else {
+                Err(signature::Error::new())
+            }
+        }
+    }
+
+    #[cfg(all(feature = "digest", feature = "signature"))]
+    #[test]
+    fn it_verifies_host_signatures() {
+        use crate::crypto::exchange::Ecdh;
+
+        let kexinit: KexInit = Packet {
+            payload: arch::Bytes::owned(
+                [&[20][..], &[0; 16], &[0; 4 * 10], &[0], &[0; 4]].concat(),
+            ),
+        }
+        .to()
+        .unwrap();
+        let exchange = Ecdh {
+            v_c: arch::Bytes::borrowed(b"SSH-2.0-client"),
+            v_s: arch::Bytes::borrowed(b"SSH-2.0-server"),
+            i_c: std::borrow::Cow::Borrowed(&kexinit),
+            i_s: std::borrow::Cow::Borrowed(&kexinit),
+            k_s: arch::Bytes::borrowed(b"host key"),
+            q_c: arch::Bytes::borrowed(b"client ephemeral"),
+            q_s: arch::Bytes::borrowed(b"server ephemeral"),
+            k: arch::MpInt::positive(b"secret"),
+        };
+        let hash = exchange.hash::<sha2::Sha256>();
+
+        let signature = |blob: &[u8]| {
+            let mut buf = std::io::Cursor::new(Vec::new());
+            arch::Bytes::borrowed(b"echo").write_be(&mut buf).unwrap();
+            arch::Bytes::borrowed(blob).write_be(&mut buf).unwrap();
+
+            arch::Bytes::owned(buf.into_inner())
+        };
+        let mut reply = KexEcdhReply {
+            k_s: arch::Bytes::borrowed(b"host key"),
+            q_s: arch::Bytes::borrowed(b"server ephemeral"),
+            signature: signature(&hash),
+        };
+
+        let verified = reply
+            .verify_host_signature::<sha2::Sha256, Echo, _>(&exchange, "echo", &EchoKey)
+            .unwrap();
+        assert_eq!(verified, hash);
+
+        reply.signature = signature(b"forged");
+        assert!(reply
+            .verify_host_signature::<sha2::Sha256, Echo, _>(&exchange, "echo", &EchoKey)
+            .is_err());
+
+        reply.signature = signature(&hash);
+        assert!(reply
+            .verify_host_signature::<sha2::Sha256, Echo, _>(&exchange, "ssh-rsa", &EchoKey)
+            .is_err());
+
+        reply.q_s = arch::Bytes::borrowed(b"another ephemeral");
+        assert!(reply
+            .verify_host_signature::<sha2::Sha256, Echo, _>(&exchange, "echo", &EchoKey)
+            .is_err());
+    }
+
+    #[test]
+    fn it_decodes_kex_messages_by_family() {
+        assert_eq!(
+            KexFamily::from_algorithm("diffie-hellman-group14-sha256"),
+            Some(KexFamily::Dh)
+        );
+        assert_eq!(
+            KexFamily::from_algorithm("curve25519-sha256@libssh.org"),
+            Some(KexFamily::Ecdh)
+        );
+        assert_eq!(
+            KexFamily::from_algorithm("diffie-hellman-group-exchange-sha256"),
+            None
+        );
+
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&[30, 0, 0, 0, 2, 0x7f, 1]),
+        };
+        assert_eq!(
+            KexMessage::from_packet(&packet, KexFamily::Dh).unwrap(),
+            KexMessage::KexdhInit(KexdhInit {
+                e: arch::MpInt::positive(&[0x7f, 1])
+            })
+        );
+        assert_eq!(
+            KexMessage::from_packet(&packet, KexFamily::Ecdh).unwrap(),
+            KexMessage::KexEcdhInit(KexEcdhInit {
+                q_c: arch::Bytes::borrowed(&[0x7f, 1])
+            })
+        );
+
+        let packet = Packet {
+            payload: arch::Bytes::borrowed(&[32]),
+        };
+        KexMessage::from_packet(&packet, KexFamily::Ecdh).unwrap_err();
+    }
+
+    #[rstest]
+    #[case(DisconnectReason::MacError, "SSH_DISCONNECT_MAC_ERROR (5)", false)]
+    #[case(DisconnectReason::Other(42), "unknown reason 42", false)]
+    #[case(
+        DisconnectReason::Other(0xFE000001),
+        "private use reason 0xfe000001",
+        true
+    )]
+    fn it_displays_disconnect_reasons(
+        #[case] reason: DisconnectReason,
+        #[case] display: &str,
+        #[case] private: bool,
+    ) {
+        assert_eq!(reason.to_string(), display);
+        assert_eq!(reason.is_private_use(), private);
+    }
+
+    #[test]
+    fn it_constructs_failures() {
+        crate::assert_wire_roundtrip!(
+            Disconnect::by_application("bye"),
+            "01 0000000b 00000003 627965 00000000"
+        );
+    }
+
+    #[test]
+    fn it_redacts_secrets_from_debug() {
+        let reply = KexEcdhReply {
+            k_s: arch::Bytes::borrowed(b"key"),
+            q_s: arch::Bytes::borrowed(b"ephemeral"),
+            signature: arch::Bytes::borrowed(&[0xde, 0xad]),
+        };
+        assert!(format!("{reply:?}").ends_with("signature: <redacted> }"));
+    }
+
+    #[test]
+    fn it_constructs_informational_messages() {
+        let debug = Debug::display("rekeying soon");
+        assert!(*debug.always_display);
+        assert_eq!(&*debug.message, "rekeying soon");
+        assert!(debug.language.is_empty());
+        assert!(!*Debug::hidden(String::from("verbose")).always_display);
+    }
+
+    #[test]
+    fn it_generates_random_fields() {
+        use rand_core::OsRng;
+
+        let ignore = Ignore::random_with_rng(64, &mut OsRng);
+        assert_eq!(ignore.data.len(), 64);
+
+        assert_ne!(
+            KexInit::random_cookie_with_rng(&mut OsRng),
+            KexInit::random_cookie_with_rng(&mut OsRng)
+        );
+    }
+
+    #[test]
+    fn it_generates_ignore_messages() {
+        use rand_core::OsRng;
+
+        let sizes = IgnoreGenerator::uniform(OsRng, 8..=16)
+            .take(256)
+            .map(|ignore| ignore.data.len())
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(sizes, (8..=16).collect());
+
+        let mut sizes = IgnoreGenerator::uniform(OsRng, 0..=usize::MAX).sizes;
+        sizes(&mut OsRng);
+
+        let mut sizes = [1, 2, 3].into_iter().cycle();
+        assert!(
+            IgnoreGenerator::new(OsRng, |_: &mut OsRng| sizes.next().unwrap())
+                .take(3)
+                .map(|ignore| ignore.data.len())
+                .eq([1, 2, 3])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_rejects_empty_ignore_sizes() {
+        #[allow(clippy::reversed_empty_ranges)]
+        let _ = IgnoreGenerator::uniform(rand_core::OsRng, 16..=8);
+    }
+}
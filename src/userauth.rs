@@ -1,35 +1,46 @@
 //! Messages involved in the SSH's **authentication** (`SSH-USERAUTH`) part of the protocol,
 //! as defined in the [RFC 4252](https://datatracker.ietf.org/doc/html/rfc4252) and [RFC 4256](https://datatracker.ietf.org/doc/html/rfc4256).
 
-use binrw::binrw;
+use binrw::{binrw, meta::WriteEndian, BinWrite};
 
-use crate::arch;
+use crate::{arch, message, owned, DecodeError, IntoOwned, Packet};
 
 /// The `SSH_MSG_USERAUTH_REQUEST` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4252#section-5>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "zeroize", derive(zeroize::Zeroize))]
 #[brw(big, magic = 50_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct Request<'b> {
     /// Username for the auth request.
+    #[br(args_raw(options))]
     pub username: arch::Utf8<'b>,
 
     /// Service name to query.
+    #[br(args_raw(options))]
     pub service_name: arch::Ascii<'b>,
 
     #[bw(calc = method.as_ascii())]
+    #[br(args_raw(options))]
     auth_method: arch::Ascii<'b>,
 
     /// Authentication method used.
-    #[br(args(auth_method))]
+    #[br(args(options, auth_method))]
     pub method: Method<'b>,
 }
 
 /// The authentication method in the `SSH_MSG_USERAUTH_REQUEST` message.
 #[binrw]
-#[derive(Debug, Clone)]
-#[br(import(method: arch::Ascii<'_>))]
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "zeroize", derive(zeroize::Zeroize))]
+#[br(import(options: crate::DecodeOptions, method: arch::Ascii<'_>))]
 pub enum Method<'b> {
     /// Authenticate using the `none` method,
     /// as defined in [RFC4252 section 5.2](https://datatracker.ietf.org/doc/html/rfc4252#section-5.2).
@@ -44,13 +55,15 @@ pub enum Method<'b> {
         signed: arch::Bool,
 
         /// Public key algorithm's name.
+        #[br(args_raw(options))]
         algorithm: arch::Bytes<'b>,
         /// Public key blob.
+        #[br(args_raw(options))]
         blob: arch::Bytes<'b>,
 
         /// The optional signature of the authentication packet,
         /// signed with the according private key.
-        #[br(if(*signed))]
+        #[br(if(*signed), args_raw(options))]
         signature: Option<arch::Bytes<'b>>,
     },
 
@@ -62,11 +75,12 @@ pub enum Method<'b> {
         change: arch::Bool,
 
         /// Plaintext password.
+        #[br(args_raw(options))]
         password: arch::Utf8<'b>,
 
         /// In the case of a the receival of a [`PasswdChangereq`],
         /// the new password to be set in place of the old one.
-        #[br(if(*change))]
+        #[br(if(*change), args_raw(options))]
         new: Option<arch::Utf8<'b>>,
     },
 
@@ -75,18 +89,23 @@ pub enum Method<'b> {
     #[br(pre_assert(method == Method::HOSTBASED))]
     Hostbased {
         /// Public key algorithm for the host key.
+        #[br(args_raw(options))]
         algorithm: arch::Bytes<'b>,
 
         /// Public host key and certificates for client host.
+        #[br(args_raw(options))]
         host_key: arch::Bytes<'b>,
 
         /// Client host name expressed as the FQDN.
+        #[br(args_raw(options))]
         client_fqdn: arch::Ascii<'b>,
 
         /// User name on the client host.
+        #[br(args_raw(options))]
         username: arch::Utf8<'b>,
 
         /// The signature of the authentication packet.
+        #[br(args_raw(options))]
         signature: arch::Bytes<'b>,
     },
 
@@ -95,9 +114,11 @@ pub enum Method<'b> {
     #[br(pre_assert(method == Method::KEYBOARD_INTERACTIVE))]
     KeyboardInteractive {
         /// Language tag.
+        #[br(args_raw(options))]
         language: arch::Ascii<'b>,
 
         /// A hint for the prefered interactive submethod.
+        #[br(args_raw(options))]
         submethods: arch::Utf8<'b>,
     },
 }
@@ -130,17 +151,120 @@ impl Method<'_> {
     }
 }
 
+/// Passwords and signatures are redacted, only the presence of a new password is logged.
+impl std::fmt::Debug for Method<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::Publickey {
+                algorithm,
+                blob,
+                signature,
+            } => f
+                .debug_struct("Publickey")
+                .field("algorithm", algorithm)
+                .field("blob", blob)
+                .field("signature", &signature.as_ref().map(arch::Redacted))
+                .finish(),
+            Self::Password { password, new } => f
+                .debug_struct("Password")
+                .field("password", &arch::Redacted(password))
+                .field("new", &new.as_ref().map(arch::Redacted))
+                .finish(),
+            Self::Hostbased {
+                algorithm,
+                host_key,
+                client_fqdn,
+                username,
+                signature,
+            } => f
+                .debug_struct("Hostbased")
+                .field("algorithm", algorithm)
+                .field("host_key", host_key)
+                .field("client_fqdn", client_fqdn)
+                .field("username", username)
+                .field("signature", &arch::Redacted(signature))
+                .finish(),
+            Self::KeyboardInteractive {
+                language,
+                submethods,
+            } => f
+                .debug_struct("KeyboardInteractive")
+                .field("language", language)
+                .field("submethods", submethods)
+                .finish(),
+        }
+    }
+}
+
+/// Passwords and signatures are redacted, only the presence of a new password is logged.
+#[cfg(feature = "defmt")]
+impl defmt::Format for Method<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::None => defmt::write!(f, "None"),
+            Self::Publickey {
+                algorithm,
+                blob,
+                signature,
+            } => defmt::write!(
+                f,
+                "Publickey {{ algorithm: {}, blob: {}, signature: {} }}",
+                algorithm,
+                blob,
+                signature.as_ref().map(arch::Redacted)
+            ),
+            Self::Password { password, new } => defmt::write!(
+                f,
+                "Password {{ password: {}, new: {} }}",
+                arch::Redacted(password),
+                new.as_ref().map(arch::Redacted)
+            ),
+            Self::Hostbased {
+                algorithm,
+                host_key,
+                client_fqdn,
+                username,
+                signature,
+            } => defmt::write!(
+                f,
+                "Hostbased {{ algorithm: {}, host_key: {}, client_fqdn: {}, username: {}, signature: {} }}",
+                algorithm,
+                host_key,
+                client_fqdn,
+                username,
+                arch::Redacted(signature)
+            ),
+            Self::KeyboardInteractive {
+                language,
+                submethods,
+            } => defmt::write!(
+                f,
+                "KeyboardInteractive {{ language: {}, submethods: {} }}",
+                language,
+                submethods
+            ),
+        }
+    }
+}
+
 /// The `SSH_MSG_USERAUTH_PK_OK` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4252#section-7>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 60_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct PkOk<'b> {
     /// Public key algorithm name from the request.
+    #[br(args_raw(options))]
     pub algorithm: arch::Bytes<'b>,
 
     /// Public key blob from the request.
+    #[br(args_raw(options))]
     pub blob: arch::Bytes<'b>,
 }
 
@@ -148,13 +272,19 @@ pub struct PkOk<'b> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4252#section-8>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 60_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct PasswdChangereq<'b> {
     /// Password change prompt.
+    #[br(args_raw(options))]
     pub prompt: arch::Utf8<'b>,
 
     /// Language tag (deprecated).
+    #[br(args_raw(options))]
     pub language: arch::Ascii<'b>,
 }
 
@@ -162,61 +292,135 @@ pub struct PasswdChangereq<'b> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4256#section-3.2>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 60_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct InfoRequest<'b> {
     /// Name of the challenge.
+    #[br(args_raw(options))]
     pub name: arch::Utf8<'b>,
 
     /// Instructions for the challenge.
+    #[br(args_raw(options))]
     pub instruction: arch::Utf8<'b>,
 
     /// Language tag (deprecated).
+    #[br(args_raw(options))]
     pub language: arch::Ascii<'b>,
 
     #[bw(calc = prompts.len() as u32)]
     num_prompts: u32,
 
     /// The challenge's prompts.
-    #[br(count = num_prompts)]
+    #[br(count = num_prompts, args { inner: options })]
     pub prompts: Vec<InfoRequestPrompt<'static>>,
 }
 
+// Implemented by hand, since the derived implementation can't bound the `'static` prompts.
+#[cfg(feature = "defmt")]
+impl defmt::Format for InfoRequest<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "InfoRequest {{ name: {}, instruction: {}, language: {}, prompts: {=[?]} }}",
+            self.name,
+            self.instruction,
+            self.language,
+            self.prompts.as_slice()
+        )
+    }
+}
+
 /// A prompt in the `SSH_MSG_USERAUTH_INFO_REQUEST` message.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[brw(big)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct InfoRequestPrompt<'b> {
     /// Challenge prompt text.
+    #[br(args_raw(options))]
     pub prompt: arch::Utf8<'b>,
 
     /// Whether the client should echo back typed characters.
     pub echo: arch::Bool,
 }
 
+// Implemented by hand, since the derived implementation would bound the
+// prompts to the input's lifetime, which `'static` prompts cannot satisfy.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for InfoRequestPrompt<'_> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            prompt: u.arbitrary()?,
+            echo: u.arbitrary()?,
+        })
+    }
+}
+
 /// The `SSH_MSG_USERAUTH_INFO_RESPONSE` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4256#section-3.4>.
 #[binrw]
-#[derive(Debug, Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "zeroize", derive(zeroize::Zeroize))]
 #[brw(big, magic = 61_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct InfoResponse {
     #[bw(calc = responses.len() as u32)]
     num_responses: u32,
 
     /// Responses to the provided challenge.
-    #[br(count = num_responses)]
+    #[br(count = num_responses, args { inner: options })]
     pub responses: Vec<arch::Utf8<'static>>,
 }
 
+/// Responses are redacted, only their count is logged.
+impl std::fmt::Debug for InfoResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InfoResponse")
+            .field(
+                "responses",
+                &self
+                    .responses
+                    .iter()
+                    .map(arch::Redacted)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Responses are redacted, only their count is logged.
+#[cfg(feature = "defmt")]
+impl defmt::Format for InfoResponse {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "InfoResponse {{ responses: <{=usize} redacted> }}",
+            self.responses.len()
+        )
+    }
+}
+
 /// The `SSH_MSG_USERAUTH_FAILURE` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4252#section-5.1>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 51_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct Failure<'b> {
     /// Authentications that can continue.
+    #[br(args_raw(options))]
     pub continue_with: arch::NameList<'b>,
 
     /// Partial success.
@@ -227,7 +431,10 @@ pub struct Failure<'b> {
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4252#section-5.1>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 52_u8)]
 pub struct Success;
 
@@ -235,12 +442,225 @@ pub struct Success;
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4252#section-5.4>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[brw(big, magic = 53_u8)]
+#[br(import_raw(options: crate::DecodeOptions))]
 pub struct Banner<'b> {
     /// The auth banner message.
+    #[br(args_raw(options))]
     pub message: arch::Utf8<'b>,
 
     /// Language tag.
+    #[br(args_raw(options))]
     pub language: arch::Ascii<'b>,
 }
+
+impl<'b> Banner<'b> {
+    /// Create a [`Banner`] message with an empty language tag.
+    pub fn new(message: impl Into<arch::Utf8<'b>>) -> Self {
+        Self {
+            message: message.into(),
+            language: Default::default(),
+        }
+    }
+}
+
+/// Any of the messages of the **authentication** (`SSH-USERAUTH`) part of the protocol,
+/// decoded from a [`Packet`] by dispatching on it's message number.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Message<'b> {
+    /// The `SSH_MSG_USERAUTH_REQUEST` message.
+    Request(Request<'b>),
+
+    /// The `SSH_MSG_USERAUTH_FAILURE` message.
+    Failure(Failure<'b>),
+
+    /// The `SSH_MSG_USERAUTH_SUCCESS` message.
+    Success(Success),
+
+    /// The `SSH_MSG_USERAUTH_BANNER` message.
+    Banner(Banner<'b>),
+
+    /// An authentication method specific message (`60` to `79`), such as
+    /// [`PkOk`] or [`InfoRequest`], depending on the requested method.
+    MethodSpecific(Packet<'b>),
+}
+
+impl<'b> Message<'b> {
+    /// Decode the [`Message`] in the `packet` by dispatching on it's message number,
+    /// failing if it is not a known message of this part of the protocol.
+    pub fn from_packet(packet: &'b Packet<'_>) -> Result<Self, DecodeError> {
+        Self::dispatch(packet, &Default::default())
+            .unwrap_or_else(|| Err(message::unexpected(packet)))
+    }
+
+    pub(crate) fn dispatch(
+        packet: &'b Packet<'_>,
+        options: &crate::DecodeOptions,
+    ) -> Option<Result<Self, DecodeError>> {
+        Some(match packet.payload.first()? {
+            50 => packet.to_with(options).map(Self::Request),
+            51 => packet.to_with(options).map(Self::Failure),
+            52 => packet.to_with(options).map(Self::Success),
+            53 => packet.to_with(options).map(Self::Banner),
+            60..=79 => Ok(Self::MethodSpecific(packet.as_borrow())),
+            _ => return None,
+        })
+    }
+}
+
+impl BinWrite for Message<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut W,
+        endian: binrw::Endian,
+        args: Self::Args<'_>,
+    ) -> binrw::BinResult<()> {
+        match self {
+            Self::Request(message) => message.write_options(writer, endian, args),
+            Self::Failure(message) => message.write_options(writer, endian, args),
+            Self::Success(message) => message.write_options(writer, endian, args),
+            Self::Banner(message) => message.write_options(writer, endian, args),
+            Self::MethodSpecific(packet) => Ok(writer.write_all(&packet.payload)?),
+        }
+    }
+}
+
+impl WriteEndian for Message<'_> {
+    const ENDIAN: binrw::meta::EndianKind = binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+}
+
+owned::into_owned!(InfoResponse, Success);
+owned::into_owned!(
+    Request { username, service_name, method },
+    PkOk { algorithm, blob },
+    PasswdChangereq { prompt, language },
+    InfoRequest { name, instruction, language; prompts },
+    InfoRequestPrompt { prompt; echo },
+    Failure { continue_with; partial_success },
+    Banner { message, language },
+);
+
+impl IntoOwned for Method<'_> {
+    type Owned = Method<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::None => Method::None,
+            Self::Publickey {
+                algorithm,
+                blob,
+                signature,
+            } => Method::Publickey {
+                algorithm: algorithm.into_owned(),
+                blob: blob.into_owned(),
+                signature: signature.into_owned(),
+            },
+            Self::Password { password, new } => Method::Password {
+                password: password.into_owned(),
+                new: new.into_owned(),
+            },
+            Self::Hostbased {
+                algorithm,
+                host_key,
+                client_fqdn,
+                username,
+                signature,
+            } => Method::Hostbased {
+                algorithm: algorithm.into_owned(),
+                host_key: host_key.into_owned(),
+                client_fqdn: client_fqdn.into_owned(),
+                username: username.into_owned(),
+                signature: signature.into_owned(),
+            },
+            Self::KeyboardInteractive {
+                language,
+                submethods,
+            } => Method::KeyboardInteractive {
+                language: language.into_owned(),
+                submethods: submethods.into_owned(),
+            },
+        }
+    }
+}
+
+impl IntoOwned for Message<'_> {
+    type Owned = Message<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Self::Request(message) => Message::Request(message.into_owned()),
+            Self::Failure(message) => Message::Failure(message.into_owned()),
+            Self::Success(message) => Message::Success(message),
+            Self::Banner(message) => Message::Banner(message.into_owned()),
+            Self::MethodSpecific(packet) => Message::MethodSpecific(packet.into_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+    use super::*;
+
+    #[test]
+    fn it_redacts_secrets_from_debug() {
+        let request = Request {
+            username: "user".into(),
+            service_name: arch::ascii!("ssh-connection"),
+            method: Method::Password {
+                password: "hunter2".into(),
+                new: Some("hunter3".into()),
+            },
+        };
+        let debug = format!("{request:?}");
+        assert!(debug.contains("\"user\""));
+        assert!(!debug.contains("hunter"));
+        assert!(debug.contains("new: Some(<redacted>)"));
+
+        let response = InfoResponse {
+            responses: vec!["123456".into()],
+        };
+        assert_eq!(
+            format!("{response:?}"),
+            "InfoResponse { responses: [<redacted>] }"
+        );
+    }
+
+    #[test]
+    fn it_constructs_informational_messages() {
+        let banner = Banner::new("Authorized use only.\r\n");
+        assert!(banner.language.is_empty());
+        crate::assert_wire_roundtrip!(
+            banner,
+            "35 00000016 417574686f72697a656420757365206f6e6c792e0d0a 00000000"
+        );
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn it_zeroizes_passwords() {
+        use zeroize::Zeroize;
+
+        let mut method = Method::Password {
+            password: arch::Utf8::owned("hunter2".into()),
+            new: Some(arch::Utf8::owned("hunter3".into())),
+        };
+        method.zeroize();
+
+        let Method::Password { password, new } = method else {
+            unreachable!()
+        };
+        assert!(password.is_empty());
+        assert!(new.is_none());
+    }
+}
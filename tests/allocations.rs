@@ -0,0 +1,95 @@
+//! Measure the heap allocations performed by the packet hot path,
+//! on a steady-state encrypted and uncompressed connection.
+
+#![allow(clippy::unwrap_used, clippy::unimplemented)]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use rand_core::OsRng;
+use ssh_packet::{connect, PacketReader, PacketWriter};
+
+mod common;
+use common::Xor;
+
+/// A global allocator counting the allocations made through it,
+/// per-thread so that the test harness' own allocations are not accounted.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    // The counter may already be destroyed while the thread exits
+    let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Count the allocations performed while running `f`.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+const PACKETS: usize = 1024;
+const WARMUP: usize = 16;
+
+#[test]
+fn it_allocates_at_most_once_per_packet() {
+    let message = connect::ChannelWindowAdjust {
+        recipient_channel: 0,
+        bytes_to_add: 2 * 1024 * 1024,
+    };
+
+    let mut writer = PacketWriter::new(Vec::with_capacity((PACKETS + WARMUP) * 64), Xor, OsRng);
+    for _ in 0..WARMUP {
+        writer.blocking_send(&message).unwrap();
+    }
+    let sent = allocations(|| {
+        for _ in 0..PACKETS {
+            writer.blocking_send(&message).unwrap();
+        }
+    });
+
+    let (wire, _) = writer.into_inner();
+    let mut reader = PacketReader::new(&wire[..], Xor);
+    for _ in 0..WARMUP {
+        reader.blocking_recv_packet().unwrap();
+    }
+    let received = allocations(|| {
+        for _ in 0..PACKETS {
+            reader.blocking_recv_packet().unwrap();
+        }
+    });
+
+    assert!(
+        sent <= PACKETS,
+        "{sent} allocations to send {PACKETS} packets"
+    );
+    assert!(
+        received <= PACKETS,
+        "{received} allocations to receive {PACKETS} packets"
+    );
+}
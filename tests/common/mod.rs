@@ -0,0 +1,92 @@
+//! Helpers shared by the integration tests and benchmarks.
+
+#![allow(clippy::unimplemented)]
+
+use ssh_packet::{
+    arch::{self, Ascii},
+    CipherCore, Compression, Mac, MacMode, OpeningCipher, SealingCipher,
+};
+
+/// A toy _encrypt-then-MAC_ cipher, allocation-free like the real ones.
+pub struct Xor;
+
+impl Xor {
+    fn tag(buf: &[u8], seq: u32) -> [u8; 4] {
+        buf.iter()
+            .fold(seq, |acc, byte| acc.rotate_left(5) ^ u32::from(*byte))
+            .to_be_bytes()
+    }
+}
+
+impl Mac for Xor {
+    fn algorithm(&self) -> Ascii<'static> {
+        arch::ascii!("xor-etm@example.com")
+    }
+
+    fn size(&self) -> usize {
+        4
+    }
+
+    fn mode(&self) -> MacMode {
+        MacMode::EncryptThenMac
+    }
+}
+
+impl CipherCore for Xor {
+    type Err = std::io::Error;
+    type Mac = Self;
+
+    fn algorithm(&self) -> Ascii<'static> {
+        arch::ascii!("xor@example.com")
+    }
+
+    fn mac(&self) -> &Self::Mac {
+        self
+    }
+
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn compression(&self) -> Compression {
+        Compression::None
+    }
+}
+
+impl OpeningCipher for Xor {
+    fn decrypt_payload(&mut self, buf: &mut [u8], seq: u32) -> Result<(), Self::Err> {
+        buf.iter_mut().for_each(|byte| *byte ^= seq as u8);
+
+        Ok(())
+    }
+
+    fn open<B: AsRef<[u8]>>(&mut self, buf: B, mac: &[u8], seq: u32) -> Result<(), Self::Err> {
+        if Self::tag(buf.as_ref(), seq) == mac {
+            Ok(())
+        } else {
+            Err(std::io::ErrorKind::InvalidData.into())
+        }
+    }
+
+    fn decompress(&mut self, _buf: &mut Vec<u8>) -> Result<(), Self::Err> {
+        unimplemented!()
+    }
+}
+
+impl SealingCipher for Xor {
+    fn compress(&mut self, _buf: &[u8], _output: &mut Vec<u8>) -> Result<(), Self::Err> {
+        unimplemented!()
+    }
+
+    fn encrypt_payload(&mut self, buf: &mut [u8], seq: u32) -> Result<(), Self::Err> {
+        buf.iter_mut().for_each(|byte| *byte ^= seq as u8);
+
+        Ok(())
+    }
+
+    fn seal<B: AsRef<[u8]>>(&mut self, buf: B, mac: &mut [u8], seq: u32) -> Result<(), Self::Err> {
+        mac.copy_from_slice(&Self::tag(buf.as_ref(), seq));
+
+        Ok(())
+    }
+}